@@ -217,6 +217,9 @@ pub(crate) fn init_symbols() {{
     for (sym, func) in BUILTIN_SYMBOLS[{defun_start}..].iter().zip(SUBR_DEFS.iter()) {{
         unsafe {{ sym.set_func((*func).into()).unwrap(); }}
     }}
+    for (sym, func) in crate::interpreter::SPECIAL_FORMS {{
+        unsafe {{ sym.set_func(func.into()).unwrap(); }}
+    }}
 }}
 "
     )
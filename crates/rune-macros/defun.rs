@@ -100,7 +100,8 @@ pub(crate) fn expand(function: Function, spec: Spec) -> TokenStream {
                 optional: #optional,
                 rest: #rest,
                 advice: false,
-            }
+            },
+            special_form: None,
         };
 
         #body
@@ -25,6 +25,9 @@
   "stub of function for bootstrapping"
   (cons docstring ""))
 
+(defvar jka-compr-load-suffixes nil
+  "stub of variable for bootstrapping")
+
 
 (defmacro trace (type &rest body)
   `(prog2 (message "BEGIN: %s" ,type)
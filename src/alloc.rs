@@ -2,7 +2,8 @@
 use crate::core::cons::Cons;
 use crate::core::gc::Context;
 use crate::core::object::{
-    ByteFn, ByteString, FnArgs, Gc, IntoObject, LispVec, Object, RecordBuilder, Symbol, NIL,
+    ByteFn, ByteString, FnArgs, Gc, IntoObject, LispVec, Object, ObjectType, RecordBuilder, Symbol,
+    NIL,
 };
 use anyhow::{ensure, Result};
 use rune_macros::{defun, elprop};
@@ -33,31 +34,44 @@ pub(crate) fn make_closure<'ob>(
         *cnst = *var;
     }
 
+    // The closure shares the bytecode of the prototype, so it only needs to be
+    // verified once
+    crate::bytecode::verify(prototype, cx)?;
     unsafe {
-        Ok(ByteFn::make(
+        let closure = ByteFn::make(
             prototype.codes(),
             constants.into_obj(cx).untag(),
             prototype.args,
             prototype.depth,
-        )
-        .into_obj(cx))
+            prototype.is_command().then(|| prototype.interactive()),
+            prototype.dynamic_args().unwrap_or_default(),
+        );
+        closure.set_verified();
+        Ok(closure.into_obj(cx))
     }
 }
 
 #[defun]
-#[expect(clippy::too_many_arguments)]
 pub(crate) fn make_byte_code<'ob>(
-    arglist: i64,
+    arglist: Object<'ob>,
     byte_code: &'ob ByteString,
     constants: &'ob LispVec,
     depth: usize,
-    _docstring: Option<Object>,
-    _interactive_spec: Option<Object>,
-    _elements: &[Object],
+    elements: &[Object],
     cx: &'ob Context,
 ) -> Result<&'ob ByteFn> {
+    // Not verified until it is called. The compiler creates functions whose
+    // constant vector is only filled in when they are made into closures.
     unsafe {
-        let bytefn = ByteFn::make(byte_code, constants, FnArgs::from_arg_spec(arglist)?, depth);
+        // Dynamic binding code has a lambda list instead of an integer spec
+        let (args, arglist) = match arglist.untag() {
+            ObjectType::Int(spec) => (FnArgs::from_arg_spec(spec)?, NIL),
+            _ => (FnArgs::from_arglist(arglist)?, arglist),
+        };
+        // (DOCSTRING INTERACTIVE-SPEC ...). A function with a spec slot is a
+        // command even if the spec is nil, as for `(interactive)`.
+        let interactive = elements.get(1).copied();
+        let bytefn = ByteFn::make(byte_code, constants, args, depth, interactive, arglist);
         Ok(bytefn.into_obj(cx).untag())
     }
 }
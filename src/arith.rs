@@ -1,5 +1,12 @@
 //! Arithmetic operators.
-use crate::core::object::{Gc, IntoObject, Number, NumberType, ObjectType};
+use crate::core::{
+    cons::Cons,
+    env::sym,
+    gc::Context,
+    object::{Gc, IntoObject, Number, NumberType, ObjectType},
+};
+use crate::data::LispError;
+use anyhow::Result;
 use float_cmp::ApproxEq;
 use rune_macros::defun;
 use std::cmp::PartialEq;
@@ -155,9 +162,23 @@ pub(crate) fn mul(numbers: &[Number]) -> NumberValue {
     numbers.iter().fold(NumberValue::Int(1), |acc, x| acc * x.val())
 }
 
+defsym!(ARITH_ERROR);
+
+fn arith_error(cx: &Context) -> anyhow::Error {
+    LispError::new(Cons::new1(sym::ARITH_ERROR, cx)).into()
+}
+
+/// Divide `x` by `y`, signaling `arith-error` on integer division by zero.
+fn quotient(x: NumberValue, y: NumberValue, cx: &Context) -> Result<NumberValue> {
+    match (x, y) {
+        (NumberValue::Int(_), NumberValue::Int(0)) => Err(arith_error(cx)),
+        _ => Ok(x / y),
+    }
+}
+
 #[defun(name = "/")]
-pub(crate) fn div(number: Number, divisors: &[Number]) -> NumberValue {
-    divisors.iter().fold(number.val(), |acc, x| acc / x.val())
+pub(crate) fn div(number: Number, divisors: &[Number], cx: &Context) -> Result<NumberValue> {
+    divisors.iter().try_fold(number.val(), |acc, x| quotient(acc, x.val(), cx))
 }
 
 #[defun(name = "1+")]
@@ -229,9 +250,37 @@ pub(crate) fn modulo(x: Number, y: Number) -> NumberValue {
 }
 
 #[defun(name = "%")]
-pub(crate) fn remainder(x: i64, y: i64) -> i64 {
+pub(crate) fn remainder(x: i64, y: i64, cx: &Context) -> Result<i64> {
     // TODO: Handle markers
-    x % y
+    match y {
+        0 => Err(arith_error(cx)),
+        _ => Ok(x % y),
+    }
+}
+
+/////////////////////////////////
+// Binary operators for the VM //
+/////////////////////////////////
+
+// These are used by the arithmetic opcodes. They take exactly two operands so
+// that no argument slice needs to be built, and handle the common fixnum case
+// before falling back to `NumberValue` arithmetic.
+
+#[inline]
+pub(crate) fn diff(x: Number, y: Number) -> NumberValue {
+    match (x.untag(), y.untag()) {
+        (NumberType::Int(x), NumberType::Int(y)) => NumberValue::Int(x - y),
+        _ => x.val() - y.val(),
+    }
+}
+
+#[inline]
+pub(crate) fn quo(x: Number, y: Number, cx: &Context) -> Result<NumberValue> {
+    match (x.untag(), y.untag()) {
+        (NumberType::Int(_), NumberType::Int(0)) => Err(arith_error(cx)),
+        (NumberType::Int(x), NumberType::Int(y)) => Ok(NumberValue::Int(x / y)),
+        _ => Ok(x.val() / y.val()),
+    }
 }
 
 #[expect(clippy::trivially_copy_pass_by_ref)]
@@ -285,6 +334,7 @@ mod test {
         assert_eq!(sub(Some(7.into()), &[]), NumberValue::Int(-7));
         assert_eq!(sub(Some(7.into()), &[13.into()]), NumberValue::Int(-6));
         assert_eq!(sub(Some(0.into()), &[(-1).into()]), NumberValue::Int(1));
+        assert_eq!(diff(7.into(), 13.into()), NumberValue::Int(-6));
     }
 
     #[test]
@@ -299,8 +349,25 @@ mod test {
         let roots = &RootSet::default();
         let cx = &Context::new(roots);
 
-        assert_eq!(div(cx.add_as(12.0), &[]), NumberValue::Float(12.0));
-        assert_eq!(div(12.into(), &[5.into(), 2.into()]), NumberValue::Int(1));
+        assert_eq!(div(cx.add_as(12.0), &[], cx).unwrap(), NumberValue::Float(12.0));
+        assert_eq!(div(12.into(), &[5.into(), 2.into()], cx).unwrap(), NumberValue::Int(1));
+        assert_eq!(
+            div(12.into(), &[cx.add_as(0.0)], cx).unwrap(),
+            NumberValue::Float(f64::INFINITY)
+        );
+        assert!(div(12.into(), &[0.into()], cx).is_err());
+        assert_eq!(quo((-7).into(), 2.into(), cx).unwrap(), NumberValue::Int(-3));
+        assert_eq!(quo(7.into(), cx.add_as(2.0), cx).unwrap(), NumberValue::Float(3.5));
+        assert!(quo(7.into(), 0.into(), cx).is_err());
+    }
+
+    #[test]
+    fn test_rem() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        assert_eq!(remainder(-7, 2, cx).unwrap(), -1);
+        assert_eq!(remainder(7, -2, cx).unwrap(), 1);
+        assert!(remainder(7, 0, cx).is_err());
     }
 
     #[test]
@@ -1,11 +1,13 @@
 //! Buffer operations.
 use crate::{
     core::{
-        env::{Env, INTERNED_SYMBOLS},
+        cons::Cons,
+        env::{sym, Env, INTERNED_SYMBOLS},
         error::{Type, TypeError},
         gc::{Context, Rt},
-        object::{Gc, LispBuffer, Object, ObjectType, OptionalFlag, NIL},
+        object::{Gc, LispBuffer, Object, ObjectType, OptionalFlag, Symbol, NIL},
     },
+    data::LispError,
     fns::slice_into_list,
 };
 use anyhow::{bail, Result};
@@ -29,7 +31,15 @@ pub(crate) fn set_buffer<'ob>(
     Ok(cx.add(buffer))
 }
 
-fn resolve_buffer<'ob>(buffer_or_name: Object, cx: &'ob Context) -> Result<&'ob LispBuffer> {
+#[defun]
+pub(crate) fn current_buffer<'ob>(env: &Rt<Env>, cx: &'ob Context) -> Object<'ob> {
+    cx.add(env.current_buffer.get().lisp_buffer(cx))
+}
+
+pub(crate) fn resolve_buffer<'ob>(
+    buffer_or_name: Object,
+    cx: &'ob Context,
+) -> Result<&'ob LispBuffer> {
     match buffer_or_name.untag() {
         ObjectType::Buffer(b) => Ok(b),
         ObjectType::String(name) => {
@@ -60,22 +70,22 @@ fn buffer_live_p(buffer: Object, env: &Rt<Env>) -> bool {
 #[defun]
 fn buffer_name(buffer: Option<Gc<&LispBuffer>>, env: &Rt<Env>) -> Result<String> {
     match buffer {
-        Some(buffer) => env.with_buffer(buffer.untag(), |b| b.name.to_string()),
-        None => Ok(env.current_buffer.get().name.to_string()),
+        Some(buffer) => env.with_buffer(buffer.untag(), |b| b.name()),
+        None => Ok(env.current_buffer.get().name()),
     }
 }
 
 #[defun]
 fn rename_buffer(newname: &str, unique: OptionalFlag, env: &mut Rt<Env>) -> Result<String> {
     let buf = env.current_buffer.get_mut();
-    if buf.name == newname {
+    if buf.name() == newname {
         return Ok(newname.to_string());
     }
     let mut buffer_list = BUFFERS.lock().unwrap();
     let mut replace_buffer = |buffer_list: &mut HashMap<_, _>, newname: &str| {
-        let buffer = buffer_list.remove(&buf.name).unwrap();
+        let buffer = buffer_list.remove(&buf.name()).unwrap();
         buffer_list.insert(newname.into(), buffer);
-        buf.name = newname.to_string();
+        buf.set_name(newname.to_string());
     };
     if buffer_list.contains_key(newname) {
         // there is already a buffer with newname
@@ -181,10 +191,15 @@ fn unique_buffer_name(name: &str, ignore: Option<&str>, buffer_list: &BufferMap)
 fn kill_buffer(buffer_or_name: Option<Object>, cx: &Context, env: &mut Rt<Env>) -> bool {
     match buffer_or_name {
         Some(buffer) => match resolve_buffer(buffer, cx) {
-            Ok(b) => env.with_buffer_mut(b, |b| b.kill()).unwrap_or(false),
+            Ok(b) => {
+                env.kill_buffer_locals(b);
+                env.with_buffer_mut(b, |b| b.kill()).unwrap_or(false)
+            }
             Err(_) => false,
         },
         None => {
+            let buffer = env.current_buffer.get().lisp_buffer(cx);
+            env.kill_buffer_locals(buffer);
             let killed = env.current_buffer.get_mut().kill();
             // todo, we need to select a new buffer
             env.current_buffer.release();
@@ -216,7 +231,63 @@ fn buffer_list<'ob>(_frame: OptionalFlag, cx: &'ob Context) -> Object<'ob> {
     slice_into_list(&buffer_list, None, cx)
 }
 
-// TODO: buffer local
+#[defun]
+fn buffer_local_value<'ob>(
+    variable: Symbol,
+    buffer: Gc<&LispBuffer>,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Object<'ob>> {
+    let value = match env.buffer_local_value(variable, buffer.untag()) {
+        Some(local) => local.as_ref(),
+        None => env.default_value(variable),
+    };
+    match value {
+        Some(value) => Ok(value.bind(cx)),
+        None => Err(LispError::void_variable(variable, cx).into()),
+    }
+}
+
+#[defun]
+fn buffer_local_variables<'ob>(
+    buffer: Option<Gc<&LispBuffer>>,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Object<'ob> {
+    let buffer = buffer.map_or_else(|| env.current_buffer.get().lisp_buffer(cx), |x| x.untag());
+    // void local variables are included as just the symbol
+    let locals: Vec<Object> = env
+        .buffer_local_values(buffer, cx)
+        .into_iter()
+        .map(|(var, value)| match value {
+            Some(value) => Cons::new(var, value, cx).into(),
+            None => var.into(),
+        })
+        .collect();
+    slice_into_list(&locals, None, cx)
+}
+
+/// Variables that have a separate value in every buffer. These are defined
+/// with `DEFVAR_PER_BUFFER` in GNU Emacs.
+const PER_BUFFER_VARIABLES: &[Symbol] = &[
+    sym::FILL_COLUMN,
+    sym::INDENT_TABS_MODE,
+    sym::LEFT_MARGIN,
+    sym::TAB_WIDTH,
+    sym::TRUNCATE_LINES,
+    sym::WORD_WRAP,
+    sym::BIDI_DISPLAY_REORDERING,
+    sym::BUFFER_FILE_NAME,
+    sym::BUFFER_READ_ONLY,
+    sym::DEFAULT_DIRECTORY,
+];
+
+pub(crate) fn init_buffer_locals(env: &mut Rt<Env>, cx: &Context) {
+    for var in PER_BUFFER_VARIABLES {
+        env.make_variable_buffer_local(*var, cx).unwrap();
+    }
+}
+
 defvar!(FILL_COLUMN, 70);
 defvar!(INDENT_TABS_MODE);
 defvar!(LEFT_MARGIN, 0);
@@ -268,4 +339,12 @@ mod test {
         let buffer = get_buffer_create(cx.add("test_create_buffer"), Some(NIL), cx).unwrap();
         assert!(matches!(buffer.untag(), ObjectType::Buffer(_)));
     }
+
+    #[test]
+    fn test_print_buffer() {
+        crate::interpreter::assert_lisp(
+            "(list (format \"%s\" (current-buffer)) (format \"%s\" (get-buffer-create \"test_print_buffer\")))",
+            "(\"#<buffer *scratch*>\" \"#<buffer test_print_buffer>\")",
+        );
+    }
 }
@@ -1,87 +1,81 @@
 //! The main bytecode interpeter.
 use crate::core::cons::Cons;
-use crate::core::env::{sym, CallFrame, Env};
+use crate::core::env::{sym, CallFrame, ConditionHandler, Env};
 use crate::core::gc::{Context, IntoRoot, Rt, Rto, Slot};
 use crate::core::object::{
-    ByteFn, ByteString, FnArgs, Function, FunctionType, Gc, LispVec, Object, ObjectType, Symbol,
-    WithLifetime, NIL,
+    ByteFn, ByteFnPrototype, ByteString, FnArgs, Function, FunctionType, Gc, LispVec, Object,
+    ObjectType, OptionalFlag, Symbol, WithLifetime, NIL,
 };
-use crate::data::LispError;
+use crate::data::{self, LispError};
 use crate::eval::{ErrorType, EvalError, EvalResult};
 use anyhow::{bail, Result};
-use rune_core::macros::{bail_err, rebind, root};
+use rune_core::macros::{call, rebind, root};
 use rune_macros::{defun, Trace};
 
+mod disassemble;
 mod opcode;
+#[cfg(feature = "vm_profile")]
+pub(crate) mod profile;
+mod verify;
 
-/// An program counter. This is implemented as a bound checked range pointer.
-// TODO: If the GC moves the bytecode, this will be invalid. We need to fix this
-#[derive(Clone, Debug)]
+/// A program counter. This is an offset into the bytecode of the current
+/// function rather than a pointer, so that it stays valid when the garbage
+/// collector moves the function. The bytecode is always read through the
+/// rooted function in the VM.
+#[derive(Clone, Copy, Debug, Default)]
 struct ProgramCounter {
-    /// Valid range for this instruction pointer.
-    range: std::ops::Range<*const u8>,
-    /// Points to the next instruction.
-    pc: *const u8,
+    /// Offset of the next instruction.
+    pc: usize,
 }
 
 impl ProgramCounter {
-    fn new(vec: &[u8]) -> Self {
-        ProgramCounter { range: vec.as_ptr_range(), pc: vec.as_ptr() }
+    fn with_offset(offset: usize) -> Self {
+        ProgramCounter { pc: offset }
     }
 
-    fn with_offset(vec: &[u8], offset: usize) -> Self {
-        ProgramCounter { range: vec.as_ptr_range(), pc: vec.as_ptr().map_addr(|a| a + offset) }
-    }
-
-    fn as_offset(&self) -> usize {
-        self.pc.addr() - self.range.start.addr()
+    fn as_offset(self) -> usize {
+        self.pc
     }
 
     fn goto(&mut self, offset: u16) {
-        unsafe {
-            self.pc = self.range.start.add(offset as usize);
-            debug_assert!(self.range.contains(&self.pc));
-        }
+        self.pc = offset as usize;
     }
 
     /// Take the next byte in the stream
-    fn next(&mut self) -> u8 {
-        unsafe {
-            debug_assert!(self.range.contains(&self.pc));
-            let value = *self.pc;
-            self.pc = self.pc.add(1);
-            value
-        }
+    fn next(&mut self, codes: &[u8]) -> u8 {
+        let value = codes[self.pc];
+        self.pc += 1;
+        value
     }
 
-    fn arg1(&mut self) -> u16 {
-        unsafe {
-            debug_assert!(self.range.contains(&self.pc));
-            let value = *self.pc;
-            self.pc = self.pc.add(1);
-            if cfg!(feature = "debug_bytecode") && crate::debug::debug_enabled() {
-                println!("  arg: {value}");
-            }
-            value.into()
+    fn arg1(&mut self, codes: &[u8]) -> u16 {
+        let value = codes[self.pc];
+        self.pc += 1;
+        if cfg!(feature = "debug_bytecode") && crate::debug::debug_enabled() {
+            println!("  arg: {value}");
         }
+        value.into()
     }
 
-    fn arg2(&mut self) -> u16 {
-        unsafe {
-            debug_assert!(self.range.contains(&self.pc.add(1)));
-            let value = u16::from_le(self.pc.cast::<u16>().read_unaligned());
-            self.pc = self.pc.add(2);
-            if cfg!(feature = "debug_bytecode") && crate::debug::debug_enabled() {
-                println!("  arg: {value}");
-            }
-            value
+    fn arg2(&mut self, codes: &[u8]) -> u16 {
+        let value = u16::from_le_bytes([codes[self.pc], codes[self.pc + 1]]);
+        self.pc += 2;
+        if cfg!(feature = "debug_bytecode") && crate::debug::debug_enabled() {
+            println!("  arg: {value}");
         }
+        value
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HandlerKind {
+    ConditionCase,
+    Catch,
+}
+
 #[derive(Debug, Trace)]
-/// A handler for a condition-case. These are stored in a vector in the VM and
-/// added/removed via bytecodes.
+/// A handler for a condition-case or catch. These are stored in a vector in
+/// the VM and added/removed via bytecodes.
 struct Handler<'ob> {
     #[no_trace]
     jump_code: u16,
@@ -89,6 +83,12 @@ struct Handler<'ob> {
     stack_size: usize,
     #[no_trace]
     stack_frame: usize,
+    /// Depth of the binding stack when the handler was pushed
+    #[no_trace]
+    binding_depth: usize,
+    #[no_trace]
+    kind: HandlerKind,
+    /// The conditions of a condition-case or the tag of a catch
     condition: Slot<Object<'ob>>,
 }
 
@@ -133,7 +133,7 @@ impl<'ob> RootedVM<'_, '_, '_> {
     fn varref(&mut self, idx: u16, cx: &'ob Context) -> Result<()> {
         let symbol = self.get_const(idx as usize, cx);
         if let ObjectType::Symbol(sym) = symbol.untag() {
-            let Some(var) = self.env.vars.get(sym) else { bail!("Void Variable: {sym}") };
+            let Some(var) = self.env.var(sym) else { bail!(LispError::void_variable(sym, cx)) };
             let var = var.bind(cx);
             self.env.stack.push(var);
             Ok(())
@@ -142,25 +142,96 @@ impl<'ob> RootedVM<'_, '_, '_> {
         }
     }
 
-    fn varset(&mut self, idx: usize, cx: &Context) -> Result<()> {
+    fn varset(&mut self, idx: usize, cx: &mut Context) -> Result<()> {
         let obj = self.get_const(idx, cx);
         let symbol: Symbol = obj.try_into()?;
+        root!(symbol, cx);
         let value = self.env.stack.pop(cx);
-        crate::data::set(symbol, value, self.env)?;
-        Ok(())
+        root!(value, cx);
+        data::set_variable(symbol, value, self.env, cx)
     }
 
-    fn varbind(&mut self, idx: u16, cx: &'ob Context) {
+    fn varbind(&mut self, idx: u16, cx: &mut Context) -> Result<()> {
         let value = self.env.stack.pop(cx);
+        root!(value, cx);
         let symbol = self.get_const(idx as usize, cx);
         let ObjectType::Symbol(sym) = symbol.untag() else {
             unreachable!("Varbind was not a symbol: {:?}", symbol)
         };
-        self.env.varbind(sym, value, cx);
+        root!(sym, cx);
+        data::specbind(sym, value, self.env, cx)
+    }
+
+    /// Remove `count` entries from the binding stack, running any
+    /// `unwind-protect` handlers along the way.
+    fn unbind(&mut self, count: usize, cx: &mut Context) -> Result<(), EvalError> {
+        for _ in 0..count {
+            let Some(handler) = self.env.pop_unwind_protect(cx) else {
+                data::unbind(1, self.env, cx)?;
+                continue;
+            };
+            if data::functionp(handler, cx) {
+                let func: Function = handler.try_into()?;
+                root!(func, cx);
+                call!(func; self.env, cx)?;
+            } else {
+                // old-style dynamic binding code passes the unwind forms
+                // instead of a function
+                let forms: Object = Cons::new(sym::PROGN, handler, cx).into();
+                root!(forms, cx);
+                crate::interpreter::eval(forms, None, self.env, cx)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unbind_to(&mut self, depth: usize, cx: &mut Context) -> Result<(), EvalError> {
+        let count = self.env.binding_depth() - depth;
+        self.unbind(count, cx)
+    }
+
+    fn push_handler(&mut self, kind: HandlerKind, cx: &Context) {
+        // pop before getting stack size
+        let condition = self.env.stack.pop(cx);
+        match kind {
+            HandlerKind::Catch => self.env.catch_stack.push(condition),
+            HandlerKind::ConditionCase => {
+                self.env.condition_stack.push(ConditionHandler::Case(Slot::new(condition)));
+            }
+        }
+        let handler = Handler {
+            jump_code: self.arg2(cx),
+            stack_size: self.env.stack.len(),
+            stack_frame: self.env.stack.current_frame(),
+            binding_depth: self.env.binding_depth(),
+            kind,
+            condition: Slot::new(condition),
+        };
+        self.handlers.push(handler);
+    }
+
+    fn pop_handler(&mut self) {
+        if let Some(handler) = self.handlers.last() {
+            match handler.kind {
+                HandlerKind::Catch => self.env.catch_stack.pop(),
+                HandlerKind::ConditionCase => self.env.condition_stack.pop(),
+            }
+        }
+        self.handlers.pop();
     }
 
-    fn unbind(&mut self, idx: u16, cx: &'ob Context) {
-        self.env.unbind(idx, cx);
+    fn concat(&mut self, len: usize, cx: &'ob Context) -> Result<()> {
+        let slice = Rt::bind_slice(&self.env.stack[..len], cx);
+        let string = cx.add(crate::fns::concat(slice)?);
+        // `(concat)` has no arguments to replace
+        match len.checked_sub(1) {
+            Some(extra) => {
+                self.env.stack.remove_top(extra);
+                self.env.stack.top().set(string);
+            }
+            None => self.env.stack.push(string),
+        }
+        Ok(())
     }
 
     fn get_const(&self, i: usize, cx: &'ob Context) -> Object<'ob> {
@@ -169,7 +240,22 @@ impl<'ob> RootedVM<'_, '_, '_> {
 
     fn set_current_frame(&mut self, f: &ByteFn, offset: usize) {
         self.func.set(f);
-        self.pc = ProgramCounter::with_offset(f.codes(), offset);
+        self.pc = ProgramCounter::with_offset(offset);
+    }
+
+    /// Take the next byte from the current function.
+    fn next_byte(&mut self, cx: &Context) -> u8 {
+        self.pc.next(self.func.bind(cx).codes())
+    }
+
+    /// Read a one byte operand from the current function.
+    fn arg1(&mut self, cx: &Context) -> u16 {
+        self.pc.arg1(self.func.bind(cx).codes())
+    }
+
+    /// Read a two byte operand from the current function.
+    fn arg2(&mut self, cx: &Context) -> u16 {
+        self.pc.arg2(self.func.bind(cx).codes())
     }
 
     fn unwind(&mut self, idx: usize, cx: &'ob Context) {
@@ -187,7 +273,7 @@ impl<'ob> RootedVM<'_, '_, '_> {
 
     #[inline(always)]
     fn debug_enabled() -> bool {
-        cfg!(test) || (cfg!(feature = "debug_bytecode") && crate::debug::debug_enabled())
+        (cfg!(test) || cfg!(feature = "debug_bytecode")) && crate::debug::debug_enabled()
     }
 
     /// Prepare the arguments for lisp function call. This means filling all
@@ -220,6 +306,28 @@ impl<'ob> RootedVM<'_, '_, '_> {
         Ok(())
     }
 
+    /// Bind the arguments of dynamic binding code to the variables of its
+    /// arglist, removing them from the stack. They are unbound when the
+    /// function returns.
+    fn bind_dynamic_args(&mut self, cx: &mut Context) -> Result<()> {
+        let Some(arglist) = self.func.bind(cx).dynamic_args() else { return Ok(()) };
+        let (required, optional, rest) = crate::interpreter::parse_arg_list(arglist)?;
+        let vars: Vec<_> = required.into_iter().chain(optional).chain(rest).collect();
+        root!(vars, cx);
+        let depth = self.env.binding_depth();
+        for (i, var) in vars.iter().enumerate() {
+            let value = self.env.stack.current_args()[i].bind(cx);
+            root!(value, cx);
+            if let Err(e) = data::specbind(var, value, self.env, cx) {
+                data::unbind((self.env.binding_depth() - depth) as u16, self.env, cx)?;
+                return Err(e);
+            }
+        }
+        self.env.stack.remove_top(vars.len());
+        self.env.stack.set_arg_count(0, false);
+        Ok(())
+    }
+
     fn call(&mut self, arg_cnt: u16, cx: &'ob mut Context) -> Result<(), EvalError> {
         let arg_cnt = usize::from(arg_cnt);
         let func: Function = self.env.stack[arg_cnt].bind(cx).try_into()?;
@@ -227,18 +335,32 @@ impl<'ob> RootedVM<'_, '_, '_> {
             FunctionType::Symbol(x) => x.name().to_owned(),
             _ => String::from("lambda"),
         };
+        #[cfg(feature = "vm_profile")]
+        profile::call_site(self.func.bind(cx), self.pc.as_offset());
         if let FunctionType::ByteFn(next_fn) = func.untag() {
+            verify(next_fn, cx)?;
+            #[cfg(feature = "vm_profile")]
+            profile::enter(next_fn, &name);
             // If bytecode, add another frame and resume execution.
             // OpCode::Return will remove the call frame.
+            crate::eval::check_eval_depth(self.env, cx)?;
             let len = self.env.stack.len();
             let pc_offset = self.pc.as_offset();
             let prev_fn = self.func.bind(cx);
             self.set_current_frame(next_fn, 0);
-            let frame_start = len - (arg_cnt + 1);
-            self.env
-                .stack
-                .push_bytecode_frame(frame_start, next_fn.depth, prev_fn, pc_offset);
+            let frame_start = len - arg_cnt;
+            let called = self.env.stack[arg_cnt].bind(cx);
+            let binding_depth = self.env.binding_depth();
+            self.env.stack.push_bytecode_frame(
+                frame_start,
+                frame_depth(next_fn),
+                called,
+                prev_fn,
+                pc_offset,
+                binding_depth,
+            );
             self.prepare_lisp_args(next_fn, arg_cnt, &name, cx)?;
+            self.bind_dynamic_args(cx)?;
         } else {
             // Otherwise, call the function directly.
             let mut frame = CallFrame::new_with_args(self.env, arg_cnt);
@@ -252,56 +374,95 @@ impl<'ob> RootedVM<'_, '_, '_> {
     }
 
     fn run(&mut self, cx: &'ob mut Context) -> EvalResult<'ob> {
+        let base_frame = self.env.stack.current_frame();
+        // The arguments of dynamic binding code are already bound
+        let base_depth = match self.func.bind(cx).dynamic_args() {
+            Some(_) => self.env.stack.frame_binding_depth(),
+            None => self.env.binding_depth(),
+        };
         'main: loop {
-            let err = match self.execute_bytecode(cx) {
+            let mut err = match self.execute_bytecode(cx) {
                 Ok(x) => return Ok(rebind!(x, cx)),
                 Err(e) => e,
             };
+            // An instruction that failed can't be resumed, so a value returned
+            // from the debugger is ignored here
+            if let Err(e) = crate::eval::run_signal_handlers(&mut err, self.env, cx) {
+                err = e;
+            }
 
-            // we will fix this once we can handle different error types
-            #[expect(clippy::never_loop)]
-            while let Some(handler) = self.handlers.bind_mut(cx).pop() {
-                match handler.condition.untag() {
-                    ObjectType::Symbol(sym::ERROR) => {}
-                    ObjectType::Cons(conditions) => {
-                        for condition in conditions {
-                            let condition = condition?;
-                            // TODO: Handle different error symbols
-                            if condition != sym::DEBUG && condition != sym::ERROR {
-                                bail_err!("non-error conditions {condition} not yet supported")
-                            }
-                        }
-                    }
-                    x => bail_err!("Invalid condition handler: {x}"),
-                }
-
-                let error = if let EvalError { error: ErrorType::Signal(id), .. } = err {
-                    let Some((sym, data)) = self.env.get_exception(id) else {
-                        unreachable!("Exception not found")
-                    };
-                    Cons::new(sym, data, cx)
-                } else {
-                    // TODO: Need to remove the anyhow branch once
-                    // full errors are implemented
-                    Cons::new(sym::ERROR, format!("{err}"), cx)
-                };
-                self.unwind(handler.stack_frame, cx);
-                self.env.stack.truncate(handler.stack_size);
-                self.env.stack.push(Object::from(error));
-                self.pc.goto(handler.jump_code);
+            while let Some(handler) = self.handlers.last() {
+                let kind = handler.kind;
+                let jump_code = handler.jump_code;
+                let stack_size = handler.stack_size;
+                let stack_frame = handler.stack_frame;
+                let binding_depth = handler.binding_depth;
+                let condition = handler.condition.bind(cx);
+                self.pop_handler();
+                let Some(value) = self.handler_value(kind, condition, &err, cx) else { continue };
+                root!(value, cx);
+                if let Err(e) = self.unbind_to(binding_depth, cx) {
+                    err = e;
+                    continue;
+                }
+                self.unwind(stack_frame, cx);
+                self.env.stack.truncate(stack_size);
+                self.env.stack.push(value.bind(cx));
+                self.pc.goto(jump_code);
                 continue 'main;
             }
+            // No handler in this call, so restore the state from before it
+            if let Err(e) = self.unbind_to(base_depth, cx) {
+                err = e;
+            }
+            self.unwind(base_frame, cx);
+            self.env.stack.truncate_to_args();
             return Err(err);
         }
     }
 
+    /// If `err` is handled by a handler of `kind`, return the value to push
+    /// on the stack before jumping to the handler.
+    fn handler_value<'a>(
+        &self,
+        kind: HandlerKind,
+        condition: Object<'a>,
+        err: &EvalError,
+        cx: &'a Context,
+    ) -> Option<Object<'a>> {
+        match (kind, &err.error) {
+            (HandlerKind::Catch, ErrorType::Throw(id)) => {
+                let Some((tag, data)) = self.env.get_exception(*id) else {
+                    unreachable!("Exception not found")
+                };
+                (tag.bind(cx) == condition).then(|| data.bind(cx))
+            }
+            (HandlerKind::Catch, _) => None,
+            // the handler was just popped, so its index is the length of the
+            // condition stack
+            (HandlerKind::ConditionCase, _)
+                if !err.is_visible_to(self.env.condition_stack.len()) =>
+            {
+                None
+            }
+            (HandlerKind::ConditionCase, _) => {
+                let error = err.condition(self.env, cx)?;
+                let handled = crate::eval::handles_condition(condition, error, self.env, cx);
+                handled.then_some(error.into())
+            }
+        }
+    }
+
     #[expect(clippy::too_many_lines)]
     /// The main bytecode execution loop.
     fn execute_bytecode(&mut self, cx: &'ob mut Context) -> EvalResult<'ob> {
-        use crate::{alloc, arith, data, fns};
+        use crate::{alloc, arith, buffer, casefiddle, cmds, data, editfns, fns};
         use opcode::OpCode as op;
         loop {
-            let op = match self.pc.next().try_into() {
+            let byte = self.next_byte(cx);
+            #[cfg(feature = "vm_profile")]
+            profile::dispatch(self.func.bind(cx), byte);
+            let op = match byte.try_into() {
                 Ok(x) => x,
                 Err(e) => panic!("Invalid Bytecode: {e}"),
             };
@@ -312,7 +473,7 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     println!("    {idx}: {x},");
                 }
                 println!("]");
-                let byte_offset = self.pc.pc as i64 - self.pc.range.start as i64 - 1;
+                let byte_offset = self.pc.as_offset() - 1;
                 println!("op :{byte_offset}: {op:?}");
             }
             match op {
@@ -323,19 +484,19 @@ impl<'ob> RootedVM<'_, '_, '_> {
                 op::StackRef4 => self.env.stack.push_ref(4, cx),
                 op::StackRef5 => self.env.stack.push_ref(5, cx),
                 op::StackRefN => {
-                    let idx = self.pc.arg1();
+                    let idx = self.arg1(cx);
                     self.env.stack.push_ref(idx, cx);
                 }
                 op::StackRefN2 => {
-                    let idx = self.pc.arg2();
+                    let idx = self.arg2(cx);
                     self.env.stack.push_ref(idx, cx);
                 }
                 op::StackSetN => {
-                    let idx = self.pc.arg1();
+                    let idx = self.arg1(cx);
                     self.env.stack.set_ref(idx);
                 }
                 op::StackSetN2 => {
-                    let idx = self.pc.arg2();
+                    let idx = self.arg2(cx);
                     self.env.stack.set_ref(idx);
                 }
                 op::VarRef0 => self.varref(0, cx)?,
@@ -345,11 +506,11 @@ impl<'ob> RootedVM<'_, '_, '_> {
                 op::VarRef4 => self.varref(4, cx)?,
                 op::VarRef5 => self.varref(5, cx)?,
                 op::VarRefN => {
-                    let idx = self.pc.arg1();
+                    let idx = self.arg1(cx);
                     self.varref(idx, cx)?;
                 }
                 op::VarRefN2 => {
-                    let idx = self.pc.arg2();
+                    let idx = self.arg2(cx);
                     self.varref(idx, cx)?;
                 }
                 op::VarSet0 => self.varset(0, cx)?,
@@ -359,26 +520,26 @@ impl<'ob> RootedVM<'_, '_, '_> {
                 op::VarSet4 => self.varset(4, cx)?,
                 op::VarSet5 => self.varset(5, cx)?,
                 op::VarSetN => {
-                    let idx = self.pc.arg1();
+                    let idx = self.arg1(cx);
                     self.varset(idx.into(), cx)?;
                 }
                 op::VarSetN2 => {
-                    let idx = self.pc.arg2();
+                    let idx = self.arg2(cx);
                     self.varset(idx.into(), cx)?;
                 }
-                op::VarBind0 => self.varbind(0, cx),
-                op::VarBind1 => self.varbind(1, cx),
-                op::VarBind2 => self.varbind(2, cx),
-                op::VarBind3 => self.varbind(3, cx),
-                op::VarBind4 => self.varbind(4, cx),
-                op::VarBind5 => self.varbind(5, cx),
+                op::VarBind0 => self.varbind(0, cx)?,
+                op::VarBind1 => self.varbind(1, cx)?,
+                op::VarBind2 => self.varbind(2, cx)?,
+                op::VarBind3 => self.varbind(3, cx)?,
+                op::VarBind4 => self.varbind(4, cx)?,
+                op::VarBind5 => self.varbind(5, cx)?,
                 op::VarBindN => {
-                    let idx = self.pc.arg1();
-                    self.varbind(idx, cx);
+                    let idx = self.arg1(cx);
+                    self.varbind(idx, cx)?;
                 }
                 op::VarBindN2 => {
-                    let idx = self.pc.arg2();
-                    self.varbind(idx, cx);
+                    let idx = self.arg2(cx);
+                    self.varbind(idx, cx)?;
                 }
                 op::Call0 => self.call(0, cx)?,
                 op::Call1 => self.call(1, cx)?,
@@ -387,42 +548,30 @@ impl<'ob> RootedVM<'_, '_, '_> {
                 op::Call4 => self.call(4, cx)?,
                 op::Call5 => self.call(5, cx)?,
                 op::CallN => {
-                    let idx = self.pc.arg1();
+                    let idx = self.arg1(cx);
                     self.call(idx, cx)?;
                 }
                 op::CallN2 => {
-                    let idx = self.pc.arg2();
+                    let idx = self.arg2(cx);
                     self.call(idx, cx)?;
                 }
-                op::Unbind0 => self.unbind(0, cx),
-                op::Unbind1 => self.unbind(1, cx),
-                op::Unbind2 => self.unbind(2, cx),
-                op::Unbind3 => self.unbind(3, cx),
-                op::Unbind4 => self.unbind(4, cx),
-                op::Unbind5 => self.unbind(5, cx),
+                op::Unbind0 => self.unbind(0, cx)?,
+                op::Unbind1 => self.unbind(1, cx)?,
+                op::Unbind2 => self.unbind(2, cx)?,
+                op::Unbind3 => self.unbind(3, cx)?,
+                op::Unbind4 => self.unbind(4, cx)?,
+                op::Unbind5 => self.unbind(5, cx)?,
                 op::UnbindN => {
-                    let idx = self.pc.arg1();
-                    self.unbind(idx, cx);
+                    let idx = self.arg1(cx);
+                    self.unbind(idx.into(), cx)?;
                 }
                 op::UnbindN2 => {
-                    let idx = self.pc.arg2();
-                    self.unbind(idx, cx);
-                }
-                op::PopHandler => {
-                    self.handlers.pop();
-                }
-                op::PushCondtionCase => {
-                    // pop before getting stack size
-                    let condition = self.env.stack.pop(cx);
-                    let handler = Handler {
-                        jump_code: self.pc.arg2(),
-                        stack_size: self.env.stack.len(),
-                        stack_frame: self.env.stack.current_frame(),
-                        condition: Slot::new(condition),
-                    };
-                    self.handlers.push(handler);
+                    let idx = self.arg2(cx);
+                    self.unbind(idx.into(), cx)?;
                 }
-                op::PushCatch => todo!("PushCatch bytecode"),
+                op::PopHandler => self.pop_handler(),
+                op::PushCondtionCase => self.push_handler(HandlerKind::ConditionCase, cx),
+                op::PushCatch => self.push_handler(HandlerKind::Catch, cx),
                 op::Nth => {
                     let list = self.env.stack.pop(cx);
                     let top = self.env.stack.top();
@@ -506,7 +655,7 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     let newlet = self.env.stack.pop(cx);
                     let idx = self.env.stack.pop(cx);
                     let top = self.env.stack.top();
-                    top.set(data::aset(top.bind(cx), idx.try_into()?, newlet)?);
+                    top.set(data::aset(top.bind(cx), idx.try_into()?, newlet, cx)?);
                 }
                 op::SymbolValue => {
                     let top = self.env.stack.top().bind_as(cx)?;
@@ -519,9 +668,11 @@ impl<'ob> RootedVM<'_, '_, '_> {
                 }
                 op::Set => {
                     let newlet = self.env.stack.pop(cx);
-                    let top = self.env.stack.top().bind_as(cx)?;
-                    let value = data::set(top, newlet, self.env)?;
-                    self.env.stack.top().set(value);
+                    root!(newlet, cx);
+                    let place: Symbol = self.env.stack.top().bind_as(cx)?;
+                    root!(place, cx);
+                    data::set_variable(place, newlet, self.env, cx)?;
+                    self.env.stack.top().set(newlet.bind(cx));
                 }
                 op::Fset => {
                     let def = self.env.stack.pop(cx);
@@ -534,10 +685,17 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     let value = data::get(top, prop, self.env, cx);
                     self.env.stack.top().set(value);
                 }
-                op::Substring => todo!("Substring bytecode"),
-                op::Concat2 => todo!("Concat2 bytecode"),
-                op::Concat3 => todo!("Concat3 bytecode"),
-                op::Concat4 => todo!("Concat4 bytecode"),
+                op::Substring => {
+                    let to = self.env.stack.pop(cx);
+                    let from = self.env.stack.pop(cx);
+                    let top = self.env.stack.top();
+                    let (from, to) = (Gc::try_from_option(from)?, Gc::try_from_option(to)?);
+                    let string = fns::substring(top.bind_as(cx)?, from, to)?;
+                    top.set(cx.add(string));
+                }
+                op::Concat2 => self.concat(2, cx)?,
+                op::Concat3 => self.concat(3, cx)?,
+                op::Concat4 => self.concat(4, cx)?,
                 op::Sub1 => {
                     let top = self.env.stack.top();
                     top.set(cx.add(arith::sub_one(top.bind_as(cx)?)));
@@ -571,7 +729,11 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     let top = self.env.stack.top();
                     top.set(arith::greater_than_or_eq(top.bind_as(cx)?, v1));
                 }
-                op::Diff => todo!("Diff bytecode"),
+                op::Diff => {
+                    let rhs = self.env.stack.pop(cx);
+                    let top = self.env.stack.top();
+                    top.set(cx.add(arith::diff(top.bind_as(cx)?, rhs.try_into()?)));
+                }
                 op::Negate => {
                     let top = self.env.stack.top();
                     top.set(cx.add(arith::sub(top.bind_as(cx)?, &[])));
@@ -600,59 +762,113 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     let args = &[top.bind_as(cx)?, arg1.try_into()?];
                     top.set(cx.add(arith::mul(args)));
                 }
-                op::Point => todo!("Point bytecode"),
-                op::GotoChar => todo!("GotoChar bytecode"),
+                op::Point => {
+                    let point = cx.add(editfns::point(self.env));
+                    self.env.stack.push(point);
+                }
+                op::GotoChar => {
+                    let top = self.env.stack.top().bind_as(cx)?;
+                    let point = cx.add(editfns::goto_char(top, self.env));
+                    self.env.stack.top().set(point);
+                }
                 op::Insert => todo!("Insert bytecode"),
-                op::PointMax => todo!("PointMax bytecode"),
-                op::PointMin => todo!("PointMin bytecode"),
-                op::CharAfter => todo!("CharAfter bytecode"),
-                op::FollowingChar => todo!("FollowingChar bytecode"),
-                op::PrecedingChar => todo!("PrecedingChar bytecode"),
+                op::PointMax => {
+                    let point = cx.add(editfns::point_max(self.env));
+                    self.env.stack.push(point);
+                }
+                op::PointMin => {
+                    let point = cx.add(editfns::point_min());
+                    self.env.stack.push(point);
+                }
+                op::CharAfter => {
+                    let top = self.env.stack.top().bind_as(cx)?;
+                    let chr = cx.add(editfns::char_after(top, self.env));
+                    self.env.stack.top().set(chr);
+                }
+                op::FollowingChar => {
+                    let chr = cx.add(editfns::following_char(self.env));
+                    self.env.stack.push(chr);
+                }
+                op::PrecedingChar => {
+                    let chr = cx.add(editfns::preceding_char(self.env));
+                    self.env.stack.push(chr);
+                }
                 op::CurrentColumn => todo!("CurrentColumn bytecode"),
                 op::IndentTo => todo!("IndentTo bytecode"),
-                op::EndOfLineP => todo!("EndOfLineP bytecode"),
-                op::EndOfBufferP => todo!("EndOfBufferP bytecode"),
-                op::BeginningOfLineP => todo!("BeginningOfLineP bytecode"),
-                op::BeginningOfBufferP => todo!("BeginningOfBufferP bytecode"),
-                op::CurrentBuffer => todo!("CurrentBuffer bytecode"),
-                op::SetBuffer => todo!("SetBuffer bytecode"),
-                op::SaveCurrentBuffer1 => todo!("SaveCurrentBuffer1 bytecode"),
-                op::ForwardChar => todo!("ForwardChar bytecode"),
+                op::EndOfLineP => {
+                    let eolp = editfns::eolp(self.env);
+                    self.env.stack.push(Object::from(eolp));
+                }
+                op::EndOfBufferP => {
+                    let eobp = editfns::eobp(self.env);
+                    self.env.stack.push(Object::from(eobp));
+                }
+                op::BeginningOfLineP => {
+                    let bolp = editfns::bolp(self.env);
+                    self.env.stack.push(Object::from(bolp));
+                }
+                op::BeginningOfBufferP => {
+                    let bobp = editfns::bobp(self.env);
+                    self.env.stack.push(Object::from(bobp));
+                }
+                op::CurrentBuffer => {
+                    let buffer = buffer::current_buffer(self.env, cx);
+                    self.env.stack.push(buffer);
+                }
+                op::SetBuffer => {
+                    let top = self.env.stack.top().bind(cx);
+                    let buffer = buffer::set_buffer(top, self.env, cx)?;
+                    self.env.stack.top().set(buffer);
+                }
+                op::SaveCurrentBuffer1 => self.env.record_current_buffer(cx),
+                op::ForwardChar => {
+                    let top = self.env.stack.top().bind(cx);
+                    cmds::forward_char(Gc::try_from_option(top)?, self.env, cx)?;
+                    self.env.stack.top().set(NIL);
+                }
                 op::ForwardWord => todo!("ForwardWord bytecode"),
                 op::SkipCharsForward => todo!("SkipCharsForward bytecode"),
                 op::SkipCharsBackward => todo!("SkipCharsBackward bytecode"),
-                op::ForwardLine => todo!("ForwardLine bytecode"),
+                op::ForwardLine => {
+                    let top = self.env.stack.top().bind(cx);
+                    let shortage = cmds::forward_line(Gc::try_from_option(top)?, self.env);
+                    self.env.stack.top().set(cx.add(shortage));
+                }
                 op::CharSyntax => todo!("CharSyntax bytecode"),
                 op::BufferSubstring => todo!("BufferSubstring bytecode"),
                 op::DeleteRegion => todo!("DeleteRegion bytecode"),
                 op::NarrowToRegion => todo!("NarrowToRegion bytecode"),
                 op::Widen => todo!("Widen bytecode"),
-                op::EndOfLine => todo!("EndOfLine bytecode"),
+                op::EndOfLine => {
+                    let top = self.env.stack.top().bind(cx);
+                    cmds::end_of_line(Gc::try_from_option(top)?, self.env);
+                    self.env.stack.top().set(NIL);
+                }
                 op::ConstantN2 => {
-                    let idx = self.pc.arg2();
+                    let idx = self.arg2(cx);
                     let cnst = self.get_const(idx.into(), cx);
                     self.env.stack.push(cnst);
                 }
                 op::Goto => {
-                    let offset = self.pc.arg2();
+                    let offset = self.arg2(cx);
                     self.pc.goto(offset);
                 }
                 op::GotoIfNil => {
                     let cond = self.env.stack.pop(cx);
-                    let offset = self.pc.arg2();
+                    let offset = self.arg2(cx);
                     if cond.is_nil() {
                         self.pc.goto(offset);
                     }
                 }
                 op::GotoIfNonNil => {
                     let cond = self.env.stack.pop(cx);
-                    let offset = self.pc.arg2();
+                    let offset = self.arg2(cx);
                     if !cond.is_nil() {
                         self.pc.goto(offset);
                     }
                 }
                 op::GotoIfNilElsePop => {
-                    let offset = self.pc.arg2();
+                    let offset = self.arg2(cx);
                     if self.env.stack[0].bind(cx).is_nil() {
                         self.pc.goto(offset);
                     } else {
@@ -660,7 +876,7 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     }
                 }
                 op::GotoIfNonNilElsePop => {
-                    let offset = self.pc.arg2();
+                    let offset = self.arg2(cx);
                     if self.env.stack[0].bind(cx).is_nil() {
                         self.env.stack.pop(cx);
                     } else {
@@ -668,11 +884,15 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     }
                 }
                 op::Return => {
+                    if self.func.bind(cx).dynamic_args().is_some() {
+                        self.unbind_to(self.env.stack.frame_binding_depth(), cx)?;
+                    }
                     if let Some((f, offset)) = self.env.stack.prev_bytecode_frame() {
                         self.set_current_frame(f.bind(cx), offset);
                         let top = self.env.stack.top().bind(cx);
                         self.env.stack.pop_frame();
-                        self.env.stack.push(top);
+                        // replace the function with its return value
+                        self.env.stack.top().set(top);
                     } else {
                         let top = self.env.stack.pop(cx);
                         return Ok(top);
@@ -682,7 +902,7 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     self.env.stack.pop(cx);
                 }
                 op::DiscardN => {
-                    let arg = self.pc.arg1();
+                    let arg = self.arg1(cx);
                     let cur_len = self.env.stack.len();
                     let keep_tos = (arg & 0x80) != 0;
                     let count = (arg & 0x7F) as usize;
@@ -698,16 +918,33 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     let top = self.env.stack[0].bind(cx);
                     self.env.stack.push(top);
                 }
-                op::SaveExcursion => todo!("SaveExcursion bytecode"),
-                op::SaveRestriction => todo!("SaveRestriction bytecode"),
-                op::UnwindProtect => todo!("UnwindProtect bytecode"),
+                op::SaveExcursion => self.env.record_excursion(cx),
+                op::SaveRestriction => self.env.record_restriction(),
+                op::UnwindProtect => {
+                    let handler = self.env.stack.pop(cx);
+                    self.env.record_unwind_protect(handler);
+                }
                 op::SetMarker => todo!("SetMarker bytecode"),
                 op::MatchBeginning => todo!("MatchBeginning bytecode"),
                 op::MatchEnd => todo!("MatchEnd bytecode"),
-                op::Upcase => todo!("Upcase bytecode"),
-                op::Downcase => todo!("Downcase bytecode"),
-                op::StringEqlSign => todo!("StringEqlSign bytecode"),
-                op::StringLessThan => todo!("StringLessThan bytecode"),
+                op::Upcase => {
+                    let top = self.env.stack.top();
+                    top.set(casefiddle::upcase(top.bind_as(cx)?, cx));
+                }
+                op::Downcase => {
+                    let top = self.env.stack.top();
+                    top.set(casefiddle::downcase(top.bind_as(cx)?, cx));
+                }
+                op::StringEqlSign => {
+                    let s2 = self.env.stack.pop(cx);
+                    let top = self.env.stack.top();
+                    top.set(fns::string_equal(top.bind(cx), s2)?);
+                }
+                op::StringLessThan => {
+                    let s2 = self.env.stack.pop(cx);
+                    let top = self.env.stack.top();
+                    top.set(fns::string_lessp(top.bind_as(cx)?, s2.try_into()?)?);
+                }
                 op::Equal => {
                     let rhs = self.env.stack.pop(cx);
                     let top = self.env.stack.top();
@@ -760,8 +997,16 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     let top = self.env.stack.top();
                     top.set(fns::nconc(&[top.bind_as(cx)?, list2.try_into()?])?);
                 }
-                op::Quo => todo!("Quo bytecode"),
-                op::Rem => todo!("Rem bytecode"),
+                op::Quo => {
+                    let rhs = self.env.stack.pop(cx);
+                    let top = self.env.stack.top();
+                    top.set(cx.add(arith::quo(top.bind_as(cx)?, rhs.try_into()?, cx)?));
+                }
+                op::Rem => {
+                    let rhs = self.env.stack.pop(cx);
+                    let top = self.env.stack.top();
+                    top.set(arith::remainder(top.bind_as(cx)?, rhs.try_into()?, cx)?);
+                }
                 op::Numberp => {
                     let top = self.env.stack.top();
                     top.set(data::numberp(top.bind(cx)));
@@ -771,14 +1016,17 @@ impl<'ob> RootedVM<'_, '_, '_> {
                     top.set(data::integerp(top.bind(cx)));
                 }
                 op::ListN => {
-                    let size = self.pc.arg1() as usize;
+                    let size = self.arg1(cx) as usize;
                     let slice = Rt::bind_slice(&self.env.stack[..size], cx);
                     let list = alloc::list(slice, cx);
                     let len = self.env.stack.len();
                     self.env.stack.truncate(len - (size - 1));
                     self.env.stack.top().set(list);
                 }
-                op::ConcatN => todo!("ConcatN bytecode"),
+                op::ConcatN => {
+                    let size = self.arg1(cx) as usize;
+                    self.concat(size, cx)?;
+                }
                 op::InsertN => todo!("InsertN bytecode"),
                 op::Switch => {
                     let ObjectType::HashTable(table) = self.env.stack.pop(cx).untag() else {
@@ -874,12 +1122,10 @@ fn byte_code<'ob>(
     cx: &'ob mut Context,
 ) -> Result<Object<'ob>> {
     let fun = crate::alloc::make_byte_code(
-        0,
+        0.into(),
         bytestr.untag(cx),
         vector.untag(cx),
         maxdepth,
-        None,
-        None,
         &[],
         cx,
     )?;
@@ -902,6 +1148,98 @@ fn num_of_fill_args(spec: FnArgs, args: u16, name: &str, cx: &Context) -> Result
     Ok(total.saturating_sub(args))
 }
 
+/// The stack space needed by a call to `func`. Dynamic binding code needs room
+/// for its arguments until they are bound.
+fn frame_depth(func: &ByteFn) -> usize {
+    let args = func.args;
+    match func.dynamic_args() {
+        Some(_) => {
+            func.depth
+                + usize::from(args.required)
+                + usize::from(args.optional)
+                + usize::from(args.rest)
+        }
+        None => func.depth,
+    }
+}
+
+defsym!(INVALID_BYTE_CODE);
+
+/// Whether every instruction in `func` can be executed by the VM.
+pub(crate) fn is_executable(func: &ByteFn) -> bool {
+    let Ok(code) = disassemble::decode(func.codes()) else { return false };
+    code.iter().all(|insn| insn.op.is_implemented())
+}
+
+/// Verify the bytecode of `func` unless that has already been done, signaling
+/// `invalid-byte-code` if it is malformed.
+pub(crate) fn verify(func: &ByteFnPrototype, cx: &Context) -> Result<()> {
+    if func.is_verified() {
+        return Ok(());
+    }
+    if let Err(e) = verify::check(func) {
+        let error = Cons::new(sym::INVALID_BYTE_CODE, Cons::new1(format!("{e}"), cx), cx);
+        bail!(LispError::new(error));
+    }
+    func.set_verified();
+    Ok(())
+}
+
+/// Return a listing of the byte-code of OBJECT, which is a byte-compiled
+/// function or a symbol whose function definition is one. If BUFFER is
+/// non-nil insert the listing there and return nil instead.
+#[defun]
+pub(crate) fn disassemble<'ob>(
+    object: Object<'ob>,
+    buffer: Option<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Object<'ob>> {
+    let (name, func) = match object.untag() {
+        ObjectType::Symbol(sym) => match sym.follow_indirect(cx) {
+            Some(func) => (Some(sym.name().to_owned()), func.into()),
+            None => bail!("Void function: {sym}"),
+        },
+        _ => (None, object),
+    };
+    let ObjectType::ByteFn(func) = func.untag() else {
+        bail!("Not a byte-compiled function: {object}")
+    };
+    let listing = disassemble::disassemble(func, name.as_deref())?;
+    match buffer {
+        Some(buffer) => {
+            let buffer = crate::buffer::resolve_buffer(buffer, cx)?;
+            let listing = cx.add(listing);
+            env.with_buffer_mut(buffer, |buffer| buffer.insert(listing))??;
+            Ok(NIL)
+        }
+        None => Ok(cx.add(listing)),
+    }
+}
+
+defsym!(OPCODES);
+defsym!(FUNCTIONS);
+defsym!(CALL_SITES);
+
+/// Return the profile collected by the bytecode VM. This is only available
+/// when built with the `vm_profile` feature. The result has the form
+///
+/// ((opcodes (NAME COUNT NANOSECONDS) ...)
+///  (functions (NAME CALLS OPS NANOSECONDS) ...)
+///  (call-sites (CALLER OFFSET COUNT) ...))
+///
+/// If RESET is non-nil, clear the profile after reporting it.
+#[defun]
+fn vm_profile_report<'ob>(reset: OptionalFlag, cx: &'ob Context) -> Result<Object<'ob>> {
+    #[cfg(feature = "vm_profile")]
+    return Ok(profile::report(reset.is_some(), cx));
+    #[cfg(not(feature = "vm_profile"))]
+    {
+        let _ = (reset, cx);
+        bail!("VM profiling is not enabled, rebuild with the `vm_profile` feature")
+    }
+}
+
 #[defun]
 fn fetch_bytecode(_object: Object) {
     // TODO: Implement
@@ -914,17 +1252,24 @@ pub(crate) fn call<'ob>(
     frame: &mut CallFrame,
     cx: &'ob mut Context,
 ) -> EvalResult<'ob> {
-    frame.stack.set_depth(func.bind(cx).depth);
     let func = func.bind(cx);
+    verify(func, cx)?;
+    #[cfg(feature = "vm_profile")]
+    profile::enter(func, name);
+    frame.stack.set_depth(frame_depth(func));
     let vm = VM {
-        pc: ProgramCounter::new(func.codes()),
+        pc: ProgramCounter::default(),
         func: Slot::new(func),
         env: frame,
         handlers: Vec::new(),
     };
     root!(vm, cx);
     vm.prepare_lisp_args(func, arg_cnt, name, cx)?;
-    vm.run(cx).map_err(|e| e.add_trace(name, vm.env.stack.current_args()))
+    vm.bind_dynamic_args(cx)?;
+    let result = vm.run(cx).map_err(|e| e.add_trace(name, vm.env.stack.current_args()));
+    #[cfg(feature = "vm_profile")]
+    profile::stop();
+    result
 }
 
 #[cfg(test)]
@@ -933,6 +1278,7 @@ mod test {
         gc::RootSet,
         object::{HashTable, IntoObject},
     };
+    use proptest::prelude::*;
     use rune_core::macros::{list, rebind, root};
 
     use super::{opcode::OpCode, *};
@@ -959,12 +1305,10 @@ mod test {
         // TODO: we should probably caculate the actual depth
         let depth = 10;
         let bytecode = crate::alloc::make_byte_code(
-            $arglist,
+            ($arglist).into(),
             &opcodes,
             constants,
             depth,
-            None,
-            None,
             &[],
             cx1
         ).unwrap();
@@ -1004,6 +1348,7 @@ mod test {
         cx: &mut Context,
     ) {
         root!(env, new(Env), cx);
+        crate::data::init_errors(env, cx);
         let frame = &mut CallFrame::new(env);
         frame.push_arg_slice(Rt::bind_slice(args, cx));
         frame.finalize_arguments();
@@ -1012,6 +1357,81 @@ mod test {
         assert_eq!(val, expect);
     }
 
+    /// Check that an inline opcode gives the same result as calling `func`
+    /// with the constants of `inline` as arguments.
+    fn check_against_funcall(inline: &Rto<&ByteFn>, func: Symbol, cx: &mut Context) {
+        let funcall = funcall_bytecode(inline, func, cx);
+        root!(funcall, cx);
+        root!(env, new(Env), cx);
+        let mut run = |func: &Rto<&ByteFn>| {
+            let frame = &mut CallFrame::new(env);
+            frame.finalize_arguments();
+            call(func, 0, "test", frame, cx).map(|x| x.to_string()).map_err(|_| ())
+        };
+        let expect = run(funcall);
+        let actual = run(inline);
+        assert_eq!(actual, expect);
+    }
+
+    /// Build a function that calls `func` with the constants of `inline` as
+    /// arguments.
+    fn funcall_bytecode<'ob>(inline: &Rto<&ByteFn>, func: Symbol, cx: &'ob Context) -> &'ob ByteFn {
+        use OpCode::*;
+        let inline = inline.bind(cx);
+        let mut consts: Vec<Object> = vec![func.into()];
+        consts.extend(inline.consts());
+        let argc = inline.consts().len() as u8;
+        let mut opcodes: Vec<u8> = (0..=argc).map(|i| Constant0 as u8 + i).collect();
+        opcodes.extend([Call0 as u8 + argc, Return as u8]);
+        let opcodes = opcodes.into_obj(cx).untag();
+        let consts = consts.into_obj(cx).untag();
+        crate::alloc::make_byte_code(0.into(), opcodes, consts, 10, &[], cx).unwrap()
+    }
+
+    /// Run `bytecode` and the interpreted `form` against a buffer containing
+    /// `text` with point at `point`, and check that they agree on both the
+    /// result and the final position of point.
+    fn check_buffer_op(
+        bytecode: &Rto<&ByteFn>,
+        form: &str,
+        text: &str,
+        point: usize,
+        cx: &mut Context,
+    ) {
+        fn reset(env: &mut Rt<Env>, text: &str, point: usize) {
+            let buffer = env.current_buffer.get_mut();
+            buffer.text = text_buffer::Buffer::default();
+            buffer.text.insert(text);
+            buffer.text.set_cursor(point);
+        }
+        root!(env, new(Env), cx);
+        // Errors are recorded as nil so that both paths can be compared with `eq`
+        root!(results, new(Vec<Slot<Object>>), cx);
+
+        reset(env, text, point);
+        let byte_ok = {
+            let frame = &mut CallFrame::new(env);
+            frame.finalize_arguments();
+            let result = call(bytecode, 0, "test", frame, cx);
+            let ok = result.is_ok();
+            results.push(result.unwrap_or_default());
+            ok
+        };
+        let byte_point = env.current_buffer.get().text.cursor().chars();
+
+        reset(env, text, point);
+        let obj = crate::reader::read(form, cx).unwrap().0;
+        root!(obj, cx);
+        let result = crate::interpreter::eval_toplevel(obj, env, cx);
+        let eval_ok = result.is_ok();
+        results.push(result.unwrap_or_default());
+        let eval_point = env.current_buffer.get().text.cursor().chars();
+
+        assert_eq!(byte_ok, eval_ok);
+        assert_eq!(results[0].bind(cx), results[1].bind(cx));
+        assert_eq!(byte_point, eval_point);
+    }
+
     #[test]
     fn test_basic() {
         use OpCode::*;
@@ -1220,6 +1640,28 @@ mod test {
         );
         check_bytecode!(bytecode, [3], 7, cx);
         check_bytecode!(bytecode, [sym::FLOOR], "floor", cx);
+
+        // (lambda () (condition-case nil (floor) (t 7)))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                O::Constant0,
+                O::PushCondtionCase,
+                0x08,
+                0x0,
+                O::Constant1,
+                O::Call0,
+                O::PopHandler,
+                O::Return,
+                O::Discard,
+                O::Constant2,
+                O::Return
+            ],
+            [sym::TRUE, sym::FLOOR, 7],
+            cx
+        );
+        check_bytecode!(bytecode, [], 7, cx);
     }
 
     #[test]
@@ -1262,4 +1704,679 @@ mod test {
         root!(inner, cx);
         check_bytecode!(outer, [inner], 7, cx);
     }
+
+    #[test]
+    fn test_error_restores_stack() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+
+        // (lambda (x) (car x)), which leaves x on the stack when it fails
+        make_bytecode!(bytecode, 257, [Duplicate, Car, Return], [], cx);
+        let func: Function = cx.add(bytecode.bind(cx)).try_into().unwrap();
+        root!(func, cx);
+        root!(env, new(Env), cx);
+        // The arguments are used in the backtrace after the call fails
+        let err = rune_core::macros::call!(func, 1; env, cx).unwrap_err();
+        assert!(err.to_string().contains("expected List"), "{err}");
+    }
+
+    #[test]
+    fn test_gc_during_call() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+
+        // (lambda () (garbage-collect) 10)
+        make_bytecode!(
+            inner,
+            0,
+            [Constant0, Call0, Discard, Constant1, Return],
+            [sym::GARBAGE_COLLECT, 10],
+            cx
+        );
+        // (lambda (x) (garbage-collect) (+ (funcall x) 5))
+        make_bytecode!(
+            outer,
+            257,
+            [
+                Constant0, Call0, Discard, Duplicate, Call0, Constant1, Plus, Goto, 0x0A, 0x00,
+                Return
+            ],
+            [sym::GARBAGE_COLLECT, 5],
+            cx
+        );
+        let inner = cx.add(inner.bind(cx));
+        root!(inner, cx);
+        let before = std::ptr::from_ref(outer.bind(cx));
+        check_bytecode!(outer, [inner], 15, cx);
+        // make sure the collection actually relocated the function
+        assert_ne!(before, std::ptr::from_ref(outer.bind(cx)));
+    }
+
+    #[test]
+    fn test_call_uses_full_depth() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+
+        fn make<'ob>(
+            args: i64,
+            codes: &[OpCode],
+            consts: Vec<Object>,
+            depth: usize,
+            cx: &'ob Context,
+        ) -> &'ob ByteFn {
+            let codes: Vec<u8> = codes.iter().map(|&x| x as u8).collect();
+            let codes = codes.into_obj(cx).untag();
+            let consts = consts.into_obj(cx).untag();
+            crate::alloc::make_byte_code(args.into(), codes, consts, depth, &[], cx).unwrap()
+        }
+        // (lambda (x) (list x x)), which needs all of its stack
+        let inner: Object = make(257, &[Duplicate, Duplicate, List2, Return], vec![], 3, cx).into();
+        root!(inner, cx);
+        // (lambda (f) (funcall f 1))
+        let outer = make(257, &[Duplicate, Constant0, Call1, Return], vec![1.into()], 3, cx);
+        root!(outer, cx);
+        let list = list![1, 1; cx];
+        root!(list, cx);
+        check_bytecode!(outer, [inner], list, cx);
+    }
+
+    #[test]
+    fn test_buffer_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        let text = "foo\nbar\n\nbaz";
+
+        make_bytecode!(bytecode, 0, [Point, Return], [], cx);
+        for point in [0, 5, 13] {
+            check_buffer_op(bytecode, "(point)", text, point, cx);
+        }
+        make_bytecode!(bytecode, 0, [PointMin, Return], [], cx);
+        check_buffer_op(bytecode, "(point-min)", text, 4, cx);
+        make_bytecode!(bytecode, 0, [PointMax, Return], [], cx);
+        check_buffer_op(bytecode, "(point-max)", text, 4, cx);
+        check_buffer_op(bytecode, "(point-max)", "", 0, cx);
+
+        for pos in [0, 1, 4, 13, 14, 20] {
+            make_bytecode!(bytecode, 0, [Constant0, GotoChar, Return], [pos], cx);
+            check_buffer_op(bytecode, &format!("(goto-char {pos})"), text, 2, cx);
+            make_bytecode!(bytecode, 0, [Constant0, CharAfter, Return], [pos], cx);
+            check_buffer_op(bytecode, &format!("(char-after {pos})"), text, 2, cx);
+        }
+        make_bytecode!(bytecode, 0, [Constant0, CharAfter, Return], [false], cx);
+        check_buffer_op(bytecode, "(char-after nil)", text, 2, cx);
+
+        let point_ops = [
+            (FollowingChar, "(following-char)"),
+            (PrecedingChar, "(preceding-char)"),
+            (BeginningOfLineP, "(bolp)"),
+            (EndOfLineP, "(eolp)"),
+            (BeginningOfBufferP, "(bobp)"),
+            (EndOfBufferP, "(eobp)"),
+        ];
+        for (op, form) in point_ops {
+            make_bytecode!(bytecode, 0, [op, Return], [], cx);
+            for point in [0, 2, 3, 4, 8, 13] {
+                check_buffer_op(bytecode, form, text, point, cx);
+            }
+        }
+
+        for n in [-20, -2, -1, 0, 1, 2, 3, 20] {
+            make_bytecode!(bytecode, 0, [Constant0, ForwardChar, Return], [n], cx);
+            check_buffer_op(bytecode, &format!("(forward-char {n})"), text, 5, cx);
+            make_bytecode!(bytecode, 0, [Constant0, ForwardLine, Return], [n], cx);
+            for point in [0, 5, 9, 13] {
+                check_buffer_op(bytecode, &format!("(forward-line {n})"), text, point, cx);
+            }
+            make_bytecode!(bytecode, 0, [Constant0, EndOfLine, Return], [n], cx);
+            check_buffer_op(bytecode, &format!("(end-of-line {n})"), text, 5, cx);
+        }
+        make_bytecode!(bytecode, 0, [Constant0, ForwardChar, Return], [false], cx);
+        check_buffer_op(bytecode, "(forward-char)", text, 5, cx);
+        make_bytecode!(bytecode, 0, [Constant0, ForwardLine, Return], [false], cx);
+        check_buffer_op(bytecode, "(forward-line)", text, 5, cx);
+        make_bytecode!(bytecode, 0, [Constant0, EndOfLine, Return], [false], cx);
+        check_buffer_op(bytecode, "(end-of-line)", text, 5, cx);
+
+        make_bytecode!(bytecode, 0, [CurrentBuffer, Return], [], cx);
+        check_buffer_op(bytecode, "(current-buffer)", text, 0, cx);
+        crate::buffer::get_buffer_create(cx.add("bytecode-set-buffer"), None, cx).unwrap();
+        make_bytecode!(
+            bytecode,
+            0,
+            [Constant0, SetBuffer, Discard, CurrentBuffer, Return],
+            ["bytecode-set-buffer"],
+            cx
+        );
+        let form = "(progn (set-buffer \"bytecode-set-buffer\") (current-buffer))";
+        check_buffer_op(bytecode, form, text, 0, cx);
+    }
+
+    proptest! {
+        #[test]
+        fn pt_concat(s1 in "\\PC*", s2 in "\\PC*", s3 in "\\PC*", s4 in "\\PC*") {
+            use OpCode::*;
+            let roots = &RootSet::default();
+            let cx = &mut Context::new(roots);
+            sym::init_symbols();
+            let (s1, s2, s3, s4) = (&*s1, &*s2, &*s3, &*s4);
+            make_bytecode!(bytecode, 0, [Constant0, Constant1, Concat2, Return], [s1, s2], cx);
+            check_against_funcall(bytecode, sym::CONCAT, cx);
+            make_bytecode!(
+                bytecode,
+                0,
+                [Constant0, Constant1, Constant2, Concat3, Return],
+                [s1, s2, s3],
+                cx
+            );
+            check_against_funcall(bytecode, sym::CONCAT, cx);
+            make_bytecode!(
+                bytecode,
+                0,
+                [Constant0, Constant1, Constant2, Constant3, Concat4, Return],
+                [s1, s2, s3, s4],
+                cx
+            );
+            check_against_funcall(bytecode, sym::CONCAT, cx);
+            make_bytecode!(
+                bytecode,
+                0,
+                [Constant0, Constant1, Constant2, Constant3, Constant4, ConcatN, 5, Return],
+                [s1, s2, false, s3, s4],
+                cx
+            );
+            check_against_funcall(bytecode, sym::CONCAT, cx);
+        }
+
+        #[test]
+        fn pt_substring(
+            s in "\\PC{0,10}",
+            from in proptest::option::of(-12..12i64),
+            to in proptest::option::of(-12..12i64),
+        ) {
+            use OpCode::*;
+            let roots = &RootSet::default();
+            let cx = &mut Context::new(roots);
+            sym::init_symbols();
+            make_bytecode!(
+                bytecode,
+                0,
+                [Constant0, Constant1, Constant2, Substring, Return],
+                [&*s, from, to],
+                cx
+            );
+            check_against_funcall(bytecode, sym::SUBSTRING, cx);
+        }
+
+        #[test]
+        fn pt_case(s in "\\PC*", c in any::<char>()) {
+            use OpCode::*;
+            let roots = &RootSet::default();
+            let cx = &mut Context::new(roots);
+            sym::init_symbols();
+            make_bytecode!(bytecode, 0, [Constant0, Upcase, Return], [&*s], cx);
+            check_against_funcall(bytecode, sym::UPCASE, cx);
+            make_bytecode!(bytecode, 0, [Constant0, Downcase, Return], [&*s], cx);
+            check_against_funcall(bytecode, sym::DOWNCASE, cx);
+            make_bytecode!(bytecode, 0, [Constant0, Upcase, Return], [c], cx);
+            check_against_funcall(bytecode, sym::UPCASE, cx);
+            make_bytecode!(bytecode, 0, [Constant0, Downcase, Return], [c], cx);
+            check_against_funcall(bytecode, sym::DOWNCASE, cx);
+        }
+
+        #[test]
+        fn pt_string_compare(s1 in "[a-c]{0,3}", s2 in "[a-c]{0,3}") {
+            use OpCode::*;
+            let roots = &RootSet::default();
+            let cx = &mut Context::new(roots);
+            sym::init_symbols();
+            let (s1, s2) = (&*s1, &*s2);
+            make_bytecode!(bytecode, 0, [Constant0, Constant1, StringEqlSign, Return], [s1, s2], cx);
+            check_against_funcall(bytecode, sym::STRING_EQUAL, cx);
+            make_bytecode!(bytecode, 0, [Constant0, Constant1, StringLessThan, Return], [s1, s2], cx);
+            check_against_funcall(bytecode, sym::STRING_LESSP, cx);
+        }
+    }
+
+    #[test]
+    fn test_string_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, Concat2, Return], ["foo", "bar"], cx);
+        check_bytecode!(bytecode, [], "foobar", cx);
+        // (lambda () (concat))
+        make_bytecode!(bytecode, 0, [ConcatN, 0, Return], [], cx);
+        check_bytecode!(bytecode, [], "", cx);
+        make_bytecode!(
+            bytecode,
+            0,
+            [Constant0, Constant1, Constant2, Substring, Return],
+            ["foobar", 1, 4],
+            cx
+        );
+        check_bytecode!(bytecode, [], "oob", cx);
+        make_bytecode!(
+            bytecode,
+            0,
+            [Constant0, Constant1, Constant2, Substring, Return],
+            ["αβγδ", -3, false],
+            cx
+        );
+        check_bytecode!(bytecode, [], "βγδ", cx);
+        make_bytecode!(bytecode, 0, [Constant0, Upcase, Return], ["foo"], cx);
+        check_bytecode!(bytecode, [], "FOO", cx);
+        make_bytecode!(bytecode, 0, [Constant0, Downcase, Return], ['A'], cx);
+        check_bytecode!(bytecode, [], 'a', cx);
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, StringEqlSign, Return], ["a", "a"], cx);
+        check_bytecode!(bytecode, [], true, cx);
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, StringLessThan, Return], ["b", "a"], cx);
+        check_bytecode!(bytecode, [], false, cx);
+    }
+
+    #[test]
+    fn test_arith_ops() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, Diff, Return], [7, 10], cx);
+        check_bytecode!(bytecode, [], -3, cx);
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, Diff, Return], [7, 0.5], cx);
+        check_bytecode!(bytecode, [], 6.5, cx);
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, Quo, Return], [-7, 2], cx);
+        check_bytecode!(bytecode, [], -3, cx);
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, Quo, Return], [7.0, 2], cx);
+        check_bytecode!(bytecode, [], 3.5, cx);
+        make_bytecode!(bytecode, 0, [Constant0, Constant1, Rem, Return], [-7, 2], cx);
+        check_bytecode!(bytecode, [], -1, cx);
+
+        use crate::arith::NumberValue::{Float, Int};
+        let cases = [
+            (Diff, sym::SUB, Int(1)),
+            (Diff, sym::SUB, Float(1.5)),
+            (Quo, sym::DIV, Int(3)),
+            (Quo, sym::DIV, Int(0)),
+            (Quo, sym::DIV, Float(0.0)),
+            (Rem, sym::REMAINDER, Int(4)),
+            (Rem, sym::REMAINDER, Int(0)),
+            (Rem, sym::REMAINDER, Float(2.0)),
+        ];
+        for (op, func, rhs) in cases {
+            for lhs in [Int(13), Int(-13), Float(2.5)] {
+                make_bytecode!(bytecode, 0, [Constant0, Constant1, op, Return], [lhs, rhs], cx);
+                check_against_funcall(bytecode, func, cx);
+            }
+        }
+    }
+
+    /// Compare the arithmetic opcodes against calling the corresponding subr.
+    /// This only reports the timings. Run with
+    /// `cargo test --release -- --ignored bench_arith_ops --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_arith_ops() {
+        use std::time::Instant;
+        use OpCode::*;
+        const ITERATIONS: u32 = 100_000;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        crate::debug::disable_debug();
+        root!(env, new(Env), cx);
+        let mut time = |func: &Rto<&ByteFn>, cx: &mut Context| {
+            let start = Instant::now();
+            for _ in 0..ITERATIONS {
+                let frame = &mut CallFrame::new(env);
+                frame.finalize_arguments();
+                call(func, 0, "bench", frame, cx).unwrap();
+            }
+            start.elapsed() / ITERATIONS
+        };
+        use crate::arith::NumberValue::{Float, Int};
+        // % only accepts integers
+        let cases = [
+            (Plus, sym::ADD, &[Int(3), Float(3.0)][..]),
+            (Diff, sym::SUB, &[Int(3), Float(3.0)]),
+            (Multiply, sym::MUL, &[Int(3), Float(3.0)]),
+            (Quo, sym::DIV, &[Int(3), Float(3.0)]),
+            (Rem, sym::REMAINDER, &[Int(3)]),
+        ];
+        for (op, func, operands) in cases {
+            for &rhs in operands {
+                make_bytecode!(inline, 0, [Constant0, Constant1, op, Return], [17, rhs], cx);
+                let funcall = funcall_bytecode(inline, func, cx);
+                root!(funcall, cx);
+                // warm up
+                time(inline, cx);
+                time(funcall, cx);
+                let inline_time = time(inline, cx);
+                let funcall_time = time(funcall, cx);
+                println!("{op:?} 17 {rhs:?}: inline {inline_time:?}, funcall {funcall_time:?}");
+            }
+        }
+        crate::debug::enable_debug();
+    }
+
+    #[test]
+    fn test_catch() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        let tag = crate::core::env::intern("tag", cx);
+        root!(tag, cx);
+        let other = crate::core::env::intern("other", cx);
+        root!(other, cx);
+
+        // (lambda () (catch 'tag (throw 'tag 5) 6))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant0, PushCatch, 0x0B, 0x00, Constant1, Constant0, Constant2, Call2, Discard,
+                Constant3, PopHandler, Return
+            ],
+            [tag.bind(cx), sym::THROW, 5, 6],
+            cx
+        );
+        check_bytecode!(bytecode, [], 5, cx);
+
+        // (lambda () (catch 'tag 6))
+        make_bytecode!(
+            bytecode,
+            0,
+            [Constant0, PushCatch, 0x06, 0x00, Constant1, PopHandler, Return],
+            [tag.bind(cx), 6],
+            cx
+        );
+        check_bytecode!(bytecode, [], 6, cx);
+
+        // (lambda () (catch 'other (throw 'tag 7)))
+        make_bytecode!(
+            inner,
+            0,
+            [
+                Constant0, PushCatch, 0x09, 0x00, Constant1, Constant2, Constant3, Call2,
+                PopHandler, Return
+            ],
+            [other.bind(cx), sym::THROW, tag.bind(cx), 7],
+            cx
+        );
+        let inner_fn = cx.add(inner.bind(cx));
+        root!(inner_fn, cx);
+        // (lambda (x) (catch 'tag (funcall x)))
+        make_bytecode!(
+            outer,
+            257,
+            [Constant0, PushCatch, 0x07, 0x00, Duplicate, Call0, PopHandler, Return],
+            [tag.bind(cx)],
+            cx
+        );
+        check_bytecode!(outer, [inner_fn], 7, cx);
+
+        // the catch stack is balanced after a throw, and throwing without a
+        // catch is an error
+        root!(env, new(Env), cx);
+        {
+            let inner_fn = cx.add(inner.bind(cx));
+            let frame = &mut CallFrame::new(env);
+            frame.push_arg(inner_fn);
+            frame.finalize_arguments();
+            assert!(call(outer, 1, "test", frame, cx).is_ok());
+        }
+        assert!(env.catch_stack.is_empty());
+        {
+            let frame = &mut CallFrame::new(env);
+            frame.finalize_arguments();
+            assert!(call(inner, 0, "test", frame, cx).is_err());
+        }
+        assert!(env.catch_stack.is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_args() {
+        use crate::interpreter::assert_lisp;
+        // (lambda (vm-dyn &optional vm-opt) vm-opt)
+        assert_lisp(r#"(funcall #[(vm-dyn &optional vm-opt) "\10\207" [vm-opt] 1] 1)"#, "nil");
+        // (lambda (&rest vm-dyn) (vm-read-dyn)), where the callee sees the binding
+        assert_lisp(
+            r#"(progn (defalias 'vm-read-dyn #'(lambda () vm-dyn))
+                      (list (funcall #[(&rest vm-dyn) "\300 \207" [vm-read-dyn] 1] 1 2)
+                            (boundp 'vm-dyn)))"#,
+            "((1 2) nil)",
+        );
+        // called from byte-code, (lambda () (vm-dyn-fn 5))
+        assert_lisp(
+            r#"(progn (defalias 'vm-dyn-fn #[(vm-dyn) "\10\207" [vm-dyn] 1])
+                      (list (funcall #[0 "\300\301!\207" [vm-dyn-fn 5] 2]) (boundp 'vm-dyn)))"#,
+            "(5 nil)",
+        );
+        // the arguments are unbound when the function exits nonlocally
+        assert_lisp(
+            r#"(progn (condition-case nil
+                          (funcall #[(vm-dyn) "\300 \207" [vm-signal] 1] 1)
+                        (void-function nil))
+                      (boundp 'vm-dyn))"#,
+            "nil",
+        );
+    }
+
+    #[test]
+    fn test_unwind_protect() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        let tag = crate::core::env::intern("tag", cx);
+        root!(tag, cx);
+
+        // (lambda () (setq load-path 9))
+        make_bytecode!(
+            handler,
+            0,
+            [Constant1, Duplicate, VarSet0, Return],
+            [sym::LOAD_PATH, 9],
+            cx
+        );
+        let handler = cx.add(handler.bind(cx));
+        root!(handler, cx);
+
+        // (let ((load-path 0))
+        //   (unwind-protect 1 (setq load-path 9))
+        //   load-path)
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant1,
+                VarBind0,
+                Constant2,
+                UnwindProtect,
+                Constant3,
+                Unbind1,
+                Discard,
+                VarRef0,
+                Unbind1,
+                Return
+            ],
+            [sym::LOAD_PATH, 0, handler.bind(cx), 1],
+            cx
+        );
+        check_bytecode!(bytecode, [], 9, cx);
+
+        // (let ((load-path 0))
+        //   (condition-case nil
+        //       (unwind-protect (floor) (setq load-path 9))
+        //     (error load-path)))
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant1,
+                VarBind0,
+                Constant2,
+                PushCondtionCase,
+                0x0D,
+                0x00,
+                Constant3,
+                UnwindProtect,
+                Constant4,
+                Call0,
+                Unbind1,
+                PopHandler,
+                Return,
+                Discard,
+                VarRef0,
+                Unbind1,
+                Return
+            ],
+            [sym::LOAD_PATH, 0, sym::ERROR, handler.bind(cx), sym::FLOOR],
+            cx
+        );
+        check_bytecode!(bytecode, [], 9, cx);
+
+        // (let ((load-path 0))
+        //   (catch 'tag (unwind-protect (throw 'tag 1) (setq load-path 9)))
+        //   load-path)
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                Constant1,
+                VarBind0,
+                Constant2,
+                PushCatch,
+                0x0E,
+                0x00,
+                Constant3,
+                UnwindProtect,
+                Constant4,
+                Constant2,
+                Constant5,
+                Call2,
+                Unbind1,
+                PopHandler,
+                Discard,
+                VarRef0,
+                Unbind1,
+                Return
+            ],
+            [sym::LOAD_PATH, 0, tag.bind(cx), handler.bind(cx), sym::THROW, 1],
+            cx
+        );
+        check_bytecode!(bytecode, [], 9, cx);
+
+        // unwind forms run when the error passes through a nested frame
+        // (lambda () (unwind-protect (floor) (setq load-path 9)))
+        make_bytecode!(
+            inner,
+            0,
+            [Constant0, UnwindProtect, Constant1, Call0, Unbind1, Return],
+            [handler.bind(cx), sym::FLOOR],
+            cx
+        );
+        let inner_fn = cx.add(inner.bind(cx));
+        root!(inner_fn, cx);
+        // (lambda (x)
+        //   (let ((load-path 0))
+        //     (condition-case nil (funcall x) (error load-path))))
+        make_bytecode!(
+            outer,
+            257,
+            [
+                Constant1,
+                VarBind0,
+                Constant2,
+                PushCondtionCase,
+                0x0A,
+                0x00,
+                Duplicate,
+                Call0,
+                PopHandler,
+                Return,
+                Discard,
+                VarRef0,
+                Unbind1,
+                Return
+            ],
+            [sym::LOAD_PATH, 0, sym::ERROR],
+            cx
+        );
+        check_bytecode!(outer, [inner_fn], 9, cx);
+
+        // an error that escapes the function still unwinds the bindings
+        root!(env, new(Env), cx);
+        env.vars.insert(sym::LOAD_PATH, NIL);
+        {
+            let frame = &mut CallFrame::new(env);
+            frame.finalize_arguments();
+            assert!(call(inner, 0, "test", frame, cx).is_err());
+        }
+        assert_eq!(env.binding_depth(), 0);
+        assert_eq!(env.var(sym::LOAD_PATH).unwrap().bind(cx), 9);
+    }
+
+    #[test]
+    fn test_save_excursion() {
+        use OpCode::*;
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        let text = "foo\nbar";
+
+        make_bytecode!(bytecode, 0, [SaveExcursion, Constant0, GotoChar, Unbind1, Return], [3], cx);
+        check_buffer_op(bytecode, "(save-excursion (goto-char 3))", text, 5, cx);
+
+        // point is restored on a non-local exit
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                SaveExcursion,
+                Constant0,
+                GotoChar,
+                Discard,
+                Constant1,
+                ForwardChar,
+                Unbind1,
+                Return
+            ],
+            [3, 100],
+            cx
+        );
+        let form = "(save-excursion (goto-char 3) (forward-char 100))";
+        check_buffer_op(bytecode, form, text, 5, cx);
+
+        crate::buffer::get_buffer_create(cx.add("bytecode-save-buffer"), None, cx).unwrap();
+        make_bytecode!(
+            bytecode,
+            0,
+            [
+                SaveCurrentBuffer1,
+                Constant0,
+                SetBuffer,
+                Discard,
+                Unbind1,
+                CurrentBuffer,
+                Return
+            ],
+            ["bytecode-save-buffer"],
+            cx
+        );
+        let form =
+            "(progn (save-current-buffer (set-buffer \"bytecode-save-buffer\")) (current-buffer))";
+        check_buffer_op(bytecode, form, text, 0, cx);
+
+        make_bytecode!(bytecode, 0, [SaveRestriction, Constant0, Unbind1, Return], [5], cx);
+        check_bytecode!(bytecode, [], 5, cx);
+    }
 }
@@ -0,0 +1,332 @@
+//! Disassembler for byte-compiled functions.
+use super::opcode::OpCode;
+use crate::core::object::{ByteFn, ByteFnPrototype, FnArgs, Object, ObjectType};
+use anyhow::{anyhow, bail, Result};
+use std::fmt::Write;
+
+/// A single decoded instruction.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Instruction {
+    /// Byte offset of the opcode.
+    pub(super) offset: usize,
+    pub(super) op: OpCode,
+    /// The operand, whether it is encoded in the opcode itself or follows it.
+    pub(super) arg: u16,
+}
+
+/// Decode `codes` into a sequence of instructions.
+pub(super) fn decode(codes: &[u8]) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < codes.len() {
+        let Ok(op) = OpCode::try_from(codes[offset]) else {
+            bail!("Invalid opcode {} at offset {offset}", codes[offset])
+        };
+        let len = op.operand_len();
+        let Some(operand) = codes.get(offset + 1..offset + 1 + len) else {
+            bail!("Truncated operand for {op:?} at offset {offset}")
+        };
+        let arg = match *operand {
+            [] => op.implicit_operand().unwrap_or(0),
+            [x] => u16::from(x),
+            [lo, hi] => u16::from_le_bytes([lo, hi]),
+            _ => unreachable!("operands are at most 2 bytes"),
+        };
+        instructions.push(Instruction { offset, op, arg });
+        offset += 1 + len;
+    }
+    Ok(instructions)
+}
+
+/// The number of values on the stack when `func` is entered. Dynamic binding
+/// code starts with an empty stack.
+pub(super) fn initial_depth(func: &ByteFnPrototype) -> usize {
+    if func.dynamic_args().is_some() {
+        return 0;
+    }
+    let args = func.args;
+    usize::from(args.required) + usize::from(args.optional) + usize::from(args.rest)
+}
+
+/// The jump targets of the `Switch` at `idx`. The jump table is the constant
+/// pushed by the instruction before it.
+fn switch_targets(code: &[Instruction], idx: usize, consts: &[Object]) -> Option<Vec<usize>> {
+    let prev = code[idx.checked_sub(1)?];
+    if prev.op.family() != Some("constant") {
+        return None;
+    }
+    let ObjectType::HashTable(table) = consts.get(prev.arg as usize)?.untag() else {
+        return None;
+    };
+    (0..table.len())
+        .map(|i| match table.get_index(i)?.1.untag() {
+            ObjectType::Int(target) => usize::try_from(target).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Compute the stack depth before each instruction by following the control
+/// flow from the start of the function, which begins with `start` values on
+/// the stack. Instructions that are never reached are `None`.
+pub(super) fn stack_depths(
+    code: &[Instruction],
+    consts: &[Object],
+    start: usize,
+) -> Result<Vec<Option<usize>>> {
+    use OpCode as op;
+    let mut depths = vec![None; code.len()];
+    if code.is_empty() {
+        return Ok(depths);
+    }
+    let index_of = |offset: usize| {
+        code.binary_search_by_key(&offset, |x| x.offset)
+            .map_err(|_| anyhow!("Invalid jump target {offset}"))
+    };
+    let mut work = vec![(0, start)];
+    while let Some((idx, depth)) = work.pop() {
+        match depths[idx] {
+            Some(prev) if prev == depth => continue,
+            Some(prev) => {
+                bail!("Inconsistent stack depth at offset {}: {prev} and {depth}", code[idx].offset)
+            }
+            None => depths[idx] = Some(depth),
+        }
+        let insn = code[idx];
+        let effect = insn.op.stack_effect(insn.arg);
+        let pops = (insn.op.pushes(insn.arg) - effect) as usize;
+        let needed = match insn.op {
+            op::StackRef0
+            | op::StackRef1
+            | op::StackRef2
+            | op::StackRef3
+            | op::StackRef4
+            | op::StackRef5
+            | op::StackRefN
+            | op::StackRefN2
+            | op::StackSetN
+            | op::StackSetN2 => usize::from(insn.arg) + 1,
+            op::Duplicate => 1,
+            _ => pops,
+        };
+        if depth < needed {
+            bail!("Stack underflow at offset {}", insn.offset);
+        }
+        let after = (depth as isize + effect as isize) as usize;
+        let mut flow_to = |offset: usize, depth: usize| -> Result<()> {
+            work.push((index_of(offset)?, depth));
+            Ok(())
+        };
+        let target = usize::from(insn.arg);
+        match insn.op {
+            op::Return => continue,
+            op::Goto => {
+                flow_to(target, depth)?;
+                continue;
+            }
+            op::GotoIfNil | op::GotoIfNonNil => flow_to(target, after)?,
+            // The condition is left on the stack when the jump is taken, and
+            // a handler jumps with the tag replaced by the error value
+            op::GotoIfNilElsePop
+            | op::GotoIfNonNilElsePop
+            | op::PushCondtionCase
+            | op::PushCatch => flow_to(target, depth)?,
+            op::Switch => {
+                let Some(targets) = switch_targets(code, idx, consts) else {
+                    bail!("Switch without a jump table at offset {}", insn.offset)
+                };
+                for target in targets {
+                    flow_to(target, after)?;
+                }
+            }
+            _ => {}
+        }
+        if idx + 1 == code.len() {
+            bail!("Bytecode falls off the end after offset {}", insn.offset);
+        }
+        work.push((idx + 1, after));
+    }
+    Ok(depths)
+}
+
+/// The name of `op` as shown in a listing.
+fn mnemonic(op: OpCode) -> String {
+    if let Some(family) = op.family() {
+        return family.to_owned();
+    }
+    let mut name = String::new();
+    for (i, c) in format!("{op:?}").chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+/// Format the operand of `insn`, annotating constant references with the
+/// constant itself.
+fn operand(insn: Instruction, consts: &[Object]) -> String {
+    let constant = |idx: u16| match consts.get(idx as usize) {
+        Some(obj) => format!("{idx}\t; {obj}"),
+        None => format!("{idx}\t; <invalid constant>"),
+    };
+    match insn.op.family() {
+        Some("constant" | "varref" | "varset" | "varbind") => constant(insn.arg),
+        Some("discard") if insn.arg & 0x80 != 0 => format!("{} preserve-tos", insn.arg & 0x7F),
+        Some(_) => insn.arg.to_string(),
+        None if insn.op.is_jump() => insn.arg.to_string(),
+        None => String::new(),
+    }
+}
+
+/// The argument list of a function with `args`, in the form used by Emacs
+/// for byte-code with an integer argument descriptor.
+fn arglist(args: FnArgs) -> String {
+    let mut list = Vec::new();
+    let mut n = 1;
+    for _ in 0..args.required {
+        list.push(format!("arg{n}"));
+        n += 1;
+    }
+    if args.optional > 0 {
+        list.push("&optional".to_owned());
+        for _ in 0..args.optional {
+            list.push(format!("arg{n}"));
+            n += 1;
+        }
+    }
+    if args.rest {
+        list.push("&rest rest".to_owned());
+    }
+    format!("({})", list.join(" "))
+}
+
+/// Produce a readable listing of `func`, with the byte offset and stack depth
+/// of each instruction.
+pub(crate) fn disassemble(func: &ByteFn, name: Option<&str>) -> Result<String> {
+    let code = decode(func.codes())?;
+    let consts = func.consts();
+    // Still show the listing if the control flow can't be followed
+    let (depths, depth_err) = match stack_depths(&code, consts, initial_depth(func)) {
+        Ok(depths) => (depths, None),
+        Err(e) => (vec![None; code.len()], Some(e)),
+    };
+    let mut out = String::new();
+    match name {
+        Some(name) => writeln!(out, "byte code for {name}:")?,
+        None => writeln!(out, "byte code:")?,
+    }
+    match func.dynamic_args() {
+        Some(args) => writeln!(out, "  args: {args}")?,
+        None => writeln!(out, "  args: {}", arglist(func.args))?,
+    }
+    writeln!(out, "  depth: {}", func.depth)?;
+    if let Some(e) = depth_err {
+        writeln!(out, "  error: {e}")?;
+    }
+    for (insn, depth) in code.iter().zip(depths) {
+        let depth = depth.map_or_else(|| "[-]".to_owned(), |x| format!("[{x}]"));
+        let line = format!(
+            "{:>5} {depth:<5} {:<21}{}",
+            insn.offset,
+            mnemonic(insn.op),
+            operand(*insn, consts)
+        );
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::sym;
+    use crate::core::gc::{Context, RootSet};
+    use crate::core::object::IntoObject;
+    use OpCode::*;
+
+    macro_rules! codes {
+        ($($x:expr),* $(,)?) => {{
+            #[allow(trivial_numeric_casts)]
+            let codes: &[u8] = &[$($x as u8),*];
+            codes
+        }};
+    }
+
+    fn make<'ob>(args: i64, codes: &[u8], consts: Vec<Object>, cx: &'ob Context) -> &'ob ByteFn {
+        let codes = codes.to_vec().into_obj(cx).untag();
+        let consts = consts.into_obj(cx).untag();
+        crate::alloc::make_byte_code(args.into(), codes, consts, 3, &[], cx).unwrap()
+    }
+
+    #[test]
+    fn test_decode() {
+        let code = decode(codes![StackRefN, 7, Goto, 0x02, 0x01, Constant3]).unwrap();
+        let ops: Vec<_> = code.iter().map(|x| (x.offset, x.arg)).collect();
+        assert_eq!(ops, [(0, 7), (2, 258), (5, 3)]);
+        assert!(decode(codes![Goto, 0x01]).is_err());
+        assert!(decode(&[51]).is_err());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        sym::init_symbols();
+        // (lambda (x) (if x (+ x 1) 'none))
+        let func = make(
+            257,
+            codes![Duplicate, GotoIfNil, 0x07, 0x00, Duplicate, Add1, Return, Constant0, Return],
+            vec![sym::NIL.into()],
+            cx,
+        );
+        let listing = disassemble(func, Some("foo")).unwrap();
+        assert_eq!(
+            listing,
+            "\
+byte code for foo:
+  args: (arg1)
+  depth: 3
+    0 [1]   duplicate
+    1 [2]   goto-if-nil          7
+    4 [1]   duplicate
+    5 [2]   add1
+    6 [2]   return
+    7 [1]   constant             0\t; nil
+    8 [2]   return
+"
+        );
+    }
+
+    #[test]
+    fn test_stack_depths() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let depths = |codes: &[u8], consts: &[Object], start| {
+            stack_depths(&decode(codes).unwrap(), consts, start)
+        };
+        // stack underflow
+        assert!(depths(codes![Discard, Return], &[], 0).is_err());
+        assert!(depths(codes![StackRef1, Return], &[], 1).is_err());
+        assert!(depths(codes![Call1, Return], &[], 1).is_err());
+        assert!(depths(codes![Duplicate, Return], &[], 0).is_err());
+        // jump into the middle of an instruction
+        assert!(depths(codes![Constant0, Goto, 0x02, 0x00, Return], &[1.into()], 0).is_err());
+        // falls off the end
+        assert!(depths(codes![Constant0], &[1.into()], 0).is_err());
+        // inconsistent depth at a join
+        let code = codes![StackRef0, GotoIfNil, 0x05, 0x00, Duplicate, Return];
+        assert!(depths(code, &[], 1).is_err());
+        // switch without a jump table
+        assert!(depths(codes![Duplicate, Duplicate, Switch, Return], &[], 1).is_err());
+        // handler target has the same depth as before the push
+        let code = codes![Constant0, PushCatch, 0x06, 0x00, PopHandler, Constant0, Return];
+        let depths = depths(code, &[cx.add(1)], 0).unwrap();
+        assert_eq!(depths, [Some(0), Some(1), Some(0), Some(0), Some(1)]);
+    }
+}
@@ -260,3 +260,288 @@ pub(crate) enum OpCode {
     Constant62 = 254,
     Constant63 = 255,
 }
+
+impl OpCode {
+    /// Whether the VM can execute this opcode. Code that uses the others
+    /// will panic when it reaches them.
+    pub(crate) fn is_implemented(self) -> bool {
+        use OpCode as op;
+        !matches!(
+            self,
+            op::Insert
+                | op::InsertN
+                | op::CurrentColumn
+                | op::IndentTo
+                | op::ForwardWord
+                | op::SkipCharsForward
+                | op::SkipCharsBackward
+                | op::CharSyntax
+                | op::BufferSubstring
+                | op::DeleteRegion
+                | op::NarrowToRegion
+                | op::Widen
+                | op::SetMarker
+                | op::MatchBeginning
+                | op::MatchEnd
+        )
+    }
+
+    /// The number of operand bytes that follow this opcode.
+    pub(crate) fn operand_len(self) -> usize {
+        use OpCode as op;
+        match self {
+            op::StackRefN
+            | op::VarRefN
+            | op::VarSetN
+            | op::VarBindN
+            | op::CallN
+            | op::UnbindN
+            | op::ListN
+            | op::ConcatN
+            | op::InsertN
+            | op::StackSetN
+            | op::DiscardN => 1,
+            op::StackRefN2
+            | op::VarRefN2
+            | op::VarSetN2
+            | op::VarBindN2
+            | op::CallN2
+            | op::UnbindN2
+            | op::PushCondtionCase
+            | op::PushCatch
+            | op::ConstantN2
+            | op::Goto
+            | op::GotoIfNil
+            | op::GotoIfNonNil
+            | op::GotoIfNilElsePop
+            | op::GotoIfNonNilElsePop
+            | op::StackSetN2 => 2,
+            _ => 0,
+        }
+    }
+
+    /// The operand that is encoded in the opcode itself, such as the 3 in
+    /// `StackRef3`.
+    pub(crate) fn implicit_operand(self) -> Option<u16> {
+        let code = self as u8;
+        match code {
+            0..=5 | 8..=13 | 16..=21 | 24..=29 | 32..=37 | 40..=45 => Some(u16::from(code % 8)),
+            // List1 - List4
+            67..=70 => Some(u16::from(code - 66)),
+            // Concat2 - Concat4
+            80..=82 => Some(u16::from(code - 78)),
+            192..=255 => Some(u16::from(code - 192)),
+            _ => None,
+        }
+    }
+
+    /// The name shared by all opcodes that only differ in how their operand is
+    /// encoded. For example `StackRef1` and `StackRefN` are both `stack-ref`.
+    pub(crate) fn family(self) -> Option<&'static str> {
+        match self as u8 {
+            0..=7 => Some("stack-ref"),
+            8..=15 => Some("varref"),
+            16..=23 => Some("varset"),
+            24..=31 => Some("varbind"),
+            32..=39 => Some("call"),
+            40..=47 => Some("unbind"),
+            67..=70 | 175 => Some("list"),
+            80..=82 | 176 => Some("concat"),
+            177 => Some("insert"),
+            178 | 179 => Some("stack-set"),
+            182 => Some("discard"),
+            129 | 192..=255 => Some("constant"),
+            _ => None,
+        }
+    }
+
+    /// Whether this opcode takes the jump offset as its operand.
+    pub(crate) fn is_jump(self) -> bool {
+        use OpCode as op;
+        matches!(
+            self,
+            op::Goto
+                | op::GotoIfNil
+                | op::GotoIfNonNil
+                | op::GotoIfNilElsePop
+                | op::GotoIfNonNilElsePop
+                | op::PushCondtionCase
+                | op::PushCatch
+        )
+    }
+
+    /// The number of values this opcode pushes onto the stack after popping
+    /// its arguments.
+    pub(crate) fn pushes(self, arg: u16) -> i32 {
+        use OpCode as op;
+        match self {
+            op::VarSet0
+            | op::VarSet1
+            | op::VarSet2
+            | op::VarSet3
+            | op::VarSet4
+            | op::VarSet5
+            | op::VarSetN
+            | op::VarSetN2
+            | op::VarBind0
+            | op::VarBind1
+            | op::VarBind2
+            | op::VarBind3
+            | op::VarBind4
+            | op::VarBind5
+            | op::VarBindN
+            | op::VarBindN2
+            | op::Unbind0
+            | op::Unbind1
+            | op::Unbind2
+            | op::Unbind3
+            | op::Unbind4
+            | op::Unbind5
+            | op::UnbindN
+            | op::UnbindN2
+            | op::PopHandler
+            | op::PushCondtionCase
+            | op::PushCatch
+            | op::SaveCurrentBuffer1
+            | op::Goto
+            | op::GotoIfNil
+            | op::GotoIfNonNil
+            | op::GotoIfNilElsePop
+            | op::GotoIfNonNilElsePop
+            | op::Return
+            | op::Discard
+            | op::SaveExcursion
+            | op::SaveRestriction
+            | op::UnwindProtect
+            | op::StackSetN
+            | op::StackSetN2
+            | op::Switch => 0,
+            op::DiscardN => i32::from(arg & 0x80 != 0),
+            _ => 1,
+        }
+    }
+
+    /// The change in stack depth from executing this opcode with operand
+    /// `arg`. For conditional jumps this is the effect when the jump is not
+    /// taken.
+    pub(crate) fn stack_effect(self, arg: u16) -> i32 {
+        use OpCode as op;
+        let arg = i32::from(arg);
+        match self {
+            op::StackRef0
+            | op::StackRef1
+            | op::StackRef2
+            | op::StackRef3
+            | op::StackRef4
+            | op::StackRef5
+            | op::StackRefN
+            | op::StackRefN2
+            | op::VarRef0
+            | op::VarRef1
+            | op::VarRef2
+            | op::VarRef3
+            | op::VarRef4
+            | op::VarRef5
+            | op::VarRefN
+            | op::VarRefN2
+            | op::ConstantN2
+            | op::Duplicate
+            | op::Point
+            | op::PointMax
+            | op::PointMin
+            | op::FollowingChar
+            | op::PrecedingChar
+            | op::CurrentColumn
+            | op::EndOfLineP
+            | op::EndOfBufferP
+            | op::BeginningOfLineP
+            | op::BeginningOfBufferP
+            | op::CurrentBuffer
+            | op::Widen => 1,
+            op::Call0
+            | op::Call1
+            | op::Call2
+            | op::Call3
+            | op::Call4
+            | op::Call5
+            | op::CallN
+            | op::CallN2 => -arg,
+            op::List1
+            | op::List2
+            | op::List3
+            | op::List4
+            | op::ListN
+            | op::Concat2
+            | op::Concat3
+            | op::Concat4
+            | op::ConcatN
+            | op::InsertN => 1 - arg,
+            op::DiscardN => -(arg & 0x7F),
+            op::Substring | op::Aset | op::SetMarker | op::Switch => -2,
+            op::VarSet0
+            | op::VarSet1
+            | op::VarSet2
+            | op::VarSet3
+            | op::VarSet4
+            | op::VarSet5
+            | op::VarSetN
+            | op::VarSetN2
+            | op::VarBind0
+            | op::VarBind1
+            | op::VarBind2
+            | op::VarBind3
+            | op::VarBind4
+            | op::VarBind5
+            | op::VarBindN
+            | op::VarBindN2
+            | op::PushCondtionCase
+            | op::PushCatch
+            | op::Nth
+            | op::Eq
+            | op::Memq
+            | op::Cons
+            | op::Aref
+            | op::Set
+            | op::Fset
+            | op::Get
+            | op::EqlSign
+            | op::GreaterThan
+            | op::LessThan
+            | op::LessThanOrEqual
+            | op::GreaterThanOrEqual
+            | op::Diff
+            | op::Plus
+            | op::Max
+            | op::Min
+            | op::Multiply
+            | op::SkipCharsForward
+            | op::SkipCharsBackward
+            | op::BufferSubstring
+            | op::DeleteRegion
+            | op::NarrowToRegion
+            | op::GotoIfNil
+            | op::GotoIfNonNil
+            | op::GotoIfNilElsePop
+            | op::GotoIfNonNilElsePop
+            | op::Return
+            | op::Discard
+            | op::UnwindProtect
+            | op::StringEqlSign
+            | op::StringLessThan
+            | op::Equal
+            | op::Nthcdr
+            | op::Elt
+            | op::Member
+            | op::Assq
+            | op::Setcar
+            | op::Setcdr
+            | op::Nconc
+            | op::Quo
+            | op::Rem
+            | op::StackSetN
+            | op::StackSetN2 => -1,
+            _ if self as u8 >= op::Constant0 as u8 => 1,
+            _ => 0,
+        }
+    }
+}
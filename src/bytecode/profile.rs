@@ -0,0 +1,311 @@
+//! Profiling of the bytecode VM. Only compiled with the `vm_profile` feature.
+use super::opcode::OpCode;
+use crate::core::cons::Cons;
+use crate::core::env::sym;
+use crate::core::gc::Context;
+use crate::core::object::{ByteFn, Object};
+use rune_core::hashmap::HashMap;
+use rune_core::macros::list;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Default, Clone, Copy)]
+struct OpStats {
+    count: u64,
+    time: Duration,
+}
+
+#[derive(Default)]
+struct FnStats {
+    name: String,
+    calls: u64,
+    ops: u64,
+    time: Duration,
+}
+
+#[derive(Default)]
+struct Profile {
+    /// Keyed by the opcode byte.
+    opcodes: HashMap<u8, OpStats>,
+    /// Keyed by the address of the function's bytecode, which does not move
+    /// when the function is collected.
+    functions: HashMap<usize, FnStats>,
+    /// Keyed by the calling function and the offset of the call.
+    call_sites: HashMap<(usize, usize), u64>,
+}
+
+static PROFILE: LazyLock<Mutex<Profile>> = LazyLock::new(Mutex::default);
+
+fn key(func: &ByteFn) -> usize {
+    func.codes().as_ptr().addr()
+}
+
+/// Record a call to `func`.
+pub(super) fn enter(func: &ByteFn, name: &str) {
+    let mut profile = PROFILE.lock().unwrap();
+    let stats = profile.functions.entry(key(func)).or_default();
+    if stats.name.is_empty() || stats.name == "lambda" {
+        name.clone_into(&mut stats.name);
+    }
+    stats.calls += 1;
+}
+
+/// Record a call made from `caller` by the instruction ending at `offset`.
+pub(super) fn call_site(caller: &ByteFn, offset: usize) {
+    let mut profile = PROFILE.lock().unwrap();
+    *profile.call_sites.entry((key(caller), offset)).or_default() += 1;
+}
+
+thread_local! {
+    /// The instruction running on this thread, as its opcode byte, the key of
+    /// its function and when it started.
+    static CURRENT: Cell<Option<(u8, usize, Instant)>> = const { Cell::new(None) };
+}
+
+/// Charge the time since the current instruction started to it and clear it.
+fn charge(profile: &mut Profile, now: Instant) {
+    if let Some((op, func, start)) = CURRENT.take() {
+        let time = now - start;
+        profile.opcodes.entry(op).or_default().time += time;
+        profile.functions.entry(func).or_default().time += time;
+    }
+}
+
+/// Record that the VM dispatched the instruction `op` of `func`. This is the
+/// only place instructions are counted. The previous instruction on this
+/// thread runs until the next one is dispatched, so the time spent in a call
+/// is charged to the instructions of the callee and not also to the call.
+pub(super) fn dispatch(func: &ByteFn, op: u8) {
+    let now = Instant::now();
+    let mut profile = PROFILE.lock().unwrap();
+    charge(&mut profile, now);
+    profile.opcodes.entry(op).or_default().count += 1;
+    profile.functions.entry(key(func)).or_default().ops += 1;
+    CURRENT.set(Some((op, key(func), now)));
+}
+
+/// Record that the VM stopped running instructions, so that the time until it
+/// dispatches another one is not charged to the last instruction.
+pub(super) fn stop() {
+    let now = Instant::now();
+    charge(&mut PROFILE.lock().unwrap(), now);
+}
+
+fn op_name(op: u8) -> String {
+    OpCode::try_from(op).map_or_else(|_| op.to_string(), |op| format!("{op:?}"))
+}
+
+fn fn_name(profile: &Profile, func: usize) -> &str {
+    profile.functions.get(&func).map_or("", |x| &x.name)
+}
+
+/// Opcodes sorted by cumulative time.
+fn sorted_opcodes(profile: &Profile) -> Vec<(u8, OpStats)> {
+    let mut opcodes: Vec<_> = profile.opcodes.iter().map(|(k, v)| (*k, *v)).collect();
+    opcodes.sort_by_key(|x| std::cmp::Reverse(x.1.time));
+    opcodes
+}
+
+/// Functions sorted by cumulative time.
+fn sorted_functions(profile: &Profile) -> Vec<&FnStats> {
+    let mut functions: Vec<_> = profile.functions.values().collect();
+    functions.sort_by_key(|x| std::cmp::Reverse(x.time));
+    functions
+}
+
+/// Call sites sorted by count.
+fn sorted_call_sites(profile: &Profile) -> Vec<(&str, usize, u64)> {
+    let mut sites: Vec<_> = profile
+        .call_sites
+        .iter()
+        .map(|((func, offset), count)| (fn_name(profile, *func), *offset, *count))
+        .collect();
+    sites.sort_by_key(|x| std::cmp::Reverse(x.2));
+    sites
+}
+
+/// The profile as a list of the form
+///
+/// ((opcodes (NAME COUNT NANOSECONDS) ...)
+///  (functions (NAME CALLS OPS NANOSECONDS) ...)
+///  (call-sites (CALLER OFFSET COUNT) ...))
+pub(super) fn report<'ob>(reset: bool, cx: &'ob Context) -> Object<'ob> {
+    let mut profile = PROFILE.lock().unwrap();
+    let nanos = |x: Duration| x.as_nanos() as i64;
+    let opcodes: Vec<Object> = sorted_opcodes(&profile)
+        .into_iter()
+        .map(|(op, stats)| list![op_name(op), stats.count as i64, nanos(stats.time); cx])
+        .collect();
+    let functions: Vec<Object> = sorted_functions(&profile)
+        .into_iter()
+        .map(|x| list![x.name.as_str(), x.calls as i64, x.ops as i64, nanos(x.time); cx])
+        .collect();
+    let call_sites: Vec<Object> = sorted_call_sites(&profile)
+        .into_iter()
+        .map(|(func, offset, count)| list![func, offset as i64, count as i64; cx])
+        .collect();
+    if reset {
+        *profile = Profile::default();
+    }
+    let opcodes = crate::alloc::list(&opcodes, cx);
+    let functions = crate::alloc::list(&functions, cx);
+    let call_sites = crate::alloc::list(&call_sites, cx);
+    list![
+        Cons::new(sym::OPCODES, opcodes, cx),
+        Cons::new(sym::FUNCTIONS, functions, cx),
+        Cons::new(sym::CALL_SITES, call_sites, cx);
+        cx
+    ]
+}
+
+fn json_string(string: &str) -> String {
+    let mut out = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The profile as a JSON document.
+fn to_json() -> String {
+    let profile = PROFILE.lock().unwrap();
+    let opcodes: Vec<String> = sorted_opcodes(&profile)
+        .into_iter()
+        .map(|(op, stats)| {
+            let name = json_string(&op_name(op));
+            format!(
+                r#"{{"name":{name},"count":{},"nanos":{}}}"#,
+                stats.count,
+                stats.time.as_nanos()
+            )
+        })
+        .collect();
+    let functions: Vec<String> = sorted_functions(&profile)
+        .into_iter()
+        .map(|x| {
+            let name = json_string(&x.name);
+            let (calls, ops, nanos) = (x.calls, x.ops, x.time.as_nanos());
+            format!(r#"{{"name":{name},"calls":{calls},"ops":{ops},"nanos":{nanos}}}"#)
+        })
+        .collect();
+    let call_sites: Vec<String> = sorted_call_sites(&profile)
+        .into_iter()
+        .map(|(func, offset, count)| {
+            let func = json_string(func);
+            format!(r#"{{"caller":{func},"offset":{offset},"count":{count}}}"#)
+        })
+        .collect();
+    format!(
+        r#"{{"opcodes":[{}],"functions":[{}],"call_sites":[{}]}}"#,
+        opcodes.join(","),
+        functions.join(","),
+        call_sites.join(",")
+    )
+}
+
+/// Writes the profile as JSON to a file when dropped, so that it is dumped
+/// when `main` returns or unwinds from a panic. It is not written if the
+/// process exits through `std::process::exit` or an abort.
+pub(crate) struct DumpOnExit(pub(crate) String);
+
+impl Drop for DumpOnExit {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::write(&self.0, to_json()) {
+            eprintln!("Failed to write VM profile to {}: {e}", self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::{CallFrame, Env};
+    use crate::core::gc::RootSet;
+    use crate::core::object::IntoObject;
+    use rune_core::macros::root;
+    use OpCode::*;
+
+    fn make<'ob>(
+        args: i64,
+        codes: &[OpCode],
+        consts: Vec<Object>,
+        cx: &'ob Context,
+    ) -> &'ob ByteFn {
+        let codes: Vec<u8> = codes.iter().map(|&x| x as u8).collect();
+        let codes = codes.into_obj(cx).untag();
+        let consts = consts.into_obj(cx).untag();
+        crate::alloc::make_byte_code(args.into(), codes, consts, 5, &[], cx).unwrap()
+    }
+
+    #[test]
+    fn test_profile() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        // (lambda (x) (1+ x))
+        let inner = make(257, &[Duplicate, Add1, Return], vec![], cx);
+        // (lambda () (funcall inner 1) (funcall inner 2))
+        let outer = make(
+            0,
+            &[Constant0, Constant1, Call1, Discard, Constant0, Constant2, Call1, Return],
+            vec![inner.into(), 1.into(), 2.into()],
+            cx,
+        );
+        let (inner_key, outer_key) = (key(inner), key(outer));
+        root!(outer, cx);
+        root!(env, new(Env), cx);
+        let frame = &mut CallFrame::new(env);
+        frame.finalize_arguments();
+        let val = super::super::call(outer, 0, "profile-outer", frame, cx).unwrap();
+        assert_eq!(val, 3);
+
+        // Other tests run the VM concurrently, so only look at our functions
+        let profile = PROFILE.lock().unwrap();
+        let outer = &profile.functions[&outer_key];
+        assert_eq!((outer.name.as_str(), outer.calls, outer.ops), ("profile-outer", 1, 8));
+        let inner = &profile.functions[&inner_key];
+        assert_eq!((inner.calls, inner.ops), (2, 6));
+        assert_eq!(profile.call_sites[&(outer_key, 3)], 1);
+        assert_eq!(profile.call_sites[&(outer_key, 7)], 1);
+        assert!(profile.opcodes[&(Add1 as u8)].count >= 2);
+    }
+
+    #[test]
+    fn test_profile_nested() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        // (lambda (x) (1+ x))
+        let inner = make(257, &[Duplicate, Add1, Return], vec![], cx);
+        // (lambda () (apply inner '(1))), which runs inner in another VM
+        let args = list![1; cx];
+        let outer = make(
+            0,
+            &[Constant0, Constant1, Constant2, Call2, Return],
+            vec![sym::APPLY.into(), inner.into(), args],
+            cx,
+        );
+        let (inner_key, outer_key) = (key(inner), key(outer));
+        root!(outer, cx);
+        root!(env, new(Env), cx);
+        let frame = &mut CallFrame::new(env);
+        frame.finalize_arguments();
+        let val = super::super::call(outer, 0, "profile-nested", frame, cx).unwrap();
+        assert_eq!(val, 2);
+
+        // each instruction is counted once, in the function that runs it
+        let profile = PROFILE.lock().unwrap();
+        let outer = &profile.functions[&outer_key];
+        assert_eq!((outer.calls, outer.ops), (1, 5));
+        let inner = &profile.functions[&inner_key];
+        assert_eq!((inner.calls, inner.ops), (1, 3));
+    }
+}
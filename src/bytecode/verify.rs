@@ -0,0 +1,100 @@
+//! Verification of bytecode before it is executed.
+use super::disassemble::{decode, initial_depth, stack_depths};
+use crate::core::object::ByteFnPrototype;
+use anyhow::{bail, Result};
+
+/// Check that `func` is well formed so that the VM can run it without checking
+/// every operation. Every opcode must be valid with complete operands, jumps
+/// must land on an instruction, constant indices must be in range and the
+/// stack must stay within `func.depth`.
+pub(super) fn check(func: &ByteFnPrototype) -> Result<()> {
+    let code = decode(func.codes())?;
+    let consts = func.consts();
+    for insn in &code {
+        let uses_const =
+            matches!(insn.op.family(), Some("constant" | "varref" | "varset" | "varbind"));
+        if uses_const && usize::from(insn.arg) >= consts.len() {
+            bail!("Constant index {} out of range at offset {}", insn.arg, insn.offset);
+        }
+    }
+    let start = initial_depth(func);
+    let depths = stack_depths(&code, consts, start)?;
+    let max_depth = code
+        .iter()
+        .zip(depths)
+        .filter_map(|(insn, depth)| {
+            let after = depth? as i32 + insn.op.stack_effect(insn.arg);
+            Some(depth?.max(after as usize))
+        })
+        .fold(start, usize::max);
+    if max_depth > func.depth {
+        bail!("Stack depth {max_depth} exceeds the maximum of {}", func.depth);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::opcode::OpCode::{self, *};
+    use crate::core::env::sym;
+    use crate::core::gc::{Context, RootSet};
+    use crate::core::object::{ByteFn, IntoObject, Object};
+    use anyhow::Result;
+
+    fn make<'ob>(
+        codes: &[OpCode],
+        consts: Vec<Object>,
+        depth: usize,
+        cx: &'ob Context,
+    ) -> Result<&'ob ByteFn> {
+        let codes: Vec<u8> = codes.iter().map(|&x| x as u8).collect();
+        make_raw(&codes, consts, depth, cx)
+    }
+
+    fn make_raw<'ob>(
+        codes: &[u8],
+        consts: Vec<Object>,
+        depth: usize,
+        cx: &'ob Context,
+    ) -> Result<&'ob ByteFn> {
+        let codes = codes.to_vec().into_obj(cx).untag();
+        let consts = consts.into_obj(cx).untag();
+        let func = crate::alloc::make_byte_code(257.into(), codes, consts, depth, &[], cx)?;
+        crate::bytecode::verify(func, cx)?;
+        Ok(func)
+    }
+
+    #[test]
+    fn test_verify() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        sym::init_symbols();
+        let func = make(&[Duplicate, Constant0, Plus, Return], vec![1.into()], 3, cx).unwrap();
+        assert!(func.is_verified());
+        let closure = crate::alloc::make_closure(func, &[2.into()], cx).unwrap();
+        assert!(closure.untag().is_verified());
+
+        let invalid = |result: Result<&ByteFn>| {
+            let err = result.unwrap_err().to_string();
+            assert!(err.contains("invalid-byte-code"), "{err}");
+        };
+        // stack depth exceeds the maximum
+        invalid(make(&[Duplicate, Constant0, Plus, Return], vec![1.into()], 2, cx));
+        // constant out of range
+        invalid(make(&[Constant1, Return], vec![1.into()], 2, cx));
+        invalid(make(&[VarRef0, Return], vec![], 2, cx));
+        // invalid opcode
+        invalid(make_raw(&[51, Return as u8], vec![], 2, cx));
+        // truncated operand
+        invalid(make_raw(&[Duplicate as u8, Goto as u8, 0x00], vec![], 2, cx));
+        // jump out of range
+        invalid(make_raw(&[Goto as u8, 0x10, 0x00, Return as u8], vec![], 2, cx));
+
+        // closure prototypes from the compiler refer to constants that are only
+        // filled in later, so functions are not verified when they are made
+        let codes = vec![Constant0 as u8, Return as u8].into_obj(cx).untag();
+        let consts = Vec::<Object>::new().into_obj(cx).untag();
+        let func = crate::alloc::make_byte_code(257.into(), codes, consts, 2, &[], cx);
+        assert!(!func.unwrap().is_verified());
+    }
+}
@@ -0,0 +1,228 @@
+//! Calling commands interactively.
+use crate::core::{
+    env::{sym, CallFrame, Env},
+    gc::{Context, Rt, Rto},
+    object::{Function, Object, ObjectType, OptionalFlag, NIL},
+};
+use crate::data::LispError;
+use crate::minibuf::read_input;
+use anyhow::{bail, Result};
+use rune_core::macros::{list, rebind, root};
+use rune_macros::defun;
+use std::path::Path;
+
+#[defun]
+pub(crate) fn prefix_numeric_value(raw: Object) -> i64 {
+    match raw.untag() {
+        ObjectType::Int(n) => n,
+        ObjectType::Symbol(sym::SUB) => -1,
+        ObjectType::Cons(cons) => match cons.car().untag() {
+            ObjectType::Int(n) => n,
+            _ => 1,
+        },
+        _ => 1,
+    }
+}
+
+#[defun]
+fn call_interactively<'ob>(
+    function: &Rto<Function>,
+    record_flag: OptionalFlag,
+    _keys: OptionalFlag,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    let cmd: Object = function.bind(cx).into();
+    root!(cmd, cx);
+    let form = crate::data::interactive_form(cmd, env, cx)?;
+    if form.is_nil() {
+        let error = list![sym::WRONG_TYPE_ARGUMENT, sym::COMMANDP, cmd.bind(cx); cx];
+        bail!(LispError::new(error.try_into().unwrap()));
+    }
+    let spec = crate::fns::nth(1, form.try_into()?)?;
+    root!(spec, cx);
+    let frame = &mut CallFrame::new(env);
+    match spec.bind(cx).untag() {
+        ObjectType::String(codes) => {
+            let codes = codes.to_string();
+            push_interactive_args(&codes, frame, cx)?;
+        }
+        // Any other spec is a form that evaluates to the list of arguments
+        _ => {
+            let args = crate::interpreter::eval_toplevel(spec, frame, cx)?;
+            let args = rebind!(args, cx);
+            for arg in args.as_list()? {
+                frame.push_arg(arg?);
+            }
+        }
+    }
+    frame.finalize_arguments();
+    if record_flag.is_some() {
+        let args = crate::fns::slice_into_list(Rt::bind_slice(frame.arg_slice(), cx), None, cx);
+        let entry = crate::core::cons::Cons::new(cmd.bind(cx), args, cx);
+        let history = frame.var(sym::COMMAND_HISTORY).map_or(NIL, |x| x.bind(cx));
+        let history = crate::core::cons::Cons::new(entry, history, cx);
+        frame.set_var(sym::COMMAND_HISTORY, history.into(), cx)?;
+    }
+    function.call(frame, None, cx).map_err(Into::into)
+}
+
+/// Read the arguments described by the code letters of an interactive `spec`
+/// string and push them onto `frame`.
+fn push_interactive_args(spec: &str, frame: &mut CallFrame, cx: &Context) -> Result<()> {
+    let mut codes = spec;
+    // `*' `@' and `^' can come before the argument codes
+    while let Some(rest) = codes.strip_prefix(['*', '@', '^']) {
+        if codes.starts_with('*') {
+            barf_if_buffer_read_only(frame, cx)?;
+        }
+        codes = rest;
+    }
+    let prefix_arg = frame.var(sym::CURRENT_PREFIX_ARG).map_or(NIL, |x| x.bind(cx));
+    for line in codes.split('\n') {
+        let mut chars = line.chars();
+        let Some(code) = chars.next() else { continue };
+        // the prompt can refer to the arguments that were already read
+        let prompt = chars.as_str();
+        let prompt = if prompt.contains('%') {
+            let args = Rt::bind_slice(frame.arg_slice(), cx);
+            crate::editfns::format_message(prompt, args)?
+        } else {
+            prompt.to_owned()
+        };
+        let arg: Object = match code {
+            'p' => prefix_numeric_value(prefix_arg).into(),
+            'P' => prefix_arg,
+            'r' => {
+                let (beg, end) = crate::editfns::region(frame)?;
+                frame.push_arg(Object::from(beg as i64));
+                (end as i64).into()
+            }
+            's' => cx.add(read_input(&prompt, frame, cx)?),
+            'b' => {
+                let current = crate::buffer::current_buffer(frame, cx);
+                cx.add(crate::minibuf::read_buffer(
+                    &prompt,
+                    Some(current),
+                    Some(sym::TRUE.into()),
+                    None,
+                    frame,
+                    cx,
+                )?)
+            }
+            'f' => cx.add(read_existing_file_name(&prompt, frame, cx)?),
+            'n' => read_number(&prompt, frame, cx)?,
+            'i' => NIL,
+            x => {
+                let code = x as u32;
+                bail!("Invalid control letter `{x}' (#o{code:03o}, #x{code:04x}) in interactive calling string")
+            }
+        };
+        frame.push_arg(arg);
+    }
+    Ok(())
+}
+
+/// Signal `buffer-read-only' if the current buffer is read-only.
+fn barf_if_buffer_read_only(env: &Rt<Env>, cx: &Context) -> Result<()> {
+    let read_only = env.var(sym::BUFFER_READ_ONLY).is_some_and(|x| !x.bind(cx).is_nil());
+    if read_only {
+        let buffer = crate::buffer::current_buffer(env, cx);
+        let error = list![sym::BUFFER_READ_ONLY, buffer; cx];
+        bail!(LispError::new(error.try_into().unwrap()));
+    }
+    Ok(())
+}
+
+/// Read the name of a file that exists, asking again until one is given.
+fn read_existing_file_name(prompt: &str, env: &mut Rt<Env>, cx: &Context) -> Result<String> {
+    loop {
+        let input = read_input(prompt, env, cx)?;
+        let file = crate::fileio::expand_file_name(&input, None, env, cx)?;
+        if Path::new(&file).exists() {
+            return Ok(file);
+        }
+    }
+}
+
+/// Read a number, asking again until one is given.
+fn read_number<'ob>(prompt: &str, env: &mut Rt<Env>, cx: &'ob Context) -> Result<Object<'ob>> {
+    loop {
+        let input = read_input(prompt, env, cx)?;
+        if let Ok((number, _)) = crate::reader::read(&input, cx) {
+            if matches!(number.untag(), ObjectType::Int(_) | ObjectType::Float(_)) {
+                return Ok(number);
+            }
+        }
+    }
+}
+
+defvar!(CURRENT_PREFIX_ARG);
+defvar!(COMMAND_HISTORY);
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::assert_lisp;
+
+    #[test]
+    fn test_interactive_form() {
+        assert_lisp(
+            "(interactive-form #'(lambda (x) \"doc\" (interactive \"p\") x))",
+            "(interactive \"p\")",
+        );
+        assert_lisp(
+            "(interactive-form #'(lambda (x) (interactive \"p\" test-mode) x))",
+            "(interactive \"p\")",
+        );
+        assert_lisp("(interactive-form #'(lambda (x) x))", "nil");
+        assert_lisp("(progn (defalias 'test-cmd #'(lambda () (interactive) 1)) (interactive-form 'test-cmd))", "(interactive)");
+        assert_lisp("(progn (put 'test-cmd 'interactive-form '(interactive \"P\")) (interactive-form 'test-cmd))", "(interactive \"P\")");
+        assert_lisp(
+            "(interactive-form (make-byte-code 0 (unibyte-string 192 135) [nil] 1 nil \"p\"))",
+            "(interactive \"p\")",
+        );
+        assert_lisp(
+            "(aref (make-byte-code 0 (unibyte-string 192 135) [nil] 1 nil \"p\") 5)",
+            "\"p\"",
+        );
+        assert_lisp(
+            "(list (commandp #'(lambda () (interactive) 1)) (commandp #'(lambda () 1)) (commandp 'car)
+                   (commandp \"keys\") (commandp \"keys\" t) (commandp '(autoload \"file\" nil t)))",
+            "(t nil nil t nil t)",
+        );
+    }
+
+    #[test]
+    fn test_call_interactively() {
+        assert_lisp(
+            "(progn (setq minibuffer-input-queue '(\"foo\" \"bar\" \"12\"))
+               (call-interactively #'(lambda (s n) (interactive \"sString: \nnNumber: \") (list s n))))",
+            "(\"foo\" 12)",
+        );
+        assert_lisp(
+            "(progn (setq current-prefix-arg '(4))
+               (call-interactively #'(lambda (p raw i) (interactive \"p\nP\ni\") (list p raw i))))",
+            "(4 (4) nil)",
+        );
+        assert_lisp(
+            "(progn (insert \"hello\") (set-mark 2)
+               (call-interactively #'(lambda (beg end) (interactive \"*r\") (list beg end))))",
+            "(2 6)",
+        );
+        assert_lisp(
+            "(progn (setq minibuffer-input-queue '(\"\"))
+               (equal (call-interactively #'(lambda (b) (interactive \"bBuffer: \") b)) (buffer-name)))",
+            "t",
+        );
+        assert_lisp("(call-interactively #'(lambda (a b) (interactive (list 1 2)) (+ a b)))", "3");
+        assert_lisp(
+            "(progn (defalias 'test-cmd #'(lambda (n) (interactive (list 5)) n))
+               (call-interactively 'test-cmd t) command-history)",
+            "((test-cmd 5))",
+        );
+        assert_lisp(
+            "(condition-case err (call-interactively #'(lambda () 1)) (wrong-type-argument (nth 1 err)))",
+            "commandp",
+        );
+    }
+}
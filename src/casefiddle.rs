@@ -19,7 +19,7 @@ fn capitalize<'ob>(string_or_char: StringOrChar<'ob>, cx: &'ob Context<'ob>) ->
 }
 
 #[defun]
-fn upcase<'ob>(string_or_char: StringOrChar<'ob>, cx: &'ob Context<'ob>) -> Object<'ob> {
+pub(crate) fn upcase<'ob>(string_or_char: StringOrChar<'ob>, cx: &'ob Context<'ob>) -> Object<'ob> {
     match string_or_char {
         StringOrChar::String(s) => cx.add(casify_string(s, CaseMode::Upcase)),
         StringOrChar::Char(c) => cx.add(casify_char(c, char::to_uppercase)),
@@ -27,7 +27,10 @@ fn upcase<'ob>(string_or_char: StringOrChar<'ob>, cx: &'ob Context<'ob>) -> Obje
 }
 
 #[defun]
-fn downcase<'ob>(string_or_char: StringOrChar<'ob>, cx: &'ob Context<'ob>) -> Object<'ob> {
+pub(crate) fn downcase<'ob>(
+    string_or_char: StringOrChar<'ob>,
+    cx: &'ob Context<'ob>,
+) -> Object<'ob> {
     match string_or_char {
         StringOrChar::String(s) => cx.add(casify_string(s, CaseMode::Downcase)),
         StringOrChar::Char(c) => cx.add(casify_char(c, char::to_lowercase)),
@@ -1,6 +1,11 @@
-use crate::core::object::{CharTable, CharTableInner, Object, Symbol};
+use crate::core::object::{CharTable, CharTableInner, Object, ObjectType, Symbol};
 use rune_macros::defun;
 
+#[defun]
+fn char_table_p(object: Object) -> bool {
+    matches!(object.untag(), ObjectType::CharTable(_))
+}
+
 #[defun]
 fn make_char_table<'ob>(_purpose: Symbol<'ob>, init: Option<Object<'ob>>) -> CharTableInner<'ob> {
     CharTableInner::new(init)
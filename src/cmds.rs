@@ -0,0 +1,173 @@
+//! Simple editing commands.
+use crate::core::{
+    cons::Cons,
+    env::{sym, Env},
+    gc::{Context, Rt},
+};
+use crate::data::LispError;
+use anyhow::{bail, Result};
+use rune_macros::defun;
+use text_buffer::Buffer as TextBuffer;
+
+#[defun]
+pub(crate) fn forward_char(n: Option<i64>, env: &mut Rt<Env>, cx: &Context) -> Result<()> {
+    let text = &mut env.current_buffer.get_mut().text;
+    let point = text.cursor().chars() as i64;
+    let new_point = point + n.unwrap_or(1);
+    if new_point < 0 {
+        text.set_cursor(0);
+        bail!(LispError::new(Cons::new1(sym::BEGINNING_OF_BUFFER, cx)));
+    }
+    if new_point > text.len_chars() as i64 {
+        text.set_cursor(text.len_chars());
+        bail!(LispError::new(Cons::new1(sym::END_OF_BUFFER, cx)));
+    }
+    text.set_cursor(new_point as usize);
+    Ok(())
+}
+
+#[defun]
+fn backward_char(n: Option<i64>, env: &mut Rt<Env>, cx: &Context) -> Result<()> {
+    forward_char(Some(-n.unwrap_or(1)), env, cx)
+}
+
+/// Move N lines forward (backward if N is negative) and return the number of
+/// lines that could not be moved.
+#[defun]
+pub(crate) fn forward_line(n: Option<i64>, env: &mut Rt<Env>) -> i64 {
+    let text = &mut env.current_buffer.get_mut().text;
+    let count = n.unwrap_or(1);
+    let start = text.cursor().chars();
+    if count > 0 {
+        let mut pos = start;
+        let mut moved = 0;
+        while moved < count {
+            match find_newline_forward(text, pos) {
+                Some(newline) => {
+                    pos = newline + 1;
+                    moved += 1;
+                }
+                None => {
+                    pos = text.len_chars();
+                    break;
+                }
+            }
+        }
+        text.set_cursor(pos);
+        let mut shortage = count - moved;
+        // a partial last line counts as a line moved
+        if shortage > 0 && pos != start && text.char_at(pos - 1) != Some('\n') {
+            shortage -= 1;
+        }
+        shortage
+    } else {
+        let mut pos = line_beginning(text, start);
+        let mut moved = 0;
+        while moved < -count && pos > 0 {
+            pos = line_beginning(text, pos - 1);
+            moved += 1;
+        }
+        text.set_cursor(pos);
+        count + moved
+    }
+}
+
+#[defun]
+fn beginning_of_line(n: Option<i64>, env: &mut Rt<Env>) {
+    forward_line(Some(n.unwrap_or(1) - 1), env);
+}
+
+#[defun]
+pub(crate) fn end_of_line(n: Option<i64>, env: &mut Rt<Env>) {
+    let n = n.unwrap_or(1);
+    if n != 1 {
+        forward_line(Some(n - 1), env);
+    }
+    let text = &mut env.current_buffer.get_mut().text;
+    let end = find_newline_forward(text, text.cursor().chars()).unwrap_or(text.len_chars());
+    text.set_cursor(end);
+}
+
+/// Find the first newline at or after `pos`.
+fn find_newline_forward(text: &TextBuffer, pos: usize) -> Option<usize> {
+    let (s1, s2) = text.slice(pos..);
+    s1.chars().chain(s2.chars()).position(|c| c == '\n').map(|i| pos + i)
+}
+
+/// Find the start of the line containing `pos`.
+fn line_beginning(text: &TextBuffer, pos: usize) -> usize {
+    let (s1, s2) = text.slice(..pos);
+    let newline = s1.chars().chain(s2.chars()).rev().position(|c| c == '\n');
+    newline.map_or(0, |i| pos - i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::{Context, RootSet};
+    use rune_core::macros::root;
+
+    fn setup(env: &mut Rt<Env>, text: &str, point: usize) {
+        let buf = env.current_buffer.get_mut();
+        buf.text = TextBuffer::default();
+        buf.text.insert(text);
+        buf.text.set_cursor(point);
+    }
+
+    #[test]
+    fn test_forward_line() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, new(Env), cx);
+        setup(env, "foo\nbar\nbaz", 1);
+        assert_eq!(forward_line(None, env), 0);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 4);
+        assert_eq!(forward_line(Some(5), env), 3);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 11);
+        assert_eq!(forward_line(Some(-1), env), 0);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 4);
+        assert_eq!(forward_line(Some(-5), env), -4);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 0);
+        setup(env, "foo\n", 0);
+        assert_eq!(forward_line(Some(2), env), 1);
+        assert_eq!(forward_line(Some(0), env), 0);
+    }
+
+    #[test]
+    fn test_forward_char() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, new(Env), cx);
+        setup(env, "foo", 1);
+        forward_char(Some(2), env, cx).unwrap();
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 3);
+        assert!(forward_char(None, env, cx).is_err());
+        assert!(backward_char(Some(4), env, cx).is_err());
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 0);
+    }
+
+    #[test]
+    fn test_buffer_edge_errors() {
+        crate::interpreter::assert_lisp(
+            "(list (condition-case nil (backward-char) (beginning-of-buffer 'bob))
+                   (condition-case nil (forward-char) (end-of-buffer 'eob)))",
+            "(bob eob)",
+        );
+    }
+
+    #[test]
+    fn test_end_of_line() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, new(Env), cx);
+        setup(env, "foo\nbar\nbaz", 1);
+        end_of_line(None, env);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 3);
+        end_of_line(Some(2), env);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 7);
+        beginning_of_line(None, env);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 4);
+        end_of_line(Some(3), env);
+        assert_eq!(env.current_buffer.get().text.cursor().chars(), 11);
+    }
+}
@@ -3,8 +3,9 @@ use crate::derive_GcMoveable;
 use super::gc::{Block, GcHeap, GcState, Trace};
 use super::object::{CloneIn, Gc, IntoObject, ObjCell, Object, ObjectType, NIL};
 use anyhow::{anyhow, Result};
-use rune_core::hashmap::HashSet;
+use rune_core::hashmap::{HashMap, HashSet};
 use rune_macros::Trace;
+use std::cell::{Cell, RefCell};
 use std::fmt::{self, Debug, Display, Write};
 
 mod iter;
@@ -102,7 +103,59 @@ impl Cons {
 
 impl<'new> CloneIn<'new, &'new Cons> for Cons {
     fn clone_in<const C: bool>(&self, bk: &'new Block<C>) -> Gc<&'new Cons> {
-        Cons::new(self.car().clone_in(bk), self.cdr().clone_in(bk), bk).into_obj(bk)
+        let _scope = bk.cloned_conses.enter();
+        if let Some(new) = bk.cloned_conses.get(self) {
+            return new.into_obj(bk);
+        }
+        // Record the copy before cloning the contents so that a cycle back to
+        // this cell will find it
+        let new = Cons::new(NIL, NIL, bk);
+        bk.cloned_conses.insert(self, new);
+        unsafe {
+            new.0.car.as_mut().set(self.car().clone_in(bk));
+            new.0.cdr.as_mut().set(self.cdr().clone_in(bk));
+        }
+        new.into_obj(bk)
+    }
+}
+
+/// When copying cons cells, a cell that is reachable more than once, like in a
+/// circular list, should only be copied once. This keeps a mapping from old
+/// cells to new. It only holds the cells of the object being cloned, because
+/// the addresses are reused once garbage is collected.
+#[derive(Default)]
+pub(in crate::core) struct ClonedConsMap {
+    map: RefCell<HashMap<*const Cons, *const Cons>>,
+    /// The number of cons cells being cloned.
+    depth: Cell<usize>,
+}
+
+/// Clears the [`ClonedConsMap`] when the outermost cons cell being cloned is
+/// done.
+struct CloneScope<'a>(&'a ClonedConsMap);
+
+impl Drop for CloneScope<'_> {
+    fn drop(&mut self) {
+        let depth = self.0.depth.get() - 1;
+        self.0.depth.set(depth);
+        if depth == 0 {
+            self.0.map.borrow_mut().clear();
+        }
+    }
+}
+
+impl ClonedConsMap {
+    fn get<'a>(&'a self, cons: &Cons) -> Option<&'a Cons> {
+        self.map.borrow().get(&(cons as *const Cons)).map(|x| unsafe { &**x })
+    }
+
+    fn insert(&self, old: &Cons, new: &Cons) {
+        self.map.borrow_mut().insert(old, new);
+    }
+
+    fn enter(&self) -> CloneScope<'_> {
+        self.depth.set(self.depth.get() + 1);
+        CloneScope(self)
     }
 }
 
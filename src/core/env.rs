@@ -1,6 +1,8 @@
-use super::gc::{Context, ObjectMap, Rto, Slot};
+use super::gc::{Context, IntoRoot, ObjectMap, Rt, Rto, Slot};
 use super::object::{LispBuffer, Object, OpenBuffer, Symbol, WithLifetime};
-use anyhow::{anyhow, Result};
+use crate::data::LispError;
+use anyhow::{bail, Result};
+use rune_core::macros::list;
 use rune_macros::Trace;
 use std::cell::OnceCell;
 
@@ -12,19 +14,121 @@ pub(crate) use symbol_map::*;
 type PropertyMap<'a> = ObjectMap<Slot<Symbol<'a>>, Vec<(Slot<Symbol<'a>>, Slot<Object<'a>>)>>;
 #[derive(Debug, Default, Trace)]
 pub(crate) struct Env<'a> {
+    /// The default values of variables. Use [var](RootedEnv::var) to get the
+    /// value in the current buffer.
     pub(crate) vars: ObjectMap<Slot<Symbol<'a>>, Slot<Object<'a>>>,
+    /// The buffer-local values of variables.
+    pub(crate) buffer_locals: ObjectMap<Slot<Symbol<'a>>, BufferLocal<'a>>,
+    /// Variables that are aliases, mapped to the variable they refer to.
+    pub(crate) var_aliases: ObjectMap<Slot<Symbol<'a>>, Slot<Symbol<'a>>>,
+    /// Variables whose watchers are running. They are not notified again
+    /// until their watchers return.
+    pub(crate) notifying: Vec<Slot<Symbol<'a>>>,
+    /// Variables declared with `(defvar VAR)` in the current lexical scope.
+    /// They are bound dynamically there without being globally special.
+    pub(crate) local_specials: Vec<Slot<Symbol<'a>>>,
     pub(crate) props: PropertyMap<'a>,
+    /// The functions defined while an autoload is loading its file, along
+    /// with their previous definitions, so that they can be restored if the
+    /// load fails.
+    pub(crate) autoload_queue: Vec<(Slot<Symbol<'a>>, Slot<Object<'a>>)>,
+    /// The number of autoloads that are loading their files.
+    #[no_trace]
+    pub(crate) autoload_depth: usize,
     pub(crate) catch_stack: Vec<Slot<Object<'a>>>,
+    /// Every active `condition-case` and `handler-bind` handler, innermost
+    /// last
+    pub(crate) condition_stack: Vec<ConditionHandler<'a>>,
     exception: (Slot<Object<'a>>, Slot<Object<'a>>),
     #[no_trace]
     exception_id: u32,
-    binding_stack: Vec<(Slot<Symbol<'a>>, Option<Slot<Object<'a>>>)>,
+    binding_stack: Vec<Binding<'a>>,
     pub(crate) match_data: Slot<Object<'a>>,
     #[no_trace]
     pub(crate) current_buffer: CurrentBuffer<'a>,
     pub(crate) stack: LispStack<'a>,
 }
 
+/// The buffer-local values of a variable.
+#[derive(Debug, Default, Trace)]
+pub(crate) struct BufferLocal<'a> {
+    /// Whether setting the variable makes it local to the current buffer, as
+    /// with `make-variable-buffer-local`.
+    #[no_trace]
+    pub(crate) automatic: bool,
+    /// The buffers where the variable is local, along with its value there.
+    /// The value is `None` if the local binding is void.
+    pub(crate) values: Vec<(Slot<&'a LispBuffer>, Option<Slot<Object<'a>>>)>,
+}
+
+/// An entry in the binding stack. Most entries are dynamic variable bindings,
+/// but the stack also records any other state that needs to be restored when
+/// it is unwound, such as the current buffer or an `unwind-protect` handler.
+#[derive(Debug, Trace)]
+pub(crate) enum Binding<'a> {
+    /// A dynamic variable and the value it had before it was bound.
+    Var(Slot<Symbol<'a>>, Option<Slot<Object<'a>>>),
+    /// A variable that was local to the buffer when it was bound, and the
+    /// buffer-local value it had before.
+    LocalVar(Slot<Symbol<'a>>, Slot<&'a LispBuffer>, Option<Slot<Object<'a>>>),
+    /// A function to call when this entry is unbound.
+    UnwindProtect(Slot<Object<'a>>),
+    /// The buffer and point saved by `save-excursion`.
+    Excursion(Slot<&'a LispBuffer>, usize),
+    /// The buffer saved by `save-current-buffer`.
+    CurrentBuffer(Slot<&'a LispBuffer>),
+    /// Saved by `save-restriction`. There is nothing to restore until
+    /// narrowing is supported.
+    Restriction,
+}
+
+/// An entry in the condition stack.
+#[derive(Debug, Trace)]
+pub(crate) enum ConditionHandler<'a> {
+    /// The condition spec of a `condition-case` clause.
+    Case(Slot<Object<'a>>),
+    /// The conditions and handler function of a `handler-bind`, along with the
+    /// index of the first handler bound by the same form.
+    Bind(Slot<Object<'a>>, Slot<Object<'a>>, usize),
+    /// Pushed while a `handler-bind` handler runs. Errors it signals skip the
+    /// handlers from here down to the given index.
+    Skip(usize),
+}
+
+impl<'new> IntoRoot<ConditionHandler<'new>> for ConditionHandler<'_> {
+    unsafe fn into_root(self) -> ConditionHandler<'new> {
+        self.with_lifetime()
+    }
+}
+
+impl<'old, 'new> WithLifetime<'new> for ConditionHandler<'old> {
+    type Out = ConditionHandler<'new>;
+
+    unsafe fn with_lifetime(self) -> Self::Out {
+        std::mem::transmute::<ConditionHandler<'old>, ConditionHandler<'new>>(self)
+    }
+}
+
+impl<'new> IntoRoot<BufferLocal<'new>> for BufferLocal<'_> {
+    unsafe fn into_root(self) -> BufferLocal<'new> {
+        std::mem::transmute::<BufferLocal<'_>, BufferLocal<'new>>(self)
+    }
+}
+
+impl<'new> IntoRoot<Binding<'new>> for Binding<'_> {
+    unsafe fn into_root(self) -> Binding<'new> {
+        self.with_lifetime()
+    }
+}
+
+impl<'old, 'new> WithLifetime<'new> for Binding<'old> {
+    type Out = Binding<'new>;
+
+    unsafe fn with_lifetime(self) -> Self::Out {
+        std::mem::transmute::<Binding<'old>, Binding<'new>>(self)
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct CurrentBuffer<'a> {
     buffer: OnceCell<OpenBuffer<'a>>,
@@ -81,15 +185,227 @@ impl PartialEq<LispBuffer> for CurrentBuffer<'_> {
 
 // RootedEnv created by #[derive(Trace)]
 impl<'a> RootedEnv<'a> {
-    pub(crate) fn set_var(&mut self, sym: Symbol, value: Object) -> Result<()> {
+    /// The value of `var` in the current buffer. This is the buffer-local
+    /// value if it has one, and the default value otherwise.
+    pub(crate) fn var(&self, var: Symbol) -> Option<&Rto<Object<'a>>> {
+        let var = self.resolve(var);
+        match self.local_value(var) {
+            Some(value) => value.as_ref(),
+            None => self.vars.get(var),
+        }
+    }
+
+    /// The default value of `var`.
+    pub(crate) fn default_value(&self, var: Symbol) -> Option<&Rto<Object<'a>>> {
+        self.vars.get(self.resolve(var))
+    }
+
+    /// Follow the chain of aliases from `var` to the variable that holds its
+    /// value.
+    pub(crate) fn indirect_variable<'ob>(
+        &self,
+        mut var: Symbol<'ob>,
+        cx: &'ob Context,
+    ) -> Symbol<'ob> {
+        while let Some(base) = self.var_aliases.get(var) {
+            var = base.bind(cx);
+        }
+        var
+    }
+
+    /// Like [indirect_variable](Self::indirect_variable), for when there is no
+    /// context to bind to.
+    fn resolve<'b>(&'b self, mut var: Symbol<'b>) -> Symbol<'b> {
+        while let Some(base) = self.var_aliases.get(var) {
+            // SAFETY: the alias is rooted by the environment, which is
+            // borrowed for as long as the symbol is used
+            var = unsafe { base.bind_unchecked() };
+        }
+        var
+    }
+
+    /// Make `new` an alias for the variable `base`. If `new` has a value and
+    /// `base` does not, `base` takes the value of `new`.
+    pub(crate) fn defvaralias(&mut self, new: Symbol, base: Symbol, cx: &Context) -> Result<()> {
+        if new.is_const() {
+            bail!("Cannot make a constant an alias: {new}");
+        }
+        if self.indirect_variable(base, cx) == new {
+            let error = list![sym::CYCLIC_VARIABLE_INDIRECTION, base; cx];
+            bail!(LispError::new(error.try_into().unwrap()));
+        }
+        let let_bound = self.binding_stack.iter().any(|binding| match &**binding {
+            RootedBinding::Var(sym, _) | RootedBinding::LocalVar(sym, ..) => *sym == new,
+            _ => false,
+        });
+        if let_bound {
+            bail!("Don't know how to make a let-bound variable an alias: {new}");
+        }
+        let base_var = self.indirect_variable(base, cx);
+        if self.vars.get(base_var).is_none() {
+            if let Some(value) = self.vars.get(new).map(|x| x.bind(cx)) {
+                self.vars.insert(base_var, value);
+            }
+        }
+        self.vars.remove(new);
+        self.buffer_locals.remove(new);
+        self.var_aliases.insert(new, base);
+        new.make_special();
+        base.make_special();
+        Ok(())
+    }
+
+    /// The buffer-local value of `var` in the current buffer, or `None` if it
+    /// is not local there.
+    fn local_value(&self, var: Symbol) -> Option<&Rt<Option<Slot<Object<'a>>>>> {
+        self.buffer_local_value(var, self.current_buffer.buf_ref)
+    }
+
+    /// The buffer-local value of `var` in `buffer`, or `None` if it is not
+    /// local there.
+    pub(crate) fn buffer_local_value(
+        &self,
+        var: Symbol,
+        buffer: &LispBuffer,
+    ) -> Option<&Rt<Option<Slot<Object<'a>>>>> {
+        let local = self.buffer_locals.get(self.resolve(var))?;
+        local.values.iter().find(|x| x.0 == buffer).map(|x| &x.1)
+    }
+
+    /// Set the value of `var` in the current buffer. If it is not local there,
+    /// this sets the default value unless the variable automatically becomes
+    /// local when set. Like Emacs, it does not become local while a `let`
+    /// binds its default value, so that the binding is what gets set.
+    pub(crate) fn set_var(&mut self, sym: Symbol, value: Object, cx: &Context) -> Result<()> {
         if sym.is_const() {
-            Err(anyhow!("Attempt to set a constant symbol: {sym}"))
-        } else {
+            return Err(LispError::setting_constant(sym, cx).into());
+        }
+        let sym = self.indirect_variable(sym, cx);
+        let buffer = self.current_buffer.buf_ref;
+        let Some(local) = self.buffer_locals.get_mut(sym) else {
             self.vars.insert(sym, value);
-            Ok(())
+            return Ok(());
+        };
+        if let Some(entry) = local.values.iter_mut().find(|x| x.0 == buffer) {
+            entry.1.set(Some(value));
+            return Ok(());
+        }
+        if local.automatic && !self.let_shadows_default(sym, cx) {
+            let local = self.buffer_locals.get_mut(sym).unwrap();
+            local.values.push((buffer, Some(value)));
+            return Ok(());
+        }
+        self.vars.insert(sym, value);
+        Ok(())
+    }
+
+    /// Whether a `let` is binding the default value of `var`.
+    fn let_shadows_default(&self, var: Symbol, cx: &Context) -> bool {
+        let bindings = self.binding_stack.bind_ref(cx);
+        bindings.iter().any(|x| matches!(x, Binding::Var(sym, _) if **sym == var))
+    }
+
+    /// Make `var` void in the current buffer.
+    pub(crate) fn unset_var(&mut self, var: Symbol, cx: &Context) {
+        let var = self.indirect_variable(var, cx);
+        let buffer = self.current_buffer.buf_ref;
+        match self.local_value(var) {
+            Some(_) => self.set_local_value(var, buffer, None),
+            None => self.vars.remove(var),
         }
     }
 
+    /// Set the default value of `var`, which is the value in buffers where it
+    /// is not local.
+    pub(crate) fn set_default(&mut self, sym: Symbol, value: Object, cx: &Context) -> Result<()> {
+        if sym.is_const() {
+            return Err(LispError::setting_constant(sym, cx).into());
+        }
+        let sym = self.indirect_variable(sym, cx);
+        self.vars.insert(sym, value);
+        Ok(())
+    }
+
+    /// Give `var` a value local to the current buffer, which starts as the
+    /// default value. Does nothing if it is already local.
+    pub(crate) fn make_local_variable(&mut self, var: Symbol, cx: &Context) -> Result<()> {
+        if var.is_const() {
+            return Err(LispError::setting_constant(var, cx).into());
+        }
+        let var = self.indirect_variable(var, cx);
+        if self.local_value(var).is_some() {
+            return Ok(());
+        }
+        let buffer = self.current_buffer.buf_ref;
+        let default = self.vars.get(var).map(|x| x.bind(cx));
+        match self.buffer_locals.get_mut(var) {
+            Some(local) => local.values.push((buffer, default)),
+            None => {
+                let values = vec![(Slot::new(buffer), default.map(Slot::new))];
+                self.buffer_locals.insert(var, BufferLocal { automatic: false, values });
+            }
+        }
+        Ok(())
+    }
+
+    /// Make `var` become local to the current buffer whenever it is set.
+    pub(crate) fn make_variable_buffer_local(&mut self, var: Symbol, cx: &Context) -> Result<()> {
+        if var.is_const() {
+            return Err(LispError::setting_constant(var, cx).into());
+        }
+        let var = self.indirect_variable(var, cx);
+        match self.buffer_locals.get_mut(var) {
+            Some(local) => local.automatic = true,
+            None => {
+                let local = BufferLocal { automatic: true, values: Vec::new() };
+                self.buffer_locals.insert(var, local);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the buffer-local value of `var` in `buffer`, if it has one.
+    pub(crate) fn kill_local_variable(&mut self, var: Symbol, buffer: &LispBuffer, cx: &Context) {
+        let var = self.indirect_variable(var, cx);
+        if let Some(local) = self.buffer_locals.get_mut(var) {
+            if let Some(idx) = local.values.iter().position(|x| x.0 == buffer) {
+                local.values.swap_remove(idx);
+            }
+        }
+    }
+
+    /// Remove all the buffer-local values of `buffer`, such as when it is
+    /// killed.
+    pub(crate) fn kill_buffer_locals(&mut self, buffer: &LispBuffer) {
+        for local in self.buffer_locals.values_mut() {
+            if let Some(idx) = local.values.iter().position(|x| x.0 == buffer) {
+                local.values.swap_remove(idx);
+            }
+        }
+    }
+
+    /// The variables local to `buffer` and their values there.
+    pub(crate) fn buffer_local_values<'ob>(
+        &self,
+        buffer: &LispBuffer,
+        cx: &'ob Context,
+    ) -> Vec<(Symbol<'ob>, Option<Object<'ob>>)> {
+        let mut values = Vec::new();
+        for (var, local) in self.buffer_locals.iter() {
+            if let Some(entry) = local.values.iter().find(|x| x.0 == buffer) {
+                values.push((var.bind(cx), entry.1.as_ref().map(|x| x.bind(cx))));
+            }
+        }
+        values
+    }
+
+    /// Whether `var` is local in `buffer`, or becomes local when set if
+    /// `if_set` is true.
+    pub(crate) fn is_local(&self, var: Symbol, buffer: &LispBuffer, if_set: bool) -> bool {
+        let automatic = self.buffer_locals.get(self.resolve(var)).is_some_and(|x| x.automatic);
+        (if_set && automatic) || self.buffer_local_value(var, buffer).is_some()
+    }
+
     pub(crate) fn set_prop(&mut self, symbol: Symbol, propname: Symbol, value: Object) {
         match self.props.get_mut(symbol) {
             Some(plist) => match plist.iter_mut().find(|x| x.0 == propname) {
@@ -113,42 +429,205 @@ impl<'a> RootedEnv<'a> {
         (id == self.exception_id).then_some((&self.exception.0, &self.exception.1))
     }
 
+    /// Dynamically bind `var` to `value`. If `var` is local to the current
+    /// buffer then only the local value is bound, and otherwise the default
+    /// value is.
     pub(crate) fn varbind(&mut self, var: Symbol, value: Object, cx: &Context) {
+        let var = self.indirect_variable(var, cx);
+        if let Some(prev_value) = self.local_value(var) {
+            let prev_value = prev_value.as_ref().map(|x| x.bind(cx));
+            let buffer = self.current_buffer.buf_ref;
+            let binding =
+                Binding::LocalVar(Slot::new(var), Slot::new(buffer), prev_value.map(Slot::new));
+            self.binding_stack.push(binding);
+            self.set_local_value(var, buffer, Some(value));
+            return;
+        }
         let prev_value = self.vars.get(var).map(|x| x.bind(cx));
-        self.binding_stack.push((var, prev_value));
+        self.binding_stack.push(Binding::Var(Slot::new(var), prev_value.map(Slot::new)));
         self.vars.insert(var, value);
     }
 
+    /// Record `function` to be called when this binding is unwound. Unlike
+    /// other bindings, this has to be removed with
+    /// [pop_unwind_protect](Self::pop_unwind_protect) so that the caller can
+    /// run it.
+    pub(crate) fn record_unwind_protect(&mut self, function: Object) {
+        self.binding_stack.push(Binding::UnwindProtect(Slot::new(function)));
+    }
+
+    /// Record the current buffer and point to be restored on unbind.
+    pub(crate) fn record_excursion(&mut self, cx: &Context) {
+        let buffer = self.current_buffer.get();
+        let point = buffer.text.cursor().chars();
+        let buffer = buffer.lisp_buffer(cx);
+        self.binding_stack.push(Binding::Excursion(Slot::new(buffer), point));
+    }
+
+    /// Record the current buffer to be restored on unbind.
+    pub(crate) fn record_current_buffer(&mut self, cx: &Context) {
+        let buffer = self.current_buffer.get().lisp_buffer(cx);
+        self.binding_stack.push(Binding::CurrentBuffer(Slot::new(buffer)));
+    }
+
+    pub(crate) fn record_restriction(&mut self) {
+        self.binding_stack.push(Binding::Restriction);
+    }
+
+    /// The number of entries in the binding stack.
+    pub(crate) fn binding_depth(&self) -> usize {
+        self.binding_stack.len()
+    }
+
+    /// If the most recent binding is a variable binding, the variable along
+    /// with the value it will be restored to, and the buffer if the binding is
+    /// buffer-local.
+    pub(crate) fn peek_var_binding<'ob>(
+        &self,
+        cx: &'ob Context,
+    ) -> Option<(Symbol<'ob>, Option<Object<'ob>>, Option<&'ob LispBuffer>)> {
+        match self.binding_stack.bind_ref(cx).last()? {
+            Binding::Var(sym, prev) => Some((**sym, prev.as_deref().copied(), None)),
+            Binding::LocalVar(sym, buffer, prev) => {
+                Some((**sym, prev.as_deref().copied(), Some(**buffer)))
+            }
+            _ => None,
+        }
+    }
+
+    /// If the most recent binding is an `unwind-protect` handler, remove it
+    /// and return the function to call.
+    pub(crate) fn pop_unwind_protect<'ob>(&mut self, cx: &'ob Context) -> Option<Object<'ob>> {
+        let Some(Binding::UnwindProtect(func)) = self.binding_stack.bind_mut(cx).last() else {
+            return None;
+        };
+        let func = **func;
+        self.binding_stack.pop();
+        Some(func)
+    }
+
     pub(crate) fn unbind(&mut self, count: u16, cx: &Context) {
         for _ in 0..count {
             match self.binding_stack.bind_mut(cx).pop() {
-                Some((sym, val)) => match val {
+                Some(Binding::Var(sym, val)) => match val {
                     Some(val) => self.vars.insert(*sym, *val),
                     None => self.vars.remove(*sym),
                 },
+                // the local value is only restored if it is still local
+                Some(Binding::LocalVar(sym, buffer, val)) => {
+                    self.set_local_value(*sym, *buffer, val.map(|x| *x));
+                }
+                Some(Binding::Excursion(buffer, point)) => {
+                    self.set_buffer(*buffer);
+                    self.current_buffer.get_mut().text.set_cursor(point);
+                }
+                Some(Binding::CurrentBuffer(buffer)) => self.set_buffer(*buffer),
+                Some(Binding::Restriction) => {}
+                Some(Binding::UnwindProtect(_)) => {
+                    panic!("unwind-protect handlers must be removed with pop_unwind_protect")
+                }
                 None => panic!("Binding stack was empty"),
             }
         }
     }
 
-    pub(crate) fn defvar(&mut self, var: Symbol, value: Object) -> Result<()> {
+    /// Set the value of `var` in `buffer` if it is local there.
+    fn set_local_value(&mut self, var: Symbol, buffer: &LispBuffer, value: Option<Object>) {
+        if let Some(local) = self.buffer_locals.get_mut(var) {
+            if let Some(entry) = local.values.iter_mut().find(|x| x.0 == buffer) {
+                entry.1.set(value);
+            }
+        }
+    }
+
+    pub(crate) fn defvar(&mut self, var: Symbol, value: Object, cx: &Context) -> Result<()> {
         // TOOD: Handle `eval-sexp` on defvar, which should always update the
         // value
+        let var = self.indirect_variable(var, cx);
+        var.make_special();
         if self.vars.get(var).is_none() {
-            self.set_var(var, value)?;
-            var.make_special();
+            self.set_default(var, value, cx)?;
         }
 
         // If this variable was unbound previously in the binding stack,
         // we will bind it to the new value
         for binding in &mut *self.binding_stack {
-            if binding.0 == var && binding.1.is_none() {
-                binding.1.set(Some(value));
+            if let RootedBinding::Var(sym, prev) = &mut **binding {
+                if *sym == var && prev.is_none() {
+                    prev.set(Some(value));
+                }
             }
         }
         Ok(())
     }
 
+    /// The value of `var` outside of any dynamic bindings, or `None` if it is
+    /// void there.
+    pub(crate) fn toplevel_value<'ob>(&self, var: Symbol, cx: &'ob Context) -> Option<Object<'ob>> {
+        let var = self.indirect_variable(var, cx);
+        for binding in self.binding_stack.iter() {
+            if let RootedBinding::Var(sym, prev) = &**binding {
+                if *sym == var {
+                    return prev.as_ref().map(|x| x.bind(cx));
+                }
+            }
+        }
+        self.vars.get(var).map(|x| x.bind(cx))
+    }
+
+    /// Set the value of `var` outside of any dynamic bindings.
+    pub(crate) fn set_toplevel_value(
+        &mut self,
+        var: Symbol,
+        value: Object,
+        cx: &Context,
+    ) -> Result<()> {
+        let var = self.indirect_variable(var, cx);
+        for binding in &mut *self.binding_stack {
+            if let RootedBinding::Var(sym, prev) = &mut **binding {
+                if *sym == var {
+                    prev.set(Some(value));
+                    return Ok(());
+                }
+            }
+        }
+        self.set_default(var, value, cx)
+    }
+
+    /// Swap the values of the variables bound above `depth` with the values
+    /// they had before they were bound. With `unwind` this restores the
+    /// dynamic environment as it was at `depth`, and calling it again without
+    /// `unwind` undoes that.
+    pub(crate) fn swap_bindings(&mut self, depth: usize, unwind: bool, cx: &Context) {
+        let len = self.binding_stack.len();
+        let swap = |env: &mut Self, idx: usize| match &mut env.binding_stack.bind_mut(cx)[idx] {
+            Binding::Var(sym, prev) => {
+                let current = env.vars.get(**sym).map(|x| x.bind(cx));
+                match prev {
+                    Some(prev) => env.vars.insert(**sym, **prev),
+                    None => env.vars.remove(**sym),
+                }
+                *prev = current.map(Slot::new);
+            }
+            Binding::LocalVar(sym, buffer, prev) => {
+                let (sym, buffer) = (**sym, **buffer);
+                let Some(local) = env.buffer_locals.get_mut(sym) else { return };
+                let Some(entry) = local.values.iter_mut().find(|x| x.0 == buffer) else {
+                    return;
+                };
+                let current = entry.1.as_ref().map(|x| x.bind(cx));
+                entry.1.set(prev.as_ref().map(|x| **x));
+                *prev = current.map(Slot::new);
+            }
+            _ => {}
+        };
+        if unwind {
+            (depth..len).rev().for_each(|i| swap(self, i));
+        } else {
+            (depth..len).for_each(|i| swap(self, i));
+        }
+    }
+
     pub(crate) fn set_buffer(&mut self, buffer: &LispBuffer) {
         if buffer == self.current_buffer.buf_ref {
             return;
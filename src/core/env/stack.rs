@@ -18,6 +18,9 @@ pub(crate) struct LispStack<'a> {
     vec: Vec<Slot<Object<'a>>>,
     #[no_trace]
     current: Frame,
+    /// The function called in the current frame, or nil if it is not known
+    /// yet.
+    func: Slot<Object<'a>>,
     frames: Vec<FrameStore<'a>>,
 }
 
@@ -34,11 +37,21 @@ struct Frame {
     /// second is boolean indicating if the last argument is a cons with the
     /// remaining variadic arguments.
     arg_cnt: (u16, bool),
+    /// False if the arguments are the unevaluated forms of a special form.
+    evaluated: bool,
+    /// Depth of the binding stack when the frame was pushed.
+    binding_depth: usize,
 }
 
 impl Default for Frame {
     fn default() -> Self {
-        Self { start: 0, end: usize::MAX, arg_cnt: (0, false) }
+        Self {
+            start: 0,
+            end: usize::MAX,
+            arg_cnt: (0, false),
+            evaluated: true,
+            binding_depth: 0,
+        }
     }
 }
 
@@ -53,6 +66,7 @@ struct ByteFrame<'a> {
 struct FrameStore<'a> {
     #[no_trace]
     frame: Frame,
+    func: Slot<Object<'a>>,
     bytecode: Option<ByteFrame<'a>>,
 }
 
@@ -64,14 +78,30 @@ impl<'new> IntoRoot<FrameStore<'new>> for FrameStore<'_> {
 
 impl<'ob> FrameStore<'ob> {
     fn new(frame: Frame) -> Self {
-        Self { frame, bytecode: None }
+        Self { frame, func: Slot::new(NIL), bytecode: None }
     }
 
     fn new_bytecode(frame: Frame, func: &'ob ByteFn, pc_offset: usize) -> Self {
-        Self { frame, bytecode: Some(ByteFrame { func: Slot::new(func), pc_offset }) }
+        let bytecode = Some(ByteFrame { func: Slot::new(func), pc_offset });
+        Self { frame, func: Slot::new(NIL), bytecode }
     }
 }
 
+/// A frame of the Lisp backtrace, as returned by
+/// [backtrace](RootedLispStack::backtrace).
+pub(crate) struct BacktraceFrame<'brw, 'a> {
+    /// The function that was called
+    pub(crate) func: &'brw Rto<Object<'a>>,
+    /// The arguments of the call. If `rest` is true, the last one is a list of
+    /// the remaining variadic arguments.
+    pub(crate) args: &'brw [Rto<Object<'a>>],
+    pub(crate) rest: bool,
+    /// False if `args` holds the unevaluated forms of a special form.
+    pub(crate) evaluated: bool,
+    /// Depth of the binding stack when the function was called
+    pub(crate) binding_depth: usize,
+}
+
 impl<'old, 'new> WithLifetime<'new> for FrameStore<'old> {
     type Out = FrameStore<'new>;
 
@@ -130,16 +160,22 @@ impl<'a> Index<RangeTo<usize>> for RootedLispStack<'a> {
 }
 
 impl<'a> RootedLispStack<'a> {
+    /// Push a frame for a call to the bytecode function `func` from `caller`,
+    /// which will resume at `pc` when it returns.
     pub(crate) fn push_bytecode_frame(
         &mut self,
         start: usize,
         depth: usize,
-        func: &ByteFn,
+        func: Object,
+        caller: &ByteFn,
         pc: usize,
+        binding_depth: usize,
     ) {
         assert!(start <= self.len());
         assert!(self.current.start <= start);
-        self.frames.push(FrameStore::new_bytecode(self.current, func, pc));
+        self.frames.push(FrameStore::new_bytecode(self.current, caller, pc));
+        self.save_frame_function();
+        self.func.set(func);
         let end = start + depth;
         // allocate space so that we don't have to reallocate later. This will
         // also let us do unchecked pushes later.
@@ -147,26 +183,61 @@ impl<'a> RootedLispStack<'a> {
             assert!(end - self.vec.len() < 100); // make sure this doesn't blow up
             self.vec.reserve(end - self.vec.len());
         }
-        self.current = Frame { start, end, ..Frame::default() };
+        self.current = Frame { start, end, binding_depth, ..Frame::default() };
     }
 
-    pub(crate) fn push_frame(&mut self, arg_cnt: usize) {
+    pub(crate) fn push_frame(&mut self, arg_cnt: usize, binding_depth: usize) {
         assert!(arg_cnt <= self.len());
         let start = self.len() - arg_cnt;
         assert!(self.current.start <= start);
         self.frames.push(FrameStore::new(self.current));
-        self.current =
-            Frame { start, arg_cnt: (u16::try_from(arg_cnt).unwrap(), false), ..Frame::default() };
+        self.save_frame_function();
+        self.func.set(NIL);
+        let arg_cnt = (u16::try_from(arg_cnt).unwrap(), false);
+        self.current = Frame { start, arg_cnt, binding_depth, ..Frame::default() };
     }
 
     /// Remove all the stack variables in the current frame and switch to the
     /// previous one
     pub(crate) fn pop_frame(&mut self) {
         self.vec.truncate(self.current.start);
-        self.current = self.frames.last().unwrap().frame;
+        let prev = self.frames.last().unwrap();
+        self.current = prev.frame;
+        self.func.set(&prev.func);
         self.frames.pop();
     }
 
+    fn save_frame_function(&mut self) {
+        self.frames.last_mut().unwrap().func.set(&self.func);
+    }
+
+    /// Record the function called in the current frame, unless it is already
+    /// known. `evaluated` is false for special forms, whose argument is the
+    /// list of their unevaluated forms.
+    pub(crate) fn set_frame_function(&mut self, func: Object, evaluated: bool) {
+        if self.func == NIL {
+            self.func.set(func);
+            self.current.evaluated = evaluated;
+        }
+    }
+
+    /// The frames of the backtrace, innermost first. Frames whose function is
+    /// not known are skipped.
+    pub(crate) fn backtrace(&self) -> impl Iterator<Item = BacktraceFrame<'_, 'a>> {
+        let current = std::iter::once((&self.current, &self.func));
+        let saved = self.frames.iter().rev().map(|x| (&x.frame, &x.func));
+        current.chain(saved).filter(|(_, func)| **func != NIL).map(|(frame, func)| {
+            let args = usize::from(frame.arg_cnt.0);
+            BacktraceFrame {
+                func,
+                args: &self.vec[frame.start..frame.start + args],
+                rest: frame.arg_cnt.1,
+                evaluated: frame.evaluated,
+                binding_depth: frame.binding_depth,
+            }
+        })
+    }
+
     pub(crate) fn get_bytecode_frame(&self, idx: usize) -> Option<(&Rto<&'a ByteFn>, usize)> {
         let frame = self.frames.get(idx)?;
         let bytecode = frame.bytecode.as_ref()?;
@@ -183,12 +254,18 @@ impl<'a> RootedLispStack<'a> {
         self.frames.len()
     }
 
+    /// Depth of the binding stack when the current frame was pushed.
+    pub(crate) fn frame_binding_depth(&self) -> usize {
+        self.current.binding_depth
+    }
+
     pub(crate) fn unwind_frames(&mut self, frame: usize) {
         if frame == self.current_frame() {
             return; /* no frames to unwind */
         }
         assert!(frame < self.current_frame());
         self.current = self.frames[frame].frame;
+        self.func.set(&self.frames[frame].func);
         self.frames.truncate(frame);
     }
 
@@ -285,6 +362,12 @@ impl<'a> RootedLispStack<'a> {
         self.len() - self.current.start
     }
 
+    /// Remove everything above the arguments of the current frame, such as
+    /// the locals of a function that exited with an error.
+    pub(crate) fn truncate_to_args(&mut self) {
+        self.vec.truncate(self.current.start + usize::from(self.current.arg_cnt.0));
+    }
+
     pub(crate) fn current_args(&self) -> &[Rto<Object<'a>>] {
         // index as vec
         &self.vec[self.current.start..]
@@ -314,7 +397,8 @@ impl<'brw, 'rt> CallFrame<'brw, 'rt> {
     /// Create a new function call frame with the top args on the stack. This
     /// frame will be removed when the `CallFrame` goes out of scope.
     pub(crate) fn new_with_args(env: &'brw mut Rt<super::Env<'rt>>, args: usize) -> Self {
-        env.stack.push_frame(args);
+        let depth = env.binding_depth();
+        env.stack.push_frame(args, depth);
         Self { env }
     }
 
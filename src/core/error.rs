@@ -1,5 +1,80 @@
+use super::cons::Cons;
+use super::env::sym;
+use super::gc::{Context, GcState, Slot, Trace};
+use super::object::{Object, Symbol, TagType, WithLifetime};
+use rune_core::macros::list;
+use std::cell::RefCell;
 use std::fmt::{Display, Formatter};
 
+thread_local! {
+    /// The objects held by errors that are being unwound. They are traced by
+    /// the garbage collector, so that the errors stay valid if garbage is
+    /// collected before they are handled, such as by an `unwind-protect`.
+    static ERROR_OBJECTS: RefCell<Vec<Option<Slot<Object<'static>>>>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Trace the objects held by errors on this thread.
+pub(in crate::core) fn trace_error_objects(state: &mut GcState) {
+    ERROR_OBJECTS.with(|objects| objects.trace(state));
+}
+
+/// An object held by an error. It is rooted until the error is dropped.
+pub(crate) struct ErrorObject(usize);
+
+impl ErrorObject {
+    pub(crate) fn new(obj: Object) -> Self {
+        let obj = Some(Slot::new(unsafe { obj.with_lifetime() }));
+        ERROR_OBJECTS.with_borrow_mut(|objects| match objects.iter().position(Option::is_none) {
+            Some(idx) => {
+                objects[idx] = obj;
+                Self(idx)
+            }
+            None => {
+                objects.push(obj);
+                Self(objects.len() - 1)
+            }
+        })
+    }
+
+    pub(crate) fn bind<'ob>(&self, cx: &'ob Context) -> Object<'ob> {
+        cx.bind(self.get())
+    }
+
+    fn get(&self) -> Object<'static> {
+        ERROR_OBJECTS.with_borrow(|objects| *objects[self.0].as_deref().unwrap())
+    }
+}
+
+impl Drop for ErrorObject {
+    fn drop(&mut self) {
+        ERROR_OBJECTS.with_borrow_mut(|objects| {
+            objects[self.0] = None;
+            while objects.last().is_some_and(Option::is_none) {
+                objects.pop();
+            }
+        });
+    }
+}
+
+impl PartialEq for ErrorObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl Display for ErrorObject {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl std::fmt::Debug for ErrorObject {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum Type {
     Int,
@@ -21,19 +96,46 @@ pub(crate) enum Type {
     CharTable,
 }
 
+impl Type {
+    /// The predicate that is reported in `wrong-type-argument` errors for
+    /// this type.
+    fn predicate(&self) -> Symbol<'static> {
+        match self {
+            Type::Int => sym::INTEGERP,
+            Type::Char => sym::CHARACTERP,
+            Type::Cons => sym::CONSP,
+            Type::Vec => sym::VECTORP,
+            Type::Record => sym::RECORDP,
+            Type::HashTable => sym::HASH_TABLE_P,
+            Type::Sequence => sym::SEQUENCEP,
+            Type::BufferOrName => sym::BUFFER_OR_STRING_P,
+            Type::String => sym::STRINGP,
+            Type::StringOrChar => sym::CHAR_OR_STRING_P,
+            Type::Symbol => sym::SYMBOLP,
+            Type::Float => sym::FLOATP,
+            Type::Func => sym::FUNCTIONP,
+            Type::Number => sym::NUMBERP,
+            Type::List => sym::LISTP,
+            Type::Buffer => sym::BUFFERP,
+            Type::CharTable => sym::CHAR_TABLE_P,
+        }
+    }
+}
+
 /// Error provided if object was the wrong type
 #[derive(Debug, PartialEq)]
 pub(crate) struct TypeError {
     expect: Type,
     actual: Type,
     print: String,
+    obj: ErrorObject,
 }
 
 impl std::error::Error for TypeError {}
 
 impl Display for TypeError {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        let Self { expect, actual, print } = self;
+        let Self { expect, actual, print, .. } = self;
         write!(f, "expected {expect:?}, found {actual:?}: {print}")
     }
 }
@@ -45,6 +147,19 @@ impl TypeError {
         T: Into<super::object::ObjectType<'ob>>,
     {
         let obj = obj.into();
-        Self { expect, actual: obj.get_type(), print: obj.to_string() }
+        let actual = obj.get_type();
+        let print = obj.to_string();
+        Self { expect, actual, print, obj: ErrorObject::new(obj.tag()) }
+    }
+
+    /// The `(wrong-type-argument PREDICATE VALUE)` condition for this error.
+    pub(crate) fn condition<'ob>(&self, cx: &'ob Context) -> &'ob Cons {
+        let obj = self.obj.bind(cx);
+        let list = list![sym::WRONG_TYPE_ARGUMENT, self.expect.predicate(), obj; cx];
+        list.try_into().unwrap()
     }
 }
+
+// The object is only accessed on the thread the error was created on.
+unsafe impl Send for TypeError {}
+unsafe impl Sync for TypeError {}
@@ -1,5 +1,6 @@
 use super::GcState;
 use super::Trace;
+use crate::core::cons::ClonedConsMap;
 use crate::core::object::GcString;
 use crate::core::object::LispHashTable;
 use crate::core::object::{Gc, IntoObject, Object, UninternedSymbolMap, WithLifetime};
@@ -40,6 +41,7 @@ pub(crate) struct Block<const CONST: bool> {
     // collected. Kind of a hack.
     pub(in crate::core) lisp_hashtables: RefCell<Vec<*const LispHashTable>>,
     pub(in crate::core) uninterned_symbol_map: UninternedSymbolMap,
+    pub(in crate::core) cloned_conses: ClonedConsMap,
 }
 
 unsafe impl<const C: bool> Send for Block<C> {}
@@ -175,6 +177,7 @@ impl<'ob, 'rt> Context<'rt> {
             }
         }
 
+        crate::core::error::trace_error_objects(&mut state);
         state.trace_stack();
 
         self.next_limit = (state.to_space.allocated_bytes() * Self::GC_GROWTH_FACTOR) / 10;
@@ -709,6 +709,18 @@ where
     pub(crate) fn remove<Q: IntoRoot<K>>(&mut self, k: Q) {
         self.as_mut().swap_remove(unsafe { &k.into_root() });
     }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Rt<K>, &Rt<V>)> {
+        use std::ptr::from_ref;
+        let inner = unsafe { &*from_ref(self.as_ref()).cast::<IndexMap<Rt<K>, Rt<V>>>() };
+        inner.iter()
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut Rt<V>> {
+        use std::ptr::from_mut;
+        let inner = unsafe { &mut *from_mut(self.as_mut()).cast::<IndexMap<K, Rt<V>>>() };
+        inner.values_mut()
+    }
 }
 
 impl<K, V> Trace for ObjectMap<K, V>
@@ -44,6 +44,14 @@ impl OpenBuffer<'_> {
         cx.bind(self.back_ref)
     }
 
+    pub(crate) fn name(&self) -> String {
+        self.back_ref.name()
+    }
+
+    pub(crate) fn set_name(&mut self, name: String) {
+        *self.back_ref.0.name.lock().unwrap() = name;
+    }
+
     pub(crate) fn insert(&mut self, arg: Object) -> Result<()> {
         match arg.untag() {
             ObjectType::Int(i) => {
@@ -72,7 +80,7 @@ impl OpenBuffer<'_> {
 
     fn in_range(&self, pos: usize) -> Result<usize> {
         if pos == 0 || pos > self.get().text.len_chars() + 1 {
-            bail!("Position {pos} out of range in {}", self.get().name);
+            bail!("Position {pos} out of range in {}", self.name());
         }
         Ok(pos - 1)
     }
@@ -110,12 +118,17 @@ impl DerefMut for OpenBuffer<'_> {
 /// eventually.
 #[derive(Debug)]
 pub(crate) struct BufferData {
-    pub(crate) name: String,
     pub(crate) text: TextBuffer,
+    /// The position of the mark, or `None` if it was never set.
+    // TODO: this should be a marker so that it moves with the text
+    pub(crate) mark: Option<usize>,
 }
 
 #[derive(Debug)]
 struct LispBufferInner {
+    /// Kept outside of the buffer data so that it can be read while the
+    /// buffer is open.
+    name: Mutex<String>,
     text_buffer: Mutex<Option<BufferData>>,
 }
 
@@ -134,11 +147,16 @@ impl LispBuffer {
 
     pub(crate) unsafe fn new(name: String, _: &Block<true>) -> LispBuffer {
         let new = LispBufferInner {
-            text_buffer: Mutex::new(Some(BufferData { name, text: TextBuffer::new() })),
+            name: Mutex::new(name),
+            text_buffer: Mutex::new(Some(BufferData { text: TextBuffer::new(), mark: None })),
         };
         Self(GcHeap::new(new, true))
     }
 
+    pub(crate) fn name(&self) -> String {
+        self.0.name.lock().unwrap().clone()
+    }
+
     pub(in crate::core) fn lock(&self) -> Result<OpenBuffer<'_>> {
         let guard = self.0.text_buffer.lock().unwrap();
         if guard.is_none() {
@@ -170,12 +188,14 @@ impl Eq for LispBufferInner {}
 
 impl Display for LispBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let data = self.0.text_buffer.lock().unwrap();
-        let name = match data.as_ref() {
-            Some(buf) => &buf.name,
-            None => "deleted buffer",
-        };
-        write!(f, "#<{name}>")
+        // The buffer may already be locked by this thread if it is the current
+        // buffer, in which case it is live.
+        let killed = self.0.text_buffer.try_lock().is_ok_and(|data| data.is_none());
+        if killed {
+            write!(f, "#<killed buffer>")
+        } else {
+            write!(f, "#<buffer {}>", self.0.name.lock().unwrap())
+        }
     }
 }
 
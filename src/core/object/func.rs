@@ -2,19 +2,21 @@ use super::{
     super::gc::{Block, Context},
     display_slice, CloneIn, IntoObject, LispVec, ObjCell,
 };
-use super::{Object, WithLifetime};
+use super::{Object, WithLifetime, NIL};
 use crate::{
     core::{
         env::Env,
         gc::{GcHeap, Rt, Slot},
     },
     derive_GcMoveable,
+    interpreter::SpecialForm,
 };
 use anyhow::{bail, ensure, Result};
 use rune_macros::Trace;
+use std::cell::Cell;
 use std::fmt::{self, Debug, Display};
 
-#[derive(PartialEq, Eq, Trace)]
+#[derive(Trace)]
 pub(crate) struct ByteFnPrototype {
     #[no_trace]
     pub(crate) args: FnArgs,
@@ -24,8 +26,36 @@ pub(crate) struct ByteFnPrototype {
     pub(super) op_codes: Box<[u8]>,
     // TODO: remove a level of pointer indirection here.
     pub(super) constants: Slot<&'static LispVec>,
+    /// The spec of the `interactive` form if this function is a command, or
+    /// nil.
+    interactive: Slot<Object<'static>>,
+    /// Whether this function is a command. The spec of a command can be nil,
+    /// as for `(interactive)`.
+    #[no_trace]
+    command: bool,
+    /// The argument list of old-style dynamic binding code, whose arguments
+    /// are bound as special variables instead of being passed on the stack.
+    /// Nil for lexical binding code.
+    arglist: Slot<Object<'static>>,
+    /// Set once the bytecode has passed verification.
+    #[no_trace]
+    verified: Cell<bool>,
+}
+
+impl PartialEq for ByteFnPrototype {
+    fn eq(&self, other: &Self) -> bool {
+        self.args == other.args
+            && self.depth == other.depth
+            && self.op_codes == other.op_codes
+            && self.constants == other.constants
+            && self.interactive == other.interactive
+            && self.command == other.command
+            && self.arglist == other.arglist
+    }
 }
 
+impl Eq for ByteFnPrototype {}
+
 /// A function implemented in lisp. Note that all functions are byte compiled,
 /// so this contains the byte-code representation of the function.
 #[derive(PartialEq, Eq, Trace)]
@@ -47,8 +77,8 @@ impl ByteFn {
     pub(in crate::core) fn new(inner: ByteFnPrototype, constant: bool) -> ByteFn {
         ByteFn(GcHeap::new(inner, constant))
     }
-    // SAFETY: The caller must ensure that the constants are part of the same
-    // block as the bytecode function. Otherwise they will be collected and we
+    // SAFETY: The caller must ensure that the constants, interactive spec and
+    // arglist are part of the same block as the bytecode function. Otherwise they will be collected and we
     // will have a dangling pointer. Also this type must immediatly be put into
     // the GC heap, because holding it past garbage collections is unsafe.
     pub(crate) unsafe fn make(
@@ -56,13 +86,15 @@ impl ByteFn {
         consts: &LispVec,
         args: FnArgs,
         depth: usize,
+        interactive: Option<Object>,
+        arglist: Object,
     ) -> ByteFnPrototype {
         let op_codes = op_codes.to_vec().into_boxed_slice();
         #[cfg(miri)]
         unsafe {
-            // TODO: the opcodes live outside of the heap because we are relying
-            // on them having a stable address. Therefore they get leaked. We
-            // need to find some way to address this.
+            // TODO: the opcodes live outside of the heap, so they get leaked.
+            // The VM only holds an offset into them, so they could be moved
+            // into the heap with the rest of the function.
             extern "Rust" {
                 fn miri_static_root(ptr: *const u8);
             }
@@ -71,9 +103,13 @@ impl ByteFn {
         }
         ByteFnPrototype {
             constants: unsafe { Slot::new(consts.with_lifetime()) },
+            interactive: unsafe { Slot::new(interactive.unwrap_or_default().with_lifetime()) },
+            command: interactive.is_some(),
+            arglist: unsafe { Slot::new(arglist.with_lifetime()) },
             op_codes,
             args,
             depth,
+            verified: Cell::new(false),
         }
     }
 }
@@ -89,35 +125,86 @@ impl ByteFnPrototype {
 
     pub(crate) fn index<'ob>(&self, index: usize, cx: &'ob Context) -> Option<Object<'ob>> {
         match index {
-            0 => Some((self.args.into_arg_spec() as i64).into()),
+            0 => Some(match self.dynamic_args() {
+                Some(arglist) => cx.bind(arglist),
+                None => (self.args.into_arg_spec() as i64).into(),
+            }),
             1 => Some(cx.add(self.codes().to_vec())),
             2 => Some(cx.add(self.consts())),
             3 => Some(self.depth.into()),
+            4 if self.is_command() => Some(NIL),
+            5 if self.is_command() => Some(cx.bind(self.interactive())),
             _ => None,
         }
     }
 
-    pub(crate) const fn len(&self) -> usize {
-        4
+    pub(crate) fn len(&self) -> usize {
+        if self.is_command() {
+            6
+        } else {
+            4
+        }
+    }
+
+    pub(crate) fn interactive(&self) -> Object<'_> {
+        *self.interactive
+    }
+
+    pub(crate) fn is_command(&self) -> bool {
+        self.command
+    }
+
+    /// The argument list if this is dynamic binding code.
+    pub(crate) fn dynamic_args(&self) -> Option<Object<'_>> {
+        (!self.arglist.is_nil()).then_some(*self.arglist)
+    }
+
+    pub(crate) fn is_verified(&self) -> bool {
+        self.verified.get()
+    }
+
+    pub(crate) fn set_verified(&self) {
+        self.verified.set(true);
     }
 }
 
 impl<'new> CloneIn<'new, &'new Self> for ByteFn {
     fn clone_in<const C: bool>(&self, bk: &'new Block<C>) -> super::Gc<&'new Self> {
         let constants = self.constants.clone_in(bk);
-        let byte_fn =
-            unsafe { ByteFn::make(&self.op_codes, constants.untag(), self.args, self.depth) };
+        let interactive = self.interactive.clone_in(bk);
+        let arglist = self.arglist.clone_in(bk);
+        let byte_fn = unsafe {
+            ByteFn::make(
+                &self.op_codes,
+                constants.untag(),
+                self.args,
+                self.depth,
+                self.is_command().then_some(interactive),
+                arglist,
+            )
+        };
+        if self.is_verified() {
+            byte_fn.set_verified();
+        }
         byte_fn.into_obj(bk)
     }
 }
 
 impl Display for ByteFn {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let spec = self.args.into_arg_spec();
+        let spec = match self.dynamic_args() {
+            Some(arglist) => arglist.to_string(),
+            None => self.args.into_arg_spec().to_string(),
+        };
         let code = display_slice(&self.op_codes);
         let consts = display_slice(&self.constants);
         let depth = self.depth;
-        write!(f, "#[{spec} {code} {consts} {depth}]")
+        if self.is_command() {
+            let interactive = self.interactive();
+            write!(f, "#[{spec} {code} {consts} {depth} nil {interactive}]")
+        } else {
+            write!(f, "#[{spec} {code} {consts} {depth}]")
+        }
     }
 }
 
@@ -163,6 +250,15 @@ impl FnArgs {
         Ok(FnArgs { required, optional, rest, advice: false })
     }
 
+    /// The argument requirements of the lambda list `arglist`, as used by
+    /// dynamic binding byte-code.
+    pub(crate) fn from_arglist(arglist: Object) -> Result<Self> {
+        let (required, optional, rest) = crate::interpreter::parse_arg_list(arglist)?;
+        let Ok(required) = u16::try_from(required.len()) else { bail!("Too many arguments") };
+        let Ok(optional) = u16::try_from(optional.len()) else { bail!("Too many arguments") };
+        Ok(FnArgs { required, optional, rest: rest.is_some(), advice: false })
+    }
+
     pub(crate) fn into_arg_spec(self) -> u64 {
         let mut spec = self.required;
         let max = self.required + self.optional;
@@ -180,6 +276,9 @@ pub(crate) struct SubrFn {
     pub(crate) subr: BuiltInFn,
     pub(crate) args: FnArgs,
     pub(crate) name: &'static str,
+    /// Set for special forms. These are evaluated by the interpreter with
+    /// their arguments unevaluated and can't be called as functions.
+    pub(crate) special_form: Option<SpecialForm>,
 }
 define_unbox!(SubrFn, Func, &'ob SubrFn);
 
@@ -190,6 +289,9 @@ impl SubrFn {
         env: &mut Rt<Env>,
         cx: &'ob mut Context,
     ) -> Result<Object<'ob>> {
+        if self.special_form.is_some() {
+            bail!("Invalid function: {}", self.name);
+        }
         (self.subr)(arg_cnt, env, cx)
     }
 }
@@ -219,7 +321,7 @@ impl PartialEq for SubrFn {
     fn eq(&self, other: &Self) -> bool {
         let lhs = self.subr as *const BuiltInFn;
         let rhs = other.subr as *const BuiltInFn;
-        lhs == rhs
+        lhs == rhs && self.special_form == other.special_form
     }
 }
 
@@ -2,23 +2,16 @@
 use crate::core::{
     cons::Cons,
     env::{sym, Env, INTERNED_SYMBOLS},
-    error::{Type, TypeError},
-    gc::{Context, Rt},
+    error::{ErrorObject, Type, TypeError},
+    gc::{Context, Rt, Rto},
     object::{
-        IntoObject, List, ListType, Number, Object, ObjectType, SubrFn, Symbol, WithLifetime, NIL,
+        Function, FunctionType, Gc, IntoObject, LispBuffer, List, ListType, Number, Object,
+        ObjectType, SubrFn, Symbol, TagType, NIL,
     },
 };
-use anyhow::{anyhow, Result};
-use rune_core::{hashmap::HashSet, macros::list};
+use anyhow::Result;
+use rune_core::macros::{call, list, rebind, root};
 use rune_macros::defun;
-use std::sync::LazyLock;
-use std::sync::Mutex;
-
-/// Rust translation of the `features` variable: A list of symbols are the features
-/// of the executing Emacs. Used by [`featurep`](`crate::fns::featurep`) and [`require`](`crate::fns::require`),
-/// altered by [`provide`].
-pub(crate) static FEATURES: LazyLock<Mutex<HashSet<Symbol<'static>>>> =
-    LazyLock::new(Mutex::default);
 
 #[defun]
 pub(crate) fn fset<'ob>(symbol: Symbol<'ob>, definition: Object) -> Result<Symbol<'ob>> {
@@ -37,18 +30,190 @@ pub(crate) fn defalias<'ob>(
     symbol: Symbol<'ob>,
     definition: Object,
     _docstring: Option<&str>,
+    env: &mut Rt<Env>,
+    cx: &Context,
 ) -> Result<Symbol<'ob>> {
+    // Remember the autoload that this replaces so that `unload-feature` can
+    // put it back
+    if let Some(FunctionType::Cons(cons)) = symbol.func(cx).map(|x| x.untag()) {
+        if cons.car() == sym::AUTOLOAD {
+            env.set_prop(symbol, sym::AUTOLOAD, cons.cdr());
+        }
+    }
+    let kind = match definition.as_cons_pair() {
+        Ok((sym::AUTOLOAD, _)) => sym::AUTOLOAD,
+        _ => sym::DEFUN,
+    };
+    crate::lread::loadhist_attach(Cons::new(kind, symbol, cx).into(), env, cx)?;
+    if env.autoload_depth > 0 {
+        let old: Object = symbol.func(cx).map_or(NIL, Into::into);
+        env.autoload_queue.push((symbol, old));
+    }
     fset(symbol, definition)
 }
 
 #[defun]
 pub(crate) fn set<'ob>(
-    place: Symbol,
-    newlet: Object<'ob>,
+    place: &Rto<Gc<Symbol>>,
+    newlet: &Rto<Object>,
     env: &mut Rt<Env>,
+    cx: &'ob mut Context,
 ) -> Result<Object<'ob>> {
-    env.set_var(place, newlet)?;
-    Ok(newlet)
+    let place: Symbol = place.untag(cx);
+    root!(place, cx);
+    set_variable(place, newlet, env, cx)?;
+    Ok(newlet.bind(cx))
+}
+
+/// Set the value of `var`, calling its watchers first.
+pub(crate) fn set_variable(
+    var: &Rto<Symbol>,
+    value: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<()> {
+    if has_watchers(var.bind(cx), env, cx) {
+        let buffer = watched_buffer(var.bind(cx), true, env, cx);
+        root!(buffer, cx);
+        notify_variable_watchers(var, value, sym::SET, buffer, env, cx)?;
+    }
+    env.set_var(var.bind(cx), value.bind(cx), cx)
+}
+
+/// Dynamically bind `var` to `value`, calling its watchers first.
+pub(crate) fn specbind(
+    var: &Rto<Symbol>,
+    value: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<()> {
+    if has_watchers(var.bind(cx), env, cx) {
+        let buffer = watched_buffer(var.bind(cx), false, env, cx);
+        root!(buffer, cx);
+        notify_variable_watchers(var, value, sym::LET, buffer, env, cx)?;
+    }
+    env.varbind(var.bind(cx), value.bind(cx), cx);
+    Ok(())
+}
+
+/// Remove `count` variable bindings, calling the watchers of each variable
+/// with the value it is restored to. All the bindings are removed even if a
+/// watcher signals an error.
+pub(crate) fn unbind(count: u16, env: &mut Rt<Env>, cx: &mut Context) -> Result<()> {
+    let mut result = Ok(());
+    for _ in 0..count {
+        if let Some((var, value, buffer)) = env.peek_var_binding(cx) {
+            if has_watchers(var, env, cx) {
+                let value = value.unwrap_or_default();
+                let buffer = buffer.map_or(NIL, |x| cx.add(x));
+                root!(var, cx);
+                root!(value, cx);
+                root!(buffer, cx);
+                let notified = notify_variable_watchers(var, value, sym::UNLET, buffer, env, cx);
+                result = result.and(notified);
+            }
+        }
+        env.unbind(1, cx);
+    }
+    result
+}
+
+fn has_watchers(var: Symbol, env: &Rt<Env>, cx: &Context) -> bool {
+    let var = env.indirect_variable(var, cx);
+    !get(var, sym::WATCHERS, env, cx).is_nil()
+}
+
+/// The `where` argument passed to the watchers of `var`, which is the current
+/// buffer if the variable is local to it and nil otherwise.
+fn watched_buffer<'ob>(var: Symbol, if_set: bool, env: &Rt<Env>, cx: &'ob Context) -> Object<'ob> {
+    let buffer = env.current_buffer.get().lisp_buffer(cx);
+    if env.is_local(var, buffer, if_set) {
+        cx.add(buffer)
+    } else {
+        NIL
+    }
+}
+
+/// Call the watchers of `var` before its value is changed by `operation`.
+/// Like in Emacs, a variable is not watched while its own watchers run.
+pub(crate) fn notify_variable_watchers(
+    var: &Rto<Symbol>,
+    newval: &Rto<Object>,
+    operation: Symbol,
+    buffer: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<()> {
+    let var = env.indirect_variable(var.bind(cx), cx);
+    if env.notifying.iter().any(|x| *x == var) {
+        return Ok(());
+    }
+    let watchers: Vec<Object> =
+        get(var, sym::WATCHERS, env, cx).as_list()?.collect::<Result<_, _>>()?;
+    env.notifying.push(var);
+    let var: Object = var.into();
+    let operation: Object = operation.into();
+    root!(watchers, cx);
+    root!(var, cx);
+    root!(operation, cx);
+    let mut result = Ok(());
+    for watcher in watchers.iter() {
+        let Ok(watcher) = Function::try_from(watcher.bind(cx)) else { continue };
+        root!(watcher, cx);
+        if let Err(e) = call!(watcher, &*var, newval, &*operation, buffer; env, cx) {
+            result = Err(e.into());
+            break;
+        }
+    }
+    env.notifying.pop();
+    result
+}
+
+#[defun]
+fn add_variable_watcher(symbol: Symbol, watch_function: Object, env: &mut Rt<Env>, cx: &Context) {
+    let symbol = env.indirect_variable(symbol, cx);
+    let watchers = get(symbol, sym::WATCHERS, env, cx);
+    let present = watchers
+        .as_list()
+        .is_ok_and(|mut x| x.any(|x| x.is_ok_and(|x| crate::fns::equal(x, watch_function))));
+    if !present {
+        let watchers = Cons::new(watch_function, watchers, cx);
+        env.set_prop(symbol, sym::WATCHERS, watchers.into());
+    }
+}
+
+#[defun]
+fn remove_variable_watcher(
+    symbol: Symbol,
+    watch_function: Object,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<()> {
+    let symbol = env.indirect_variable(symbol, cx);
+    let watchers = get(symbol, sym::WATCHERS, env, cx);
+    let watchers = crate::fns::delete(watch_function, watchers.try_into()?)?;
+    env.set_prop(symbol, sym::WATCHERS, watchers);
+    Ok(())
+}
+
+#[defun]
+fn get_variable_watchers<'ob>(symbol: Symbol, env: &Rt<Env>, cx: &'ob Context) -> Object<'ob> {
+    get(env.indirect_variable(symbol, cx), sym::WATCHERS, env, cx)
+}
+
+defsym!(WATCHERS);
+defsym!(UNLET);
+
+#[defun]
+pub(crate) fn indirect_variable<'ob>(
+    object: Object<'ob>,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Object<'ob> {
+    match object.untag() {
+        ObjectType::Symbol(symbol) => env.indirect_variable(symbol, cx).into(),
+        _ => object,
+    }
 }
 
 #[defun]
@@ -79,9 +244,46 @@ pub(crate) fn get<'ob>(
 }
 
 #[defun]
-pub(crate) fn local_variable_if_set_p(_sym: Symbol) -> bool {
-    // TODO: Implement buffer locals
-    false
+pub(crate) fn local_variable_if_set_p(
+    variable: Symbol,
+    buffer: Option<Gc<&LispBuffer>>,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> bool {
+    let buffer = buffer.map_or_else(|| env.current_buffer.get().lisp_buffer(cx), |x| x.untag());
+    env.is_local(variable, buffer, true)
+}
+
+#[defun]
+pub(crate) fn local_variable_p(
+    variable: Symbol,
+    buffer: Option<Gc<&LispBuffer>>,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> bool {
+    let buffer = buffer.map_or_else(|| env.current_buffer.get().lisp_buffer(cx), |x| x.untag());
+    env.is_local(variable, buffer, false)
+}
+
+#[defun]
+pub(crate) fn make_local_variable<'ob>(
+    variable: Symbol<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<Symbol<'ob>> {
+    env.make_local_variable(variable, cx)?;
+    Ok(variable)
+}
+
+#[defun]
+pub(crate) fn kill_local_variable<'ob>(
+    variable: Symbol<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Symbol<'ob> {
+    let buffer = env.current_buffer.get().lisp_buffer(cx);
+    env.kill_local_variable(variable, buffer, cx);
+    variable
 }
 
 #[defun]
@@ -90,8 +292,10 @@ pub(crate) fn default_value<'ob>(
     env: &Rt<Env>,
     cx: &'ob Context,
 ) -> Result<Object<'ob>> {
-    // TODO: Implement buffer locals
-    symbol_value(symbol, env, cx).ok_or_else(|| anyhow!("Void variable: {symbol}"))
+    match env.default_value(symbol) {
+        Some(value) => Ok(value.bind(cx)),
+        None => Err(LispError::void_variable(symbol, cx).into()),
+    }
 }
 
 #[defun]
@@ -108,7 +312,7 @@ pub(crate) fn symbol_value<'ob>(
     env: &Rt<Env>,
     cx: &'ob Context,
 ) -> Option<Object<'ob>> {
-    env.vars.get(symbol).map(|x| x.bind(cx))
+    env.var(symbol).map(|x| x.bind(cx))
 }
 
 #[defun]
@@ -134,18 +338,30 @@ pub(crate) fn fmakunbound(symbol: Symbol) -> Symbol {
 
 #[defun]
 pub(crate) fn boundp(symbol: Symbol, env: &Rt<Env>) -> bool {
-    env.vars.get(symbol).is_some()
+    env.var(symbol).is_some()
 }
 
 #[defun]
-pub(crate) fn makunbound<'ob>(symbol: Symbol<'ob>, env: &mut Rt<Env>) -> Symbol<'ob> {
-    env.vars.remove(symbol);
-    symbol
+pub(crate) fn makunbound<'ob>(
+    symbol: &Rto<Gc<Symbol>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Symbol<'ob>> {
+    if has_watchers(symbol.untag(cx), env, cx) {
+        let var: Symbol = symbol.untag(cx);
+        let buffer = watched_buffer(var, false, env, cx);
+        root!(var, cx);
+        root!(buffer, cx);
+        root!(newval, NIL, cx);
+        notify_variable_watchers(var, newval, sym::MAKUNBOUND, buffer, env, cx)?;
+    }
+    env.unset_var(symbol.untag(cx), cx);
+    Ok(symbol.untag(cx))
 }
 
 #[defun]
 pub(crate) fn default_boundp(symbol: Symbol, env: &Rt<Env>) -> bool {
-    env.vars.get(symbol).is_some()
+    env.default_value(symbol).is_some()
 }
 
 #[defun]
@@ -164,11 +380,26 @@ pub(crate) fn symbolp(object: Object) -> bool {
 }
 
 #[defun]
-pub(crate) fn functionp(object: Object) -> bool {
+pub(crate) fn functionp(object: Object, cx: &Context) -> bool {
     match object.untag() {
-        ObjectType::ByteFn(_) | ObjectType::SubrFn(_) => true,
+        ObjectType::ByteFn(_) => true,
+        // special forms can't be called
+        ObjectType::SubrFn(func) => func.special_form.is_none(),
         ObjectType::Cons(cons) => cons.car() == sym::CLOSURE || cons.car() == sym::LAMBDA,
-        ObjectType::Symbol(sym) => sym.has_func(),
+        ObjectType::Symbol(sym) => match sym.follow_indirect(cx) {
+            Some(func) => match func.untag() {
+                // An autoload is a function unless it will load a macro or keymap
+                FunctionType::Cons(cons) if cons.car() == sym::AUTOLOAD => {
+                    let load_type = cons.elements().nth(4).and_then(|x| x.ok()).map(|x| x.untag());
+                    !matches!(
+                        load_type,
+                        Some(ObjectType::Symbol(sym::MACRO | sym::KEYMAP | sym::TRUE))
+                    )
+                }
+                _ => functionp(func.into(), cx),
+            },
+            None => false,
+        },
         _ => false,
     }
 }
@@ -232,6 +463,24 @@ pub(crate) fn atom(object: Object) -> bool {
     !consp(object)
 }
 
+#[defun]
+fn sequencep(object: Object) -> bool {
+    matches!(
+        object.untag(),
+        ObjectType::NIL
+            | ObjectType::Cons(_)
+            | ObjectType::Vec(_)
+            | ObjectType::String(_)
+            | ObjectType::ByteString(_)
+            | ObjectType::CharTable(_)
+    )
+}
+
+#[defun]
+fn char_or_string_p(object: Object) -> bool {
+    stringp(object) || char::try_from(object).is_ok()
+}
+
 #[defun]
 fn byte_code_function_p(object: Object) -> bool {
     matches!(object.untag(), ObjectType::ByteFn(_))
@@ -269,32 +518,40 @@ fn string_to_number<'ob>(string: &str, base: Option<i64>, cx: &'ob Context) -> N
 
 #[defun]
 pub(crate) fn defvar<'ob>(
-    symbol: Symbol,
-    initvalue: Option<Object<'ob>>,
-    _docstring: Option<&str>,
+    symbol: &Rto<Gc<Symbol>>,
+    initvalue: Option<&Rto<Object>>,
+    _docstring: Option<&Rto<Object>>,
     env: &mut Rt<Env>,
+    cx: &'ob mut Context,
 ) -> Result<Object<'ob>> {
-    let value = initvalue.unwrap_or_default();
-    set(symbol, value, env)
+    let value = initvalue.map_or(NIL, |x| x.bind(cx));
+    root!(value, cx);
+    set(symbol, value, env, cx)
 }
 
 #[defun]
-pub(crate) fn make_variable_buffer_local(variable: Symbol) -> Symbol {
-    // TODO: Implement
-    variable
+pub(crate) fn make_variable_buffer_local<'ob>(
+    variable: Symbol<'ob>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<Symbol<'ob>> {
+    env.make_variable_buffer_local(variable, cx)?;
+    Ok(variable)
 }
 
 #[defun]
-fn subr_arity<'ob>(subr: &SubrFn, cx: &'ob Context) -> Object<'ob> {
+pub(crate) fn subr_arity<'ob>(subr: &SubrFn, cx: &'ob Context) -> &'ob Cons {
     let min = subr.args.required as usize;
     let max: Object = {
-        if subr.args.rest {
+        if subr.special_form.is_some() {
+            sym::UNEVALLED.into()
+        } else if subr.args.rest {
             sym::MANY.into()
         } else {
             (min + subr.args.optional as usize).into()
         }
     };
-    Cons::new(min, max, cx).into()
+    Cons::new(min, max, cx)
 }
 
 #[defun]
@@ -313,6 +570,7 @@ pub(crate) fn aset<'ob>(
     array: Object<'ob>,
     idx: usize,
     newlet: Object<'ob>,
+    cx: &Context,
 ) -> Result<Object<'ob>> {
     match array.untag() {
         ObjectType::Vec(vec) => {
@@ -321,8 +579,7 @@ pub(crate) fn aset<'ob>(
                 vec[idx].set(newlet);
                 Ok(newlet)
             } else {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
+                Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into())
             }
         }
         ObjectType::Record(vec) => {
@@ -331,8 +588,7 @@ pub(crate) fn aset<'ob>(
                 vec[idx].set(newlet);
                 Ok(newlet)
             } else {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
+                Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into())
             }
         }
         ObjectType::CharTable(table) => {
@@ -348,35 +604,23 @@ pub(crate) fn aref<'ob>(array: Object<'ob>, idx: usize, cx: &'ob Context) -> Res
     match array.untag() {
         ObjectType::Vec(vec) => match vec.get(idx) {
             Some(x) => Ok(x.get()),
-            None => {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            None => Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into()),
         },
         ObjectType::Record(vec) => match vec.get(idx) {
             Some(x) => Ok(x.get()),
-            None => {
-                let len = vec.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            None => Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into()),
         },
         ObjectType::String(string) => match string.chars().nth(idx) {
             Some(x) => Ok((i64::from(x as u32)).into()),
-            None => {
-                let len = string.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            None => Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into()),
         },
         ObjectType::ByteString(string) => match string.get(idx) {
             Some(x) => Ok((i64::from(*x)).into()),
-            None => {
-                let len = string.len();
-                Err(anyhow!("index {idx} is out of bounds. Length was {len}"))
-            }
+            None => Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into()),
         },
         ObjectType::ByteFn(fun) => match fun.index(idx, cx) {
             Some(x) => Ok(x),
-            None => Err(anyhow!("index {idx} is out of bounds")),
+            None => Err(LispError::args_out_of_range(&[array, (idx as i64).into()], cx).into()),
         },
         ObjectType::CharTable(chartable) => Ok(chartable.get(idx)),
         x => Err(TypeError::new(Type::Sequence, x).into()),
@@ -412,15 +656,6 @@ pub(crate) fn indirect_function<'ob>(object: Object<'ob>, cx: &'ob Context) -> O
     }
 }
 
-#[defun]
-pub(crate) fn provide<'ob>(feature: Symbol<'ob>, _subfeatures: Option<&Cons>) -> Symbol<'ob> {
-    let mut features = FEATURES.lock().unwrap();
-    // TODO: SYMBOL - need to trace this
-    let feat = unsafe { feature.with_lifetime() };
-    features.insert(feat);
-    feature
-}
-
 #[defun]
 pub(crate) fn car(list: List) -> Object {
     match list.untag() {
@@ -477,6 +712,68 @@ fn bare_symbol(sym: Symbol) -> Symbol {
     sym
 }
 
+#[defun]
+pub(crate) fn interactive_form<'ob>(
+    cmd: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    let mut fun = cmd.bind(cx);
+    let mut name = None;
+    // The `interactive-form' property of a symbol overrides the spec of its
+    // function
+    while let ObjectType::Symbol(sym) = fun.untag() {
+        let form = get(sym, sym::INTERACTIVE_FORM, env, cx);
+        if !form.is_nil() {
+            return Ok(rebind!(form, cx));
+        }
+        let Some(func) = sym.func(cx) else { return Ok(NIL) };
+        name = Some(sym);
+        fun = func.into();
+    }
+    if let (Ok((sym::AUTOLOAD, _)), Some(name)) = (fun.as_cons_pair(), name) {
+        let name = name.tag();
+        root!(fun, cx);
+        root!(name, cx);
+        crate::eval::autoload_do_load(fun, Some(name), None, env, cx)?;
+        return interactive_form(cmd, env, cx);
+    }
+    Ok(rebind!(function_interactive_form(fun, cx)?, cx))
+}
+
+/// The `(interactive SPEC)` form of `fun`, or nil if it is not a command.
+/// Symbols are not followed.
+pub(crate) fn function_interactive_form<'ob>(
+    fun: Object<'ob>,
+    cx: &'ob Context,
+) -> Result<Object<'ob>> {
+    match fun.untag() {
+        ObjectType::ByteFn(func) if func.is_command() => {
+            // The spec can be stored in a vector along with the modes that the
+            // command applies to
+            let spec = match func.interactive().untag() {
+                ObjectType::Vec(vec) => vec.first().map_or(NIL, |x| x.get()),
+                _ => func.interactive(),
+            };
+            Ok(list![sym::INTERACTIVE, cx.bind(spec); cx])
+        }
+        ObjectType::Cons(func) => {
+            let body = match func.car().untag() {
+                ObjectType::Symbol(sym::LAMBDA) => crate::fns::nthcdr(2, fun.try_into()?)?,
+                ObjectType::Symbol(sym::CLOSURE) => crate::fns::nthcdr(3, fun.try_into()?)?,
+                _ => return Ok(NIL),
+            };
+            let form = crate::fns::assq(sym::INTERACTIVE.into(), body.try_into()?)?;
+            // Drop the modes from `(interactive SPEC MODES...)'
+            match form.as_list()?.nth(2) {
+                Some(_) => Ok(list![sym::INTERACTIVE, crate::fns::nth(1, form.try_into()?)?; cx]),
+                None => Ok(form),
+            }
+        }
+        _ => Ok(NIL),
+    }
+}
+
 #[defun]
 fn symbol_with_pos_p(_sym: Object) -> bool {
     // TODO: implement
@@ -485,7 +782,7 @@ fn symbol_with_pos_p(_sym: Object) -> bool {
 
 #[derive(Debug, PartialEq)]
 pub(crate) struct LispError {
-    message: &'static Cons,
+    message: ErrorObject,
 }
 
 impl std::error::Error for LispError {}
@@ -500,11 +797,11 @@ impl std::fmt::Display for LispError {
 defsym!(WRONG_NUMBER_OF_ARGUMENTS);
 impl LispError {
     pub(crate) fn new(message: &Cons) -> Self {
-        Self { message: unsafe { message.with_lifetime() } }
+        Self { message: ErrorObject::new(message.into()) }
     }
 
     pub(crate) fn bind<'ob>(&self, cx: &'ob Context) -> &'ob Cons {
-        cx.bind(self.message)
+        self.message.bind(cx).try_into().unwrap()
     }
 
     pub(crate) fn arg_cnt<'ob, T>(
@@ -517,11 +814,204 @@ impl LispError {
         let list = list![sym::WRONG_NUMBER_OF_ARGUMENTS, func, expected, actual; cx];
         Self::new(list.try_into().unwrap())
     }
+
+    /// A plain `error` with `message`, like the `error` function signals.
+    pub(crate) fn error(message: &str, cx: &Context) -> Self {
+        let list = list![sym::ERROR, message; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn void_variable(symbol: Symbol, cx: &Context) -> Self {
+        let list = list![sym::VOID_VARIABLE, symbol; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn void_function(symbol: Symbol, cx: &Context) -> Self {
+        let list = list![sym::VOID_FUNCTION, symbol; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn invalid_function(func: Object, cx: &Context) -> Self {
+        let list = list![sym::INVALID_FUNCTION, func; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn setting_constant(symbol: Symbol, cx: &Context) -> Self {
+        let list = list![sym::SETTING_CONSTANT, symbol; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn no_catch(tag: Object, value: Object, cx: &Context) -> Self {
+        let list = list![sym::NO_CATCH, tag, value; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn excessive_lisp_nesting(depth: usize, cx: &Context) -> Self {
+        let list = list![sym::EXCESSIVE_LISP_NESTING, depth as i64; cx];
+        Self::new(list.try_into().unwrap())
+    }
+
+    pub(crate) fn args_out_of_range(args: &[Object], cx: &Context) -> Self {
+        Self::new(Cons::new(
+            sym::ARGS_OUT_OF_RANGE,
+            crate::fns::slice_into_list(args, None, cx),
+            cx,
+        ))
+    }
+
+    /// The `file-error` for an I/O error. `action` describes what was being
+    /// done, e.g. "Opening input file", and the error is refined to
+    /// `file-missing`, `permission-denied` or `file-already-exists` when the
+    /// cause is one of those.
+    pub(crate) fn file_error(
+        action: &str,
+        file: &str,
+        error: &std::io::Error,
+        cx: &Context,
+    ) -> Self {
+        use std::io::ErrorKind;
+        let (symbol, reason) = match error.kind() {
+            ErrorKind::NotFound => (sym::FILE_MISSING, "No such file or directory".to_owned()),
+            ErrorKind::PermissionDenied => (sym::PERMISSION_DENIED, "Permission denied".to_owned()),
+            ErrorKind::AlreadyExists => (sym::FILE_ALREADY_EXISTS, "File exists".to_owned()),
+            _ => (sym::FILE_ERROR, error.to_string()),
+        };
+        let list = list![symbol, action, reason, file; cx];
+        Self::new(list.try_into().unwrap())
+    }
 }
 
 unsafe impl Send for LispError {}
 unsafe impl Sync for LispError {}
 
+defsym!(ERROR_MESSAGE);
+defsym!(QUIT);
+defsym!(MINIBUFFER_QUIT);
+defsym!(USER_ERROR);
+defsym!(WRONG_LENGTH_ARGUMENT);
+defsym!(WRONG_TYPE_ARGUMENT);
+defsym!(ARGS_OUT_OF_RANGE);
+defsym!(VOID_FUNCTION);
+defsym!(CYCLIC_FUNCTION_INDIRECTION);
+defsym!(CYCLIC_VARIABLE_INDIRECTION);
+defsym!(CIRCULAR_LIST);
+defsym!(SETTING_CONSTANT);
+defsym!(TRAPPING_CONSTANT);
+defsym!(INVALID_READ_SYNTAX);
+defsym!(INVALID_FUNCTION);
+defsym!(NO_CATCH);
+defsym!(END_OF_FILE);
+defsym!(BEGINNING_OF_BUFFER);
+defsym!(END_OF_BUFFER);
+defsym!(BUFFER_READ_ONLY);
+defsym!(TEXT_READ_ONLY);
+defsym!(INHIBITED_INTERACTION);
+defsym!(SEARCH_FAILED);
+defsym!(INVALID_REGEXP);
+defsym!(MARK_INACTIVE);
+defsym!(SCAN_ERROR);
+defsym!(DOMAIN_ERROR);
+defsym!(SINGULARITY_ERROR);
+defsym!(OVERFLOW_ERROR);
+defsym!(RANGE_ERROR);
+defsym!(UNDERFLOW_ERROR);
+defsym!(RECURSION_ERROR);
+defsym!(EXCESSIVE_LISP_NESTING);
+defsym!(EXCESSIVE_VARIABLE_BINDING);
+defsym!(FILE_ERROR);
+defsym!(FILE_ALREADY_EXISTS);
+defsym!(FILE_DATE_ERROR);
+defsym!(FILE_MISSING);
+defsym!(PERMISSION_DENIED);
+defsym!(FILE_NOTIFY_ERROR);
+defsym!(REMOTE_FILE_ERROR);
+defsym!(BUFFER_OR_STRING_P);
+
+/// The standard errors as `(symbol, message, parent)`. Every parent comes
+/// before its children so that the parent's conditions are already set when
+/// the child's are built.
+const STANDARD_ERRORS: &[(Symbol, &str, Option<Symbol>)] = &[
+    (sym::ERROR, "error", None),
+    (sym::QUIT, "Quit", None),
+    (sym::MINIBUFFER_QUIT, "Quit", Some(sym::QUIT)),
+    (sym::USER_ERROR, "", Some(sym::ERROR)),
+    (sym::WRONG_LENGTH_ARGUMENT, "Wrong length argument", Some(sym::ERROR)),
+    (sym::WRONG_TYPE_ARGUMENT, "Wrong type argument", Some(sym::ERROR)),
+    (sym::ARGS_OUT_OF_RANGE, "Args out of range", Some(sym::ERROR)),
+    (sym::VOID_FUNCTION, "Symbol's function definition is void", Some(sym::ERROR)),
+    (
+        sym::CYCLIC_FUNCTION_INDIRECTION,
+        "Symbol's chain of function indirections contains a loop",
+        Some(sym::ERROR),
+    ),
+    (
+        sym::CYCLIC_VARIABLE_INDIRECTION,
+        "Symbol's chain of variable indirections contains a loop",
+        Some(sym::ERROR),
+    ),
+    (sym::CIRCULAR_LIST, "List contains a loop", Some(sym::ERROR)),
+    (sym::VOID_VARIABLE, "Symbol's value as variable is void", Some(sym::ERROR)),
+    (sym::SETTING_CONSTANT, "Attempt to set a constant symbol", Some(sym::ERROR)),
+    (
+        sym::TRAPPING_CONSTANT,
+        "Attempt to trap writes to a constant symbol",
+        Some(sym::ERROR),
+    ),
+    (sym::INVALID_READ_SYNTAX, "Invalid read syntax", Some(sym::ERROR)),
+    (sym::INVALID_FUNCTION, "Invalid function", Some(sym::ERROR)),
+    (sym::WRONG_NUMBER_OF_ARGUMENTS, "Wrong number of arguments", Some(sym::ERROR)),
+    (sym::NO_CATCH, "No catch for tag", Some(sym::ERROR)),
+    (sym::END_OF_FILE, "End of file during parsing", Some(sym::ERROR)),
+    (sym::ARITH_ERROR, "Arithmetic error", Some(sym::ERROR)),
+    (sym::BEGINNING_OF_BUFFER, "Beginning of buffer", Some(sym::ERROR)),
+    (sym::END_OF_BUFFER, "End of buffer", Some(sym::ERROR)),
+    (sym::BUFFER_READ_ONLY, "Buffer is read-only", Some(sym::ERROR)),
+    (sym::TEXT_READ_ONLY, "Text is read-only", Some(sym::BUFFER_READ_ONLY)),
+    (sym::INHIBITED_INTERACTION, "User interaction while inhibited", Some(sym::ERROR)),
+    (sym::SEARCH_FAILED, "Search failed", Some(sym::ERROR)),
+    (sym::INVALID_REGEXP, "Invalid regexp", Some(sym::ERROR)),
+    (sym::MARK_INACTIVE, "The mark is not active now", Some(sym::ERROR)),
+    (sym::SCAN_ERROR, "Scan error", Some(sym::ERROR)),
+    (sym::DOMAIN_ERROR, "Arithmetic domain error", Some(sym::ARITH_ERROR)),
+    (sym::SINGULARITY_ERROR, "Arithmetic singularity error", Some(sym::DOMAIN_ERROR)),
+    (sym::OVERFLOW_ERROR, "Arithmetic overflow error", Some(sym::DOMAIN_ERROR)),
+    (sym::RANGE_ERROR, "Arithmetic range error", Some(sym::ARITH_ERROR)),
+    (sym::UNDERFLOW_ERROR, "Arithmetic underflow error", Some(sym::RANGE_ERROR)),
+    (sym::RECURSION_ERROR, "Excessive recursive calling error", Some(sym::ERROR)),
+    (
+        sym::EXCESSIVE_LISP_NESTING,
+        "Lisp nesting exceeds `max-lisp-eval-depth'",
+        Some(sym::RECURSION_ERROR),
+    ),
+    (
+        sym::EXCESSIVE_VARIABLE_BINDING,
+        "Variable binding depth exceeds max-specpdl-size",
+        Some(sym::RECURSION_ERROR),
+    ),
+    (sym::INVALID_BYTE_CODE, "Invalid byte code", Some(sym::ERROR)),
+    (sym::FILE_ERROR, "File error", Some(sym::ERROR)),
+    (sym::FILE_ALREADY_EXISTS, "File already exists", Some(sym::FILE_ERROR)),
+    (sym::FILE_DATE_ERROR, "Cannot set file date", Some(sym::FILE_ERROR)),
+    (sym::FILE_MISSING, "No such file or directory", Some(sym::FILE_ERROR)),
+    (sym::PERMISSION_DENIED, "Cannot access file or directory", Some(sym::FILE_ERROR)),
+    (sym::FILE_NOTIFY_ERROR, "File notification error", Some(sym::FILE_ERROR)),
+    (sym::REMOTE_FILE_ERROR, "Remote file error", Some(sym::FILE_ERROR)),
+];
+
+/// Set the `error-conditions` and `error-message` properties of the standard
+/// errors.
+pub(crate) fn init_errors(env: &mut Rt<Env>, cx: &Context) {
+    for &(error, message, parent) in STANDARD_ERRORS {
+        let parent_conditions = match parent {
+            Some(parent) => get(parent, sym::ERROR_CONDITIONS, env, cx),
+            None => NIL,
+        };
+        let conditions = Cons::new(error, parent_conditions, cx);
+        env.set_prop(error, sym::ERROR_CONDITIONS, conditions.into());
+        env.set_prop(error, sym::ERROR_MESSAGE, cx.add(message));
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -539,10 +1029,159 @@ mod test {
     #[test]
     fn test_functionp() {
         assert_lisp("(functionp '(lambda nil))", "t");
+        assert_lisp("(functionp 'car)", "t");
+        assert_lisp("(functionp 'if)", "nil");
+        assert_lisp("(functionp (symbol-function 'if))", "nil");
+    }
+
+    #[test]
+    fn test_fset_circular() {
+        // the closure captures the list that holds it
+        assert_lisp(
+            "(let ((x (list 1))) (setcar x #'(lambda () x)) (fset 'fset-circular (car x)) (eq (car (fset-circular)) (symbol-function 'fset-circular)))",
+            "t",
+        );
+    }
+
+    #[test]
+    fn test_global_hash_table_keys_after_gc() {
+        // the addresses of collected keys are reused by the next ones
+        assert_lisp(
+            "(progn (fset 'global-table-holder (list 'lambda nil (make-hash-table :test 'equal)))
+               (let ((table (nth 2 (symbol-function 'global-table-holder))) (i 0) (n 0))
+                 (while (< i 20) (puthash (list i) i table) (garbage-collect) (setq i (1+ i)))
+                 (maphash #'(lambda (_k _v) (setq n (1+ n))) table)
+                 n))",
+            "20",
+        );
+    }
+
+    #[test]
+    fn test_error_data_survives_gc() {
+        // the data is moved by garbage collection while the error unwinds
+        assert_lisp(
+            r#"(condition-case err
+                   (unwind-protect (car (concat "abc" "def")) (garbage-collect))
+                 (wrong-type-argument err))"#,
+            r#"(wrong-type-argument listp "abcdef")"#,
+        );
+        assert_lisp(
+            r#"(condition-case err
+                   (unwind-protect (aref (make-vector 2 (concat "a" "b")) 5) (garbage-collect))
+                 (args-out-of-range err))"#,
+            r#"(args-out-of-range ["ab" "ab"] 5)"#,
+        );
+    }
+
+    #[test]
+    fn test_special_forms() {
+        assert_lisp("(subrp (symbol-function 'if))", "t");
+        assert_lisp("(subr-arity (symbol-function 'if))", "(2 . unevalled)");
+        assert_lisp("(subr-arity (symbol-function 'car))", "(1 . 1)");
+        assert_lisp("(func-arity 'progn)", "(0 . unevalled)");
+        assert_lisp("(condition-case nil (funcall 'if t 1) (error 'invalid))", "invalid");
+        assert_lisp("(progn (defalias 'my-if 'if) (my-if nil (error \"evaluated\") 2))", "2");
+    }
+
+    #[test]
+    fn test_buffer_local_variables() {
+        assert_lisp(
+            "(progn (defvar test-local 1) (make-local-variable 'test-local) (setq test-local 2)
+               (let ((other (get-buffer-create \"test-local-1\")))
+                 (list test-local (default-value 'test-local) (local-variable-p 'test-local)
+                       (save-current-buffer (set-buffer other) test-local)
+                       (local-variable-p 'test-local other) (buffer-local-value 'test-local other)
+                       (assq 'test-local (buffer-local-variables)))))",
+            "(2 1 t 1 nil 1 (test-local . 2))",
+        );
+        assert_lisp(
+            "(progn (defvar test-local 1) (make-variable-buffer-local 'test-local)
+               (list (local-variable-p 'test-local) (local-variable-if-set-p 'test-local)
+                     (progn (setq test-local 2) (local-variable-p 'test-local))
+                     (progn (set-default 'test-local 3) test-local) (default-value 'test-local)
+                     (progn (kill-local-variable 'test-local) test-local)))",
+            "(nil t t 2 3 3)",
+        );
+        // `let' binds the local value if there is one and the default otherwise
+        assert_lisp(
+            "(progn (defvar test-local 1) (make-local-variable 'test-local) (setq test-local 2)
+               (let ((other (get-buffer-create \"test-local-2\")))
+                 (list (let ((test-local 3))
+                         (list test-local (default-value 'test-local)
+                               (save-current-buffer (set-buffer other) test-local)))
+                       test-local
+                       (save-current-buffer
+                         (set-buffer other)
+                         (let ((test-local 4)) (default-value 'test-local))))))",
+            "((3 1 1) 2 4)",
+        );
+        assert_lisp(
+            "(progn (defvar test-local 1) (make-local-variable 'test-local)
+               (let ((test-local 2)) (kill-local-variable 'test-local) test-local))",
+            "1",
+        );
+        // setting an automatically local variable sets a `let' of its default
+        assert_lisp(
+            "(progn (defvar test-auto 1) (make-variable-buffer-local 'test-auto)
+               (list (let ((test-auto 2)) (setq test-auto 3) (local-variable-p 'test-auto))
+                     test-auto (local-variable-p 'test-auto)
+                     (progn (setq test-auto 4) (local-variable-p 'test-auto))))",
+            "(nil 1 nil t)",
+        );
+    }
+
+    #[test]
+    fn test_variable_aliases() {
+        assert_lisp(
+            "(progn (defvar test-alias-base 1) (defvaralias 'test-alias 'test-alias-base)
+               (setq test-alias 2)
+               (list test-alias test-alias-base (indirect-variable 'test-alias)
+                     (let ((test-alias 3)) test-alias-base) test-alias-base))",
+            "(2 2 test-alias-base 3 2)",
+        );
+        assert_lisp(
+            "(progn (defvaralias 'test-alias-a 'test-alias-b)
+               (condition-case err (defvaralias 'test-alias-b 'test-alias-a)
+                 (cyclic-variable-indirection (car err))))",
+            "cyclic-variable-indirection",
+        );
+    }
+
+    #[test]
+    fn test_variable_watchers() {
+        assert_lisp(
+            "(progn (defvar test-log nil) (defvar test-watched 1)
+               (add-variable-watcher 'test-watched
+                 #'(lambda (sym new op where) (setq test-log (cons (list sym new op where) test-log))))
+               (setq test-watched 2)
+               (let ((test-watched 3)) nil)
+               (makunbound 'test-watched)
+               (defvaralias 'test-watched-alias 'test-watched)
+               (setq test-watched-alias 4)
+               (add-variable-watcher 'test-watched-other (car (get-variable-watchers 'test-watched)))
+               (defvaralias 'test-watched-other 'test-watched)
+               (list (length (get-variable-watchers 'test-watched-alias)) (nreverse test-log)))",
+            "(1 ((test-watched 2 set nil) (test-watched 3 let nil) (test-watched 2 unlet nil)
+                 (test-watched nil makunbound nil) (test-watched 4 set nil)
+                 (test-watched-other test-watched defvaralias nil)))",
+        );
+        assert_lisp(
+            "(progn (defvar test-log nil) (defvar test-watched 1)
+               (defalias 'test-watcher #'(lambda (&rest args) (setq test-log args)))
+               (add-variable-watcher 'test-watched 'test-watcher)
+               (add-variable-watcher 'test-watched 'test-watcher)
+               (let ((watchers (get-variable-watchers 'test-watched)))
+                 (remove-variable-watcher 'test-watched 'test-watcher)
+                 (setq test-watched 2)
+                 (list watchers (get-variable-watchers 'test-watched) test-log)))",
+            "((test-watcher) nil nil)",
+        );
     }
 }
 
 defsym!(MANY);
+defsym!(UNEVALLED);
+defsym!(KEYMAP);
 defsym!(INTEGER);
 defsym!(SYMBOL);
 defsym!(COMPILED_FUNCTION);
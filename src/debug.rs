@@ -1,14 +1,21 @@
-//! Debugging utilities.
+//! Debugging utilities, and the debugger used by the REPL.
+use crate::core::env::{sym, Env};
+use crate::core::gc::{Context, Rt, Rto};
+use crate::core::object::{Object, NIL};
+use crate::eval::EvalError;
+use anyhow::Result;
+use rune_core::macros::root;
+use rune_macros::defun;
+use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 
-static FLAG: AtomicBool = AtomicBool::new(false);
+// Debug output is on by default in tests, but can be turned off for things
+// like the arithmetic benchmark in `bytecode.rs`, where the tracing would
+// dominate.
+static FLAG: AtomicBool = AtomicBool::new(cfg!(test));
 
 pub(crate) fn debug_enabled() -> bool {
-    if cfg!(test) {
-        true
-    } else {
-        FLAG.load(Ordering::Acquire)
-    }
+    FLAG.load(Ordering::Acquire)
 }
 
 pub(crate) fn enable_debug() {
@@ -26,3 +33,51 @@ macro_rules! debug {
         }
     }}
 }
+
+/// The debugger used by the REPL. It is called at the site of an error, so
+/// expressions evaluated here see the dynamic bindings that were active when
+/// the error was signaled.
+#[defun]
+fn repl_debugger<'ob>(
+    _kind: &Rto<Object>,
+    condition: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    println!("Debugger entered--Lisp error: {}", condition.bind(cx));
+    println!("c: continue, e EXPR: evaluate EXPR, r EXPR: return EXPR, q: quit to top level");
+    let stdin = io::stdin();
+    loop {
+        print!("debug> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(NIL);
+        }
+        let line = line.trim();
+        let (command, expr) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "c" => return Ok(NIL),
+            "q" => return crate::eval::top_level(env, cx).map(|_| NIL),
+            "e" | "r" => {
+                let obj = match crate::reader::read(expr, cx) {
+                    Ok((obj, _)) => obj,
+                    Err(e) => {
+                        println!("Error: {e}");
+                        continue;
+                    }
+                };
+                root!(obj, cx);
+                match crate::interpreter::eval_toplevel(obj, env, cx) {
+                    Ok(value) if command == "r" => {
+                        let tag = sym::DEBUGGER_RETURN.into();
+                        return Err(EvalError::throw(tag, value, env).into());
+                    }
+                    Ok(value) => println!("{value}"),
+                    Err(e) => println!("Error: {e}"),
+                }
+            }
+            _ => println!("Unknown command: {line}"),
+        }
+    }
+}
@@ -63,7 +63,7 @@ fn format(string: &str, objects: &[Object]) -> Result<String> {
 }
 
 #[defun]
-fn format_message(string: &str, objects: &[Object]) -> Result<String> {
+pub(crate) fn format_message(string: &str, objects: &[Object]) -> Result<String> {
     let formatted = format(string, objects)?;
     // TODO: implement support for `text-quoting-style`.
     Ok(formatted
@@ -93,20 +93,18 @@ pub(crate) fn insert(args: ArgSlice, env: &mut Rt<Env>, cx: &Context) -> Result<
     Ok(())
 }
 
-// TODO: this should not throw and error. Buffer will always be present.
 #[defun]
-pub(crate) fn goto_char(position: usize, env: &mut Rt<Env>) -> Result<()> {
+pub(crate) fn goto_char(position: usize, env: &mut Rt<Env>) -> usize {
     let buffer = env.current_buffer.get_mut();
-    buffer.text.set_cursor(position);
-    Ok(())
+    // positions are 1-based, but the text cursor is 0-based
+    buffer.text.set_cursor(position.saturating_sub(1));
+    position
 }
 
-// TODO: this should not throw and error. Buffer will always be present.
 #[defun]
-pub(crate) fn point_max(env: &mut Rt<Env>) -> Result<usize> {
-    let buffer = env.current_buffer.get_mut();
+pub(crate) fn point_max(env: &Rt<Env>) -> usize {
     // TODO: Handle narrowing
-    Ok(buffer.text.len_chars() + 1)
+    env.current_buffer.get().text.len_chars() + 1
 }
 
 #[defun]
@@ -118,7 +116,41 @@ pub(crate) fn point_min() -> usize {
 #[defun]
 pub(crate) fn point_marker(env: &mut Rt<Env>) -> usize {
     // TODO: Implement marker objects
-    env.current_buffer.get_mut().text.cursor().chars()
+    point(env)
+}
+
+#[defun]
+pub(crate) fn mark_marker(env: &Rt<Env>) -> Option<usize> {
+    // TODO: Implement marker objects
+    let buffer = env.current_buffer.get();
+    buffer.mark.map(|x| x.min(buffer.text.len_chars() + 1))
+}
+
+/// Set the mark of the current buffer to `pos`, or unset it if `pos` is nil.
+/// This is part of simple.el in GNU Emacs.
+#[defun]
+fn set_mark(pos: Option<usize>, env: &mut Rt<Env>) -> Option<usize> {
+    env.current_buffer.get_mut().mark = pos;
+    pos
+}
+
+/// The start and end of the region between point and mark.
+pub(crate) fn region(env: &Rt<Env>) -> Result<(usize, usize)> {
+    let Some(mark) = mark_marker(env) else {
+        bail!("The mark is not set now, so there is no region")
+    };
+    let point = point(env);
+    Ok((point.min(mark), point.max(mark)))
+}
+
+#[defun]
+pub(crate) fn region_beginning(env: &Rt<Env>) -> Result<usize> {
+    Ok(region(env)?.0)
+}
+
+#[defun]
+pub(crate) fn region_end(env: &Rt<Env>) -> Result<usize> {
+    Ok(region(env)?.1)
 }
 
 #[defun]
@@ -127,15 +159,64 @@ fn delete_region(start: usize, end: usize, env: &mut Rt<Env>) -> Result<()> {
 }
 
 #[defun]
-fn bolp(env: &Rt<Env>) -> bool {
-    let buf = env.current_buffer.get();
-    let chars = buf.text.cursor().chars();
-    chars == 0 || buf.text.char_at(chars - 1).unwrap() == '\n'
+pub(crate) fn bolp(env: &Rt<Env>) -> bool {
+    preceding_char(env) == '\n' || bobp(env)
+}
+
+#[defun]
+pub(crate) fn eolp(env: &Rt<Env>) -> bool {
+    following_char(env) == '\n' || eobp(env)
+}
+
+#[defun]
+pub(crate) fn bobp(env: &Rt<Env>) -> bool {
+    env.current_buffer.get().text.cursor().chars() == 0
+}
+
+#[defun]
+pub(crate) fn eobp(env: &Rt<Env>) -> bool {
+    let text = &env.current_buffer.get().text;
+    text.cursor().chars() == text.len_chars()
+}
+
+#[defun]
+pub(crate) fn point(env: &Rt<Env>) -> usize {
+    env.current_buffer.get().text.cursor().chars() + 1
+}
+
+/// Return the character following POS, or nil if POS is out of range.
+#[defun]
+pub(crate) fn char_after(pos: Option<usize>, env: &Rt<Env>) -> Option<char> {
+    let text = &env.current_buffer.get().text;
+    let pos = pos.unwrap_or_else(|| text.cursor().chars() + 1);
+    if pos == 0 || pos > text.len_chars() {
+        return None;
+    }
+    text.char_at(pos - 1)
+}
+
+/// Return the character before POS, or nil if POS is out of range.
+#[defun]
+pub(crate) fn char_before(pos: Option<usize>, env: &Rt<Env>) -> Option<char> {
+    let text = &env.current_buffer.get().text;
+    let pos = pos.unwrap_or_else(|| text.cursor().chars() + 1);
+    // position 1 has no char before it
+    if pos < 2 || pos > text.len_chars() + 1 {
+        return None;
+    }
+    text.char_at(pos - 2)
+}
+
+/// Return the character following point. At the end of the buffer this is 0.
+#[defun]
+pub(crate) fn following_char(env: &Rt<Env>) -> char {
+    char_after(None, env).unwrap_or('\0')
 }
 
+/// Return the character preceding point. At the start of the buffer this is 0.
 #[defun]
-fn point(env: &Rt<Env>) -> usize {
-    env.current_buffer.get().text.cursor().chars()
+pub(crate) fn preceding_char(env: &Rt<Env>) -> char {
+    char_before(None, env).unwrap_or('\0')
 }
 
 #[defun]
@@ -1,10 +1,10 @@
 //! Lisp evaluation primitives.
 use crate::core::cons::{Cons, ConsError};
-use crate::core::env::{sym, ArgSlice, CallFrame, Env};
+use crate::core::env::{sym, ArgSlice, CallFrame, ConditionHandler, Env};
 use crate::core::error::{Type, TypeError};
-use crate::core::gc::{Rt, Rto};
+use crate::core::gc::{Rt, Rto, Slot};
 use crate::core::object::{
-    display_slice, FnArgs, Function, LispString, ObjectType, Symbol, TagType, NIL,
+    display_slice, FnArgs, Function, LispString, ObjectType, OptionalFlag, Symbol, TagType, NIL,
 };
 use crate::core::{
     gc::Context,
@@ -13,17 +13,25 @@ use crate::core::{
 use crate::data::LispError;
 use crate::fns::{assq, eq};
 use crate::rooted_iter;
-use anyhow::{anyhow, bail, ensure, Result};
+use anyhow::{bail, ensure, Result};
 use fallible_iterator::FallibleIterator;
 use fallible_streaming_iterator::FallibleStreamingIterator;
-use rune_core::macros::{bail_err, call, list, root};
+use rune_core::macros::{bail_err, call, list, rebind, root};
 use rune_macros::defun;
+use std::cell::Cell;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug)]
 pub(crate) struct EvalError {
     backtrace: Vec<Box<str>>,
     pub(crate) error: ErrorType,
+    /// Whether the handlers that run where the error is signaled, such as
+    /// `handler-bind` handlers and the debugger, have already been run
+    signaled: bool,
+    /// Only handlers below this index of the condition stack can handle the
+    /// error. This is set when the error escapes a `handler-bind` handler, as
+    /// it skips the handlers that were muted while that handler ran.
+    handler_depth: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -48,18 +56,30 @@ impl Display for EvalError {
 
 impl EvalError {
     pub(crate) fn new_error(error: anyhow::Error) -> Self {
-        Self { backtrace: Vec::new(), error: ErrorType::Err(error) }
+        Self {
+            backtrace: Vec::new(),
+            error: ErrorType::Err(error),
+            signaled: false,
+            handler_depth: None,
+        }
     }
 
     pub(crate) fn signal(error_symbol: Object, data: Object, env: &mut Rt<Env>) -> Self {
         Self {
             backtrace: Vec::new(),
             error: ErrorType::Signal(env.set_exception(error_symbol, data)),
+            signaled: false,
+            handler_depth: None,
         }
     }
 
     pub(crate) fn throw(tag: Object, data: Object, env: &mut Rt<Env>) -> Self {
-        Self { backtrace: Vec::new(), error: ErrorType::Throw(env.set_exception(tag, data)) }
+        Self {
+            backtrace: Vec::new(),
+            error: ErrorType::Throw(env.set_exception(tag, data)),
+            signaled: false,
+            handler_depth: None,
+        }
     }
 
     pub(crate) fn new(error: impl Into<Self>) -> Self {
@@ -69,7 +89,12 @@ impl EvalError {
     pub(crate) fn with_trace(error: anyhow::Error, name: &str, args: &[Rto<Object>]) -> Self {
         let display = display_slice(args);
         let trace = format!("{name} {display}").into_boxed_str();
-        Self { backtrace: vec![trace], error: ErrorType::Err(error) }
+        Self {
+            backtrace: vec![trace],
+            error: ErrorType::Err(error),
+            signaled: false,
+            handler_depth: None,
+        }
     }
 
     pub(crate) fn add_trace(mut self, name: &str, args: &[Rto<Object>]) -> Self {
@@ -85,11 +110,274 @@ impl EvalError {
         }
         println!("END_BACKTRACE");
     }
+
+    /// Whether this error is a `throw` to `tag`.
+    pub(crate) fn is_throw_to(&self, tag: Symbol, env: &Rt<Env>, cx: &Context) -> bool {
+        let ErrorType::Throw(id) = self.error else { return false };
+        env.get_exception(id).is_some_and(|(x, _)| x.bind(cx) == tag)
+    }
+
+    /// Whether the handler at `index` of the condition stack can handle this
+    /// error.
+    pub(crate) fn is_visible_to(&self, index: usize) -> bool {
+        self.handler_depth.is_none_or(|depth| index < depth)
+    }
+
+    /// The `(ERROR-SYMBOL . DATA)` condition that this error was signaled
+    /// with, or `None` if it is a `throw`.
+    pub(crate) fn condition<'ob>(&self, env: &Rt<Env>, cx: &'ob Context) -> Option<&'ob Cons> {
+        match &self.error {
+            ErrorType::Throw(_) => None,
+            ErrorType::Signal(id) => {
+                let Some((sym, data)) = env.get_exception(*id) else {
+                    unreachable!("Exception not found")
+                };
+                Some(Cons::new(sym, data, cx))
+            }
+            ErrorType::Err(e) => {
+                if let Some(lisp_error) = e.downcast_ref::<LispError>() {
+                    Some(lisp_error.bind(cx))
+                } else if let Some(type_error) = e.downcast_ref::<TypeError>() {
+                    Some(type_error.condition(cx))
+                } else if let Some(read_error) = e.downcast_ref::<crate::reader::Error>() {
+                    Some(read_error.condition(cx))
+                } else if let Some(io_error) = e.downcast_ref::<std::io::Error>() {
+                    let data = Cons::new1(io_error.to_string(), cx);
+                    Some(Cons::new(sym::FILE_ERROR, data, cx))
+                } else {
+                    // Errors that don't have a Lisp condition of their own are
+                    // signaled as plain errors with their message
+                    Some(Cons::new(sym::ERROR, Cons::new1(format!("{e}"), cx), cx))
+                }
+            }
+        }
+    }
+}
+
+/// Whether a `condition-case` handler for `spec` handles `condition`. `spec` is
+/// either `t`, which handles every error, a condition name, or a list of
+/// them. The condition names of an error are the `error-conditions` property
+/// of its error symbol. This is used by both the interpreter and the VM so
+/// that they dispatch errors the same way.
+pub(crate) fn handles_condition(
+    spec: Object,
+    condition: &Cons,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> bool {
+    let error_symbol = condition.car();
+    let conditions = match error_symbol.untag() {
+        ObjectType::Symbol(sym) => crate::data::get(sym, sym::ERROR_CONDITIONS, env, cx),
+        _ => NIL,
+    };
+    let matches = |name: Object| {
+        name == sym::TRUE
+            || conditions.as_list().is_ok_and(|mut x| x.any(|x| x.is_ok_and(|x| x == name)))
+    };
+    match spec.untag() {
+        ObjectType::NIL => false,
+        ObjectType::Symbol(_) => matches(spec),
+        // A `debug` element only asks for the debugger to be entered, so it is
+        // not a condition name and never matches on its own
+        ObjectType::Cons(names) => names.elements().any(|x| x.is_ok_and(&matches)),
+        _ => false,
+    }
+}
+
+/// Search the condition stack below `from` for the innermost handler that
+/// applies to `condition`, skipping the handlers that are muted while a
+/// `handler-bind` handler runs.
+fn find_handler(condition: &Cons, from: usize, env: &Rt<Env>, cx: &Context) -> Option<usize> {
+    let mut idx = from;
+    while idx > 0 {
+        idx -= 1;
+        match &env.condition_stack.bind_ref(cx)[idx] {
+            ConditionHandler::Case(spec) | ConditionHandler::Bind(spec, _, _) => {
+                if handles_condition(**spec, condition, env, cx) {
+                    return Some(idx);
+                }
+            }
+            ConditionHandler::Skip(to) => idx = *to,
+        }
+    }
+    None
+}
+
+/// Run the handlers for `err` at the site where it was signaled, before
+/// anything has been unwound. First the `handler-bind` handlers for the error
+/// are called, innermost first, until a `condition-case` that handles it is
+/// reached. Then it is offered to the debugger, see [maybe_call_debugger].
+///
+/// A handler that returns normally lets the error continue. One that exits
+/// non-locally replaces the error, and an error it signals can only be
+/// handled outside of the `handler-bind` that it was bound by.
+pub(crate) fn run_signal_handlers<'ob>(
+    err: &mut EvalError,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Option<Object<'ob>>, EvalError> {
+    if std::mem::replace(&mut err.signaled, true) {
+        return Ok(None);
+    }
+    let Some(condition) = err.condition(env, cx) else { return Ok(None) };
+    let nesting = condition.car() == sym::EXCESSIVE_LISP_NESTING;
+    let condition: Object = condition.into();
+    root!(condition, cx);
+    // Like Emacs, let the handlers and the debugger nest deeper than the limit
+    // that was just exceeded
+    // that was just exceeded, using the native stack that was held back for
+    // them
+    let reserve = nesting.then(|| NATIVE_STACK_RESERVE_OPEN.replace(true));
+    if nesting {
+        let limit = env.stack.current_frame() as i64 + HANDLER_EVAL_DEPTH;
+        env.varbind(sym::MAX_LISP_EVAL_DEPTH, limit.into(), cx);
+    }
+    let result = match call_signal_handlers(condition, env, cx) {
+        Ok(value) => Ok(rebind!(value, cx)),
+        Err(e) => Err(e),
+    };
+    if let Some(open) = reserve {
+        env.unbind(1, cx);
+        NATIVE_STACK_RESERVE_OPEN.set(open);
+    }
+    result
+}
+
+fn call_signal_handlers<'ob>(
+    condition: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Option<Object<'ob>>, EvalError> {
+    let mut idx = env.condition_stack.len();
+    while let Some(found) = find_handler(condition.as_cons().untag(cx), idx, env, cx) {
+        let ConditionHandler::Bind(_, handler, first) = &env.condition_stack.bind_ref(cx)[found]
+        else {
+            break;
+        };
+        let first = *first;
+        let handler: Function = (**handler).try_into()?;
+        root!(handler, cx);
+        let depth = env.condition_stack.len();
+        env.condition_stack.push(ConditionHandler::Skip(first));
+        let result = call!(handler, condition; env, cx).map(|_| ());
+        env.condition_stack.truncate(depth);
+        if let Err(mut e) = result {
+            if !matches!(e.error, ErrorType::Throw(_)) {
+                e.handler_depth = Some(e.handler_depth.map_or(first, |x| x.min(first)));
+            }
+            e.signaled = true;
+            return Err(e);
+        }
+        idx = found;
+    }
+    maybe_call_debugger(condition, env, cx)
+}
+
+/// Offer the error `condition` to the debugger. The debugger is entered if
+/// `debug-on-signal` is set, or if `debug-on-error` wants the error and no
+/// active `condition-case` handles it (other than one that lists `debug`). It
+/// is called with `error` and the `(ERROR-SYMBOL . DATA)` condition, and can
+///
+/// - return normally to let the error keep unwinding
+/// - throw to `debugger-return` to return the thrown value in place of the
+///   error, which is returned as `Some`
+/// - throw to `top-level` to abort, which replaces the error
+fn maybe_call_debugger<'ob>(
+    condition: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Option<Object<'ob>>, EvalError> {
+    let cons = condition.as_cons().untag(cx);
+    let var = |sym| env.var(sym).map_or(NIL, |x| x.bind(cx));
+    let debugger = var(sym::DEBUGGER);
+    if debugger.is_nil()
+        || !var(sym::INHIBIT_DEBUGGER).is_nil()
+        || handles_condition(var(sym::DEBUG_IGNORED_ERRORS), cons, env, cx)
+    {
+        return Ok(None);
+    }
+    let handled = || {
+        let mut idx = env.condition_stack.len();
+        while let Some(found) = find_handler(cons, idx, env, cx) {
+            if let ConditionHandler::Case(spec) = &env.condition_stack.bind_ref(cx)[found] {
+                return match spec.untag() {
+                    ObjectType::Cons(names) => {
+                        !names.elements().any(|x| x.is_ok_and(|x| x == sym::DEBUG))
+                    }
+                    _ => true,
+                };
+            }
+            idx = found;
+        }
+        false
+    };
+    let wanted = !var(sym::DEBUG_ON_SIGNAL).is_nil()
+        || (handles_condition(var(sym::DEBUG_ON_ERROR), cons, env, cx) && !handled());
+    if !wanted {
+        return Ok(None);
+    }
+    let debugger: Function = debugger.try_into()?;
+    root!(debugger, cx);
+    env.varbind(sym::INHIBIT_DEBUGGER, sym::TRUE.into(), cx);
+    env.catch_stack.push(Object::from(sym::DEBUGGER_RETURN));
+    let result = call!(debugger, Object::from(sym::ERROR), condition; env, cx).map(|_| ());
+    env.catch_stack.pop();
+    env.unbind(1, cx);
+    match result {
+        Ok(_) => Ok(None),
+        Err(mut e) => {
+            if let ErrorType::Throw(id) = e.error {
+                if let Some((tag, value)) = env.get_exception(id) {
+                    if tag.bind(cx) == sym::DEBUGGER_RETURN {
+                        return Ok(Some(value.bind(cx)));
+                    }
+                }
+            }
+            e.signaled = true;
+            Err(e)
+        }
+    }
+}
+
+/// Call `bodyfun` with `handler-bind` handlers active. `handlers` alternates
+/// between a list of conditions and the function that handles them, and the
+/// first handler is tried first.
+#[defun(name = "handler-bind-1")]
+fn handler_bind_1<'ob>(
+    bodyfun: &Rto<Function>,
+    handlers: ArgSlice,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    ensure!(
+        handlers.len().is_multiple_of(2),
+        "handler-bind-1 needs a handler for each condition list"
+    );
+    let depth = env.condition_stack.len();
+    // push in reverse so that the first handler is the innermost
+    let handlers: Vec<_> = env
+        .stack
+        .arg_slice(handlers)
+        .rchunks(2)
+        .map(|pair| {
+            let conditions = Slot::new(pair[0].bind(cx));
+            ConditionHandler::Bind(conditions, Slot::new(pair[1].bind(cx)), depth)
+        })
+        .collect();
+    for handler in handlers {
+        env.condition_stack.push(handler);
+    }
+    let result = call!(bodyfun; env, cx);
+    env.condition_stack.truncate(depth);
+    result.map_err(Into::into)
 }
 
 impl From<anyhow::Error> for EvalError {
     fn from(e: anyhow::Error) -> Self {
-        Self::new_error(e)
+        match e.downcast::<EvalError>() {
+            Ok(e) => e,
+            Err(e) => Self::new_error(e),
+        }
     }
 }
 
@@ -177,7 +465,7 @@ fn run_hooks<'ob>(hooks: ArgSlice, env: &mut Rt<Env>, cx: &'ob mut Context) -> R
         let hook = env.stack[hook_count - i - 1].bind(cx);
         match hook.untag() {
             ObjectType::Symbol(sym) => {
-                if let Some(val) = env.vars.get(sym) {
+                if let Some(val) = env.var(sym) {
                     let val = val.bind(cx);
                     match val.untag() {
                         ObjectType::Cons(hook_list) => {
@@ -211,7 +499,7 @@ fn run_hook_with_args<'ob>(
 ) -> Result<Object<'ob>> {
     match hook.untag(cx) {
         ObjectType::Symbol(sym) => {
-            if let Some(val) = env.vars.get(sym) {
+            if let Some(val) = env.var(sym) {
                 let val = val.bind(cx);
                 match val.untag() {
                     ObjectType::Cons(hook_list) => {
@@ -238,6 +526,11 @@ fn run_hook_with_args<'ob>(
     Ok(NIL)
 }
 
+/// Load the file of the autoload object `fundef`. If `funname` is given, return
+/// its definition after loading, which must no longer be the autoload. If
+/// `macro_only` is `macro`, only autoloads of macros are loaded, errors are
+/// ignored and nil is returned. Functions defined by a load that fails are
+/// restored to their previous definitions.
 #[defun]
 pub(crate) fn autoload_do_load<'ob>(
     fundef: &Rto<Object>,
@@ -246,28 +539,48 @@ pub(crate) fn autoload_do_load<'ob>(
     env: &mut Rt<Env>,
     cx: &'ob mut Context,
 ) -> Result<Object<'ob>> {
-    // TODO: want to handle the case where the file is already loaded.
+    // (autoload FILE DOCSTRING INTERACTIVE TYPE)
     let Ok((sym::AUTOLOAD, ObjectType::Cons(body))) = fundef.bind(cx).as_cons_pair() else {
         return Ok(fundef.bind(cx));
     };
-    ensure!(macro_only.is_none(), "autoload-do-load macro-only is not yet implemented");
-    let mut iter = body.elements();
-    let file: Gc<&LispString> = match iter.next() {
-        Some(x) => x?.try_into()?,
-        None => bail!("Malformed autoload"),
-    };
-    ensure!(
-        iter.fallible().all(|x| Ok(x.is_nil()))?,
-        "autoload arguments are not yet implemented"
-    );
+    let file: Gc<&LispString> = body.car().try_into()?;
+    // keymap autoloads are loaded when the keymap is used, see
+    // `crate::keymap::get_keymap`
+    let kind = crate::fns::nth(3, body.into())?;
+    let macro_only = macro_only.is_some_and(|x| x.bind(cx) == sym::MACRO);
+    if macro_only && kind != sym::TRUE && kind != sym::MACRO {
+        return Ok(fundef.bind(cx));
+    }
     root!(file, cx);
-    crate::lread::load(file, None, None, cx, env)?;
-    match funname {
-        Some(func) => match func.untag(cx).func(cx) {
-            Some(x) => Ok(x.into()),
-            None => Err(anyhow!("autoload of {func} did not provide a definition")),
-        },
-        _ => Ok(NIL),
+    let features = env.var(sym::FEATURES).map_or(NIL, |x| x.bind(cx));
+    root!(features, cx);
+    // expanding a macro should not fail just because its file is missing
+    let noerror = macro_only.then_some(());
+    let queued = env.autoload_queue.len();
+    env.autoload_depth += 1;
+    let result = crate::lread::load(file, noerror, None, cx, env);
+    env.autoload_depth -= 1;
+    if result.is_err() {
+        // undo the definitions of the failed load, newest first
+        for i in (queued..env.autoload_queue.len()).rev() {
+            let (symbol, old) = &*env.autoload_queue[i];
+            crate::data::fset(symbol.bind(cx), old.bind(cx))?;
+        }
+        env.set_var(sym::FEATURES, features.bind(cx), cx)?;
+    }
+    // once a load has finished, an outer failed load does not undo it
+    env.autoload_queue.truncate(queued);
+    result?;
+    let Some(funname) = funname.filter(|_| !macro_only) else { return Ok(NIL) };
+    let name = funname.untag(cx);
+    match name.follow_indirect(cx) {
+        // a file that does not define the function would be loaded forever
+        Some(func) if Object::from(func) != fundef.bind(cx) => Ok(func.into()),
+        _ => {
+            let message =
+                format!("Autoloading file {} failed to define function {name}", file.untag(cx));
+            Err(LispError::error(&message, cx).into())
+        }
     }
 }
 
@@ -278,13 +591,44 @@ fn autoload<'ob>(
     docstring: Option<Object>,
     interactive: Option<Object>,
     load_type: Option<Object>,
+    env: &mut Rt<Env>,
     cx: &'ob Context,
 ) -> Result<Symbol<'ob>> {
-    if function.has_func() {
-        Ok(sym::NIL)
-    } else {
-        let autoload = list![sym::AUTOLOAD, file, docstring, interactive, load_type; cx];
-        crate::data::fset(function, autoload)
+    // only replace a definition if it is another autoload
+    if let Some(func) = function.func(cx) {
+        if !matches!(func.as_cons_pair(), Ok((sym::AUTOLOAD, _))) {
+            return Ok(sym::NIL);
+        }
+    }
+    let autoload = list![sym::AUTOLOAD, file, docstring, interactive, load_type; cx];
+    crate::data::defalias(function, autoload, None, env, cx)
+}
+
+#[defun]
+pub(crate) fn commandp(
+    function: Object,
+    for_call_interactively: OptionalFlag,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> Result<bool> {
+    let mut fun = function;
+    while let ObjectType::Symbol(sym) = fun.untag() {
+        if !crate::data::get(sym, sym::INTERACTIVE_FORM, env, cx).is_nil() {
+            return Ok(true);
+        }
+        let Some(func) = sym.func(cx) else { return Ok(false) };
+        fun = func.into();
+    }
+    match fun.untag() {
+        // keyboard macros can be run with `command-execute'
+        ObjectType::String(_) | ObjectType::ByteString(_) | ObjectType::Vec(_) => {
+            Ok(for_call_interactively.is_none())
+        }
+        ObjectType::Cons(cons) if cons.car() == sym::AUTOLOAD => {
+            // (autoload FILE DOCSTRING INTERACTIVE TYPE)
+            Ok(!crate::fns::nth(3, cons.into())?.is_nil())
+        }
+        _ => Ok(!crate::data::function_interactive_form(fun, cx)?.is_nil()),
     }
 }
 
@@ -305,7 +649,22 @@ pub(crate) fn macroexpand<'ob>(
         },
         _ => get_macro_func(sym, cx),
     };
-    let Some(macro_func) = func else { return Ok(form.bind(cx)) };
+    let Some(macro_func) = func else {
+        // load the macro if it is autoloaded and try again
+        if let Some(fundef) = sym.follow_indirect(cx).map(Object::from) {
+            if let Ok((sym::AUTOLOAD, _)) = fundef.as_cons_pair() {
+                root!(fundef, cx);
+                root!(name, sym.tag(), cx);
+                root!(macro_only, Object::from(sym::MACRO), cx);
+                autoload_do_load(fundef, Some(name), Some(macro_only), env, cx)?;
+                let still_autoload = name.untag(cx).follow_indirect(cx).map(Object::from);
+                if still_autoload != Some(fundef.bind(cx)) {
+                    return macroexpand(form, environment, cx, env);
+                }
+            }
+        }
+        return Ok(form.bind(cx));
+    };
     let mut iter = cons.cdr().as_list()?.fallible();
     let mut frame = CallFrame::new(env);
     while let Some(arg) = iter.next()? {
@@ -338,7 +697,6 @@ fn func_arity<'ob>(function: Function, cx: &'ob Context) -> Result<&'ob Cons> {
     let from_args = |args: FnArgs| {
         let min = args.required;
         if args.rest {
-            // TODO: Handle unevalled
             Cons::new(min, sym::MANY, cx)
         } else {
             Cons::new(min, args.optional + min, cx)
@@ -346,7 +704,7 @@ fn func_arity<'ob>(function: Function, cx: &'ob Context) -> Result<&'ob Cons> {
     };
     match function.untag() {
         FunctionType::ByteFn(func) => Ok(from_args(func.args)),
-        FunctionType::SubrFn(func) => Ok(from_args(func.args)),
+        FunctionType::SubrFn(func) => Ok(crate::data::subr_arity(func, cx)),
         FunctionType::Cons(func) => {
             let arg_pos = match func.car().untag() {
                 ObjectType::Symbol(sym::CLOSURE) => 2,
@@ -354,7 +712,7 @@ fn func_arity<'ob>(function: Function, cx: &'ob Context) -> Result<&'ob Cons> {
                 other => bail!(TypeError::new(Type::Func, other)),
             };
             let Some(args) = func.elements().fallible().nth(arg_pos)? else {
-                bail!("Invalid function: {func}")
+                bail!(LispError::invalid_function(func.into(), cx))
             };
             let (req, opt, rest) = crate::interpreter::parse_arg_list(args)?;
             let args = FnArgs {
@@ -366,7 +724,9 @@ fn func_arity<'ob>(function: Function, cx: &'ob Context) -> Result<&'ob Cons> {
             Ok(from_args(args))
         }
         FunctionType::Symbol(sym) => {
-            let Some(func) = sym.follow_indirect(cx) else { bail!("Void Function: {sym}") };
+            let Some(func) = sym.follow_indirect(cx) else {
+                bail!(LispError::void_function(sym, cx))
+            };
             func_arity(func, cx)
         }
     }
@@ -390,30 +750,243 @@ fn signal(mut error_symbol: Object, data: Object, env: &mut Rt<Env>) -> Result<b
     Err(EvalError::signal(error_symbol, data, env).into())
 }
 
+#[defun]
+fn throw(tag: Object, value: Object, env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    // Need to check now that there is a catch, because we may have a
+    // condition-case along the unwind path
+    if env.catch_stack.iter().any(|x| x.bind(cx) == tag) {
+        Err(EvalError::throw(tag, value, env).into())
+    } else {
+        bail!(LispError::no_catch(tag, value, cx))
+    }
+}
+
+/// Exit to the top level by throwing to `top-level`.
+#[defun]
+pub(crate) fn top_level(env: &mut Rt<Env>, cx: &Context) -> Result<bool> {
+    throw(sym::TOP_LEVEL.into(), NIL, env, cx)
+}
+
+/// The frames of the backtrace, innermost first, as `[EVALD FUNC ARGS]` along
+/// with the binding depth of each frame. If `base` is non-nil the frames start
+/// at the innermost call to `base`.
+fn backtrace_from<'ob>(
+    base: Option<&Rto<Object>>,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Vec<([Object<'ob>; 3], usize)> {
+    let indirect = |func: Object<'ob>| match func.untag() {
+        ObjectType::Symbol(sym) => sym.follow_indirect(cx).map_or(func, Into::into),
+        _ => func,
+    };
+    let base = base.map(|x| indirect(x.bind(cx)));
+    env.stack
+        .backtrace()
+        .skip_while(|frame| base.is_some_and(|base| indirect(frame.func.bind(cx)) != base))
+        .map(|frame| {
+            let args = Rt::bind_slice(frame.args, cx);
+            let args = match (frame.evaluated, frame.rest) {
+                (false, _) => args[0],
+                (true, false) => crate::fns::slice_into_list(args, None, cx),
+                (true, true) => {
+                    let (rest, args) = args.split_last().unwrap();
+                    crate::fns::slice_into_list(args, Some(*rest), cx)
+                }
+            };
+            let func = frame.func.bind(cx);
+            ([frame.evaluated.into(), func, args], frame.binding_depth)
+        })
+        .collect()
+}
+
+/// Call `function` with `(EVALD FUNC ARGS FLAGS)` for each frame of the
+/// backtrace, starting at the innermost call to `base` if it is non-nil.
+#[defun]
+fn mapbacktrace(
+    function: &Rto<Function>,
+    base: Option<&Rto<Object>>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<bool> {
+    let frames: Vec<Object> = backtrace_from(base, env, cx).into_iter().flat_map(|x| x.0).collect();
+    root!(frames, cx);
+    for frame in frames.chunks(3) {
+        let [evald, func, args] = frame else { unreachable!() };
+        call!(function, evald, func, args, NIL; env, cx)?;
+    }
+    Ok(false)
+}
+
+/// Call `function` with `(EVALD FUNC ARGS FLAGS)` for the frame `nframes` out
+/// from the innermost call to `base`, and return its result. Return nil if
+/// there is no such frame.
+#[defun(name = "backtrace-frame--internal")]
+fn backtrace_frame_internal<'ob>(
+    function: &Rto<Function>,
+    nframes: usize,
+    base: Option<&Rto<Object>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    let Some((frame, _)) = backtrace_from(base, env, cx).into_iter().nth(nframes) else {
+        return Ok(NIL);
+    };
+    let [evald, func, args] = frame;
+    call!(function, evald, func, args, NIL; env, cx).map_err(Into::into)
+}
+
+/// Evaluate `exp` in the dynamic environment of the frame `nframes` out from
+/// the innermost call to `base`.
+#[defun]
+fn backtrace_eval<'ob>(
+    exp: &Rto<Object>,
+    nframes: usize,
+    base: Option<&Rto<Object>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    let Some(&(_, depth)) = backtrace_from(base, env, cx).get(nframes) else {
+        bail!("Activation frame {nframes} not found")
+    };
+    env.swap_bindings(depth, true, cx);
+    let value = match crate::interpreter::eval_toplevel(exp, env, cx) {
+        Ok(x) => rebind!(x, cx),
+        Err(e) => {
+            env.swap_bindings(depth, false, cx);
+            return Err(e);
+        }
+    };
+    env.swap_bindings(depth, false, cx);
+    Ok(value)
+}
+
 #[defun]
 fn special_variable_p(symbol: Symbol) -> bool {
     symbol.is_special()
 }
 
+#[defun]
+fn default_toplevel_value<'ob>(
+    symbol: Symbol,
+    env: &Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Object<'ob>> {
+    match env.toplevel_value(symbol, cx) {
+        Some(value) => Ok(value),
+        None => Err(LispError::void_variable(symbol, cx).into()),
+    }
+}
+
 #[defun]
 fn set_default_toplevel_value<'ob>(
     symbol: Symbol,
     value: Object,
     env: &'ob mut Rt<Env>,
+    cx: &Context,
 ) -> Result<Object<'ob>> {
-    env.set_var(symbol, value)?;
+    env.set_toplevel_value(symbol, value, cx)?;
     Ok(NIL)
 }
 
 #[defun]
 fn set_default<'ob>(
-    symbol: Symbol,
-    value: Object<'ob>,
-    env: &'ob mut Rt<Env>,
+    symbol: &Rto<Gc<Symbol>>,
+    value: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
 ) -> Result<Object<'ob>> {
-    // TODO: implement buffer local variables
-    env.set_var(symbol, value)?;
-    Ok(value)
+    let var: Symbol = symbol.untag(cx);
+    root!(var, cx);
+    root!(buffer, NIL, cx);
+    crate::data::notify_variable_watchers(var, value, sym::SET, buffer, env, cx)?;
+    env.set_default(symbol.untag(cx), value.bind(cx), cx)?;
+    Ok(value.bind(cx))
+}
+
+/// Signal `excessive-lisp-nesting` if the depth of lisp calls is more than
+/// `max-lisp-eval-depth`, or if the native stack is close to overflowing.
+/// Every frame on the [LispStack](crate::core::env::LispStack) counts as one
+/// level of nesting.
+pub(crate) fn check_eval_depth(env: &Rt<Env>, cx: &Context) -> Result<()> {
+    let depth = env.stack.current_frame();
+    let max_depth = match env.var(sym::MAX_LISP_EVAL_DEPTH).map(|x| x.bind(cx).untag()) {
+        // like Emacs, don't let the limit be so low that nothing can run
+        Some(ObjectType::Int(max)) => max.max(MIN_LISP_EVAL_DEPTH) as usize,
+        _ => DEFAULT_LISP_EVAL_DEPTH as usize,
+    };
+    if depth > max_depth || native_stack_exhausted() {
+        bail!(LispError::excessive_lisp_nesting(depth, cx));
+    }
+    Ok(())
+}
+
+const DEFAULT_LISP_EVAL_DEPTH: i64 = 1600;
+const MIN_LISP_EVAL_DEPTH: i64 = 100;
+/// How much deeper than the exceeded limit the handlers of an
+/// `excessive-lisp-nesting` error may nest.
+const HANDLER_EVAL_DEPTH: i64 = 100;
+
+/// The most native stack that one level of Lisp nesting takes. Frames are
+/// several times larger without optimizations.
+const NATIVE_FRAME_SIZE: usize = if cfg!(debug_assertions) { 10 * 1024 } else { 4 * 1024 };
+
+/// How much of the native stack is kept free for the handlers and the
+/// debugger that run after a nesting error is signaled.
+const NATIVE_STACK_RESERVE: usize = HANDLER_EVAL_DEPTH as usize * NATIVE_FRAME_SIZE;
+
+/// How much of the native stack is never used by Lisp, so that an error can
+/// still be signaled once the reserve is used up as well.
+const NATIVE_STACK_MARGIN: usize = 64 * 1024;
+
+/// The native stack size of threads started from Lisp. This is the default
+/// size of the main thread, so that Lisp can nest as deep there as on the
+/// main thread.
+pub(crate) const LISP_THREAD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+thread_local! {
+    /// The lowest address of this thread's stack, if it is known.
+    static NATIVE_STACK_BASE: Option<usize> = native_stack_bounds().map(|(base, _)| base);
+    /// Whether the handlers of a nesting error are running, and so may use
+    /// the native stack reserve.
+    static NATIVE_STACK_RESERVE_OPEN: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the native stack of this thread is nearly used up. Lisp recursion
+/// also recurses in Rust, so this lets us signal an error before the process
+/// would crash with a stack overflow.
+fn native_stack_exhausted() -> bool {
+    let marker = 0u8;
+    let sp = std::ptr::addr_of!(marker) as usize;
+    let reserve = if NATIVE_STACK_RESERVE_OPEN.get() {
+        NATIVE_STACK_MARGIN
+    } else {
+        NATIVE_STACK_MARGIN + NATIVE_STACK_RESERVE
+    };
+    NATIVE_STACK_BASE.with(|base| base.is_some_and(|base| sp < base + reserve))
+}
+
+/// The lowest address and the size of the native stack of this thread.
+#[cfg(target_os = "linux")]
+fn native_stack_bounds() -> Option<(usize, usize)> {
+    // SAFETY: the attributes are initialized by `pthread_getattr_np` before
+    // they are read, and are destroyed afterwards
+    unsafe {
+        let mut attr = std::mem::MaybeUninit::<libc::pthread_attr_t>::uninit();
+        if libc::pthread_getattr_np(libc::pthread_self(), attr.as_mut_ptr()) != 0 {
+            return None;
+        }
+        let mut addr = std::ptr::null_mut();
+        let mut size = 0;
+        let result = libc::pthread_attr_getstack(attr.as_ptr(), &mut addr, &mut size);
+        libc::pthread_attr_destroy(attr.as_mut_ptr());
+        (result == 0).then_some((addr as usize, size))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn native_stack_bounds() -> Option<(usize, usize)> {
+    // TODO: find the stack bounds on other platforms
+    None
 }
 
 impl Rto<Function<'_>> {
@@ -424,8 +997,10 @@ impl Rto<Function<'_>> {
         cx: &'ob mut Context,
     ) -> EvalResult<'ob> {
         debug!("calling: {self}");
+        check_eval_depth(frame, cx)?;
         let name = name.unwrap_or("lambda");
         frame.finalize_arguments();
+        frame.stack.set_frame_function(self.bind(cx).into(), true);
         let arg_cnt = frame.arg_count();
         cx.garbage_collect(false);
         match self.untag(cx) {
@@ -435,30 +1010,41 @@ impl Rto<Function<'_>> {
                     .map_err(|e| e.add_trace(name, frame.arg_slice()))
             }
             FunctionType::SubrFn(f) => {
-                (*f).call(arg_cnt, frame, cx).map_err(|e| add_trace(e, name, frame.arg_slice()))
+                let mut err = match (*f).call(arg_cnt, frame, cx) {
+                    Ok(x) => return Ok(rebind!(x, cx)),
+                    Err(e) => add_trace(e, name, frame.arg_slice()),
+                };
+                match run_signal_handlers(&mut err, frame, cx) {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => Err(err),
+                    Err(e) => Err(e),
+                }
             }
-            FunctionType::Cons(_) => {
+            FunctionType::Cons(closure) => {
+                if let Some((advice, func, how)) = crate::nadvice::advice_slots(closure) {
+                    root!(advice, cx);
+                    root!(func, cx);
+                    return crate::nadvice::call(advice, func, how, frame, cx)
+                        .map_err(|e| e.add_trace(name, frame.arg_slice()));
+                }
                 crate::interpreter::call_closure(self.try_as().unwrap(), arg_cnt, name, frame, cx)
                     .map_err(|e| e.add_trace(name, frame.arg_slice()))
             }
             FunctionType::Symbol(sym) => {
-                let Some(func) = sym.follow_indirect(cx) else { bail_err!("Void Function: {sym}") };
-                if let Ok((sym::AUTOLOAD, _)) = func.as_cons_pair() {
-                    // TODO: inifinite loop if autoload does not resolve
-                    root!(sym, cx);
-                    crate::eval::autoload_do_load(self.cast(), None, None, frame, cx)
-                        .map_err(|e| add_trace(e, name, frame.arg_slice()))?;
-                    let Some(func) = sym.bind(cx).follow_indirect(cx) else {
-                        bail_err!("autoload for {sym} failed to define function")
-                    };
-                    root!(func, cx);
-                    let name = sym.bind(cx).name().to_owned();
-                    func.call(frame, Some(&name), cx)
-                } else {
-                    root!(func, cx);
-                    let name = sym.name().to_owned();
-                    func.call(frame, Some(&name), cx)
+                let Some(func) = sym.follow_indirect(cx) else {
+                    bail_err!(LispError::void_function(sym, cx))
+                };
+                root!(func, cx);
+                let name = sym.name().to_owned();
+                if let Ok((sym::AUTOLOAD, _)) = func.bind(cx).as_cons_pair() {
+                    let fundef = Object::from(func.bind(cx));
+                    root!(fundef, cx);
+                    root!(sym, sym.tag(), cx);
+                    let loaded = autoload_do_load(fundef, Some(sym), None, frame, cx)
+                        .map_err(|e| add_trace(e, &name, frame.arg_slice()))?;
+                    func.set(Function::try_from(loaded)?);
                 }
+                func.call(frame, Some(&name), cx)
             }
         }
     }
@@ -500,10 +1086,19 @@ defsym!(AND);
 defsym!(OR);
 defsym!(INTERACTIVE);
 defsym!(CATCH);
-defsym!(THROW);
 defsym!(ERROR);
 defsym!(DEBUG);
 defsym!(VOID_VARIABLE);
+defsym!(ERROR_CONDITIONS);
+defsym!(KW_SUCCESS, ":success");
+
+defsym!(DEBUGGER_RETURN);
+defsym!(DEBUG_EARLY);
 
 defvar!(DEBUG_ON_ERROR, false);
+defvar!(DEBUG_ON_SIGNAL, false);
+defvar!(DEBUG_IGNORED_ERRORS);
+defvar!(DEBUGGER, sym::DEBUG_EARLY);
+defvar!(INHIBIT_DEBUGGER, false);
 defvar!(INTERNAL_MAKE_INTERPRETED_CLOSURE_FUNCTION);
+defvar!(MAX_LISP_EVAL_DEPTH, 1600);
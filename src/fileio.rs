@@ -6,6 +6,7 @@ use crate::core::{
     gc::{Context, Rt},
     object::{Number, Object, ObjectType, OptionalFlag},
 };
+use crate::data::LispError;
 use anyhow::{bail, ensure, Result};
 use rune_macros::defun;
 use std::path::{Component, Path, MAIN_SEPARATOR};
@@ -27,7 +28,7 @@ pub(crate) fn expand_file_name(
         let path = Path::new(dir);
         Ok(path.join(name).to_string_lossy().to_string())
     } else {
-        let dir = env.vars.get(sym::DEFAULT_DIRECTORY).unwrap();
+        let dir = env.var(sym::DEFAULT_DIRECTORY).unwrap();
         match dir.untag(cx) {
             ObjectType::String(dir) => {
                 let path = Path::new(dir.as_ref());
@@ -205,6 +206,7 @@ fn write_region(
     lockname: OptionalFlag,
     mustbenew: OptionalFlag,
     env: &Rt<Env>,
+    cx: &Context,
 ) -> Result<()> {
     use std::io::Write;
     ensure!(append.is_none(), "append not implemented");
@@ -217,7 +219,7 @@ fn write_region(
         .create(true)
         .truncate(true)
         .open(filename)
-        .unwrap();
+        .map_err(|e| LispError::file_error("Opening output file", filename, &e, cx))?;
     let b = env.current_buffer.get();
     let (s1, s2) = b.slice_with_gap(start as usize, end as usize)?;
     write!(file, "{s1}")?;
@@ -7,10 +7,10 @@ use crate::{
         gc::{Context, Rt, Rto},
         object::{
             Function, Gc, HashTable, IntoObject, LispHashTable, LispString, LispVec, List,
-            ListType, Object, ObjectType, OptionalFlag, Symbol, WithLifetime, NIL,
+            ListType, Object, ObjectType, OptionalFlag, Symbol, NIL,
         },
     },
-    data::aref,
+    data::{aref, LispError},
     library::filevercmp::filevercmp,
     rooted_iter,
 };
@@ -485,17 +485,78 @@ fn sort<'ob>(
 
 #[defun]
 pub(crate) fn defvaralias<'ob>(
-    new_alias: Symbol<'ob>,
-    _base_variable: Symbol,
-    _docstring: Option<&str>,
-) -> Symbol<'ob> {
-    // TODO: implement
-    new_alias
+    new_alias: &Rto<Gc<Symbol>>,
+    base_variable: &Rto<Gc<Symbol>>,
+    docstring: Option<&Rto<Object>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Symbol<'ob>> {
+    let alias: Symbol = new_alias.untag(cx);
+    root!(alias, cx);
+    root!(buffer, NIL, cx);
+    let base = base_variable.cast();
+    crate::data::notify_variable_watchers(alias, base, sym::DEFVARALIAS, buffer, env, cx)?;
+    env.defvaralias(new_alias.untag(cx), base_variable.untag(cx), cx)?;
+    let docstring = docstring.map_or(NIL, |x| x.bind(cx));
+    env.set_prop(new_alias.untag(cx), sym::VARIABLE_DOCUMENTATION, docstring);
+    Ok(base_variable.untag(cx))
+}
+
+/// Whether `feature` is in `features`.
+fn has_feature(feature: Symbol, env: &Rt<Env>, cx: &Context) -> bool {
+    let features = env.var(sym::FEATURES).map_or(NIL, |x| x.bind(cx));
+    let Ok(features) = features.try_into() else { return false };
+    memq(feature.into(), features).is_ok_and(|x| !x.is_nil())
+}
+
+#[defun]
+pub(crate) fn featurep(
+    feature: Symbol,
+    subfeature: Option<Object>,
+    env: &Rt<Env>,
+    cx: &Context,
+) -> Result<bool> {
+    if !has_feature(feature, env, cx) {
+        return Ok(false);
+    }
+    match subfeature {
+        Some(subfeature) if !subfeature.is_nil() => {
+            let subfeatures = crate::data::get(feature, sym::SUBFEATURES, env, cx);
+            Ok(!member(subfeature, subfeatures.try_into()?)?.is_nil())
+        }
+        _ => Ok(true),
+    }
 }
 
 #[defun]
-// TODO: implement
-pub(crate) fn featurep(_feature: Symbol, _subfeature: Option<Symbol>) {}
+pub(crate) fn provide<'ob>(
+    feature: &Rto<Gc<Symbol>>,
+    subfeatures: Option<&Rto<Object>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Symbol<'ob>> {
+    let name = feature.untag(cx);
+    if !has_feature(name, env, cx) {
+        let features = env.var(sym::FEATURES).map_or(NIL, |x| x.bind(cx));
+        env.set_var(sym::FEATURES, Cons::new(name, features, cx).into(), cx)?;
+    }
+    if let Some(subfeatures) = subfeatures.map(|x| x.bind(cx)).filter(|x| !x.is_nil()) {
+        env.set_prop(name, sym::SUBFEATURES, subfeatures);
+    }
+    crate::lread::loadhist_attach(Cons::new(sym::PROVIDE, name, cx).into(), env, cx)?;
+    // run the `eval-after-load' forms that are waiting for this feature
+    let after_load = env.var(sym::AFTER_LOAD_ALIST).map_or(NIL, |x| x.bind(cx));
+    if let ObjectType::Cons(entry) = assq(name.into(), after_load.try_into()?)?.untag() {
+        if let ObjectType::Cons(funcs) = entry.cdr().untag() {
+            rooted_iter!(funcs, funcs, cx);
+            while let Some(func) = funcs.next()? {
+                let func: &Rto<Function> = func.try_as()?;
+                call!(func; env, cx)?;
+            }
+        }
+    }
+    Ok(feature.untag(cx))
+}
 
 #[defun]
 pub(crate) fn require<'ob>(
@@ -505,9 +566,16 @@ pub(crate) fn require<'ob>(
     env: &mut Rt<Env>,
     cx: &'ob mut Context,
 ) -> Result<Symbol<'ob>> {
-    // TODO: Fix this unsafe into_root
-    let feat = unsafe { feature.untag(cx).with_lifetime() };
-    if crate::data::FEATURES.lock().unwrap().contains(&feat) {
+    // Record the dependency of the file being loaded even if the feature is
+    // already present, but only once per file.
+    if env.var(sym::LOAD_FILE_NAME).is_some_and(|x| !x.bind(cx).is_nil()) {
+        let dependency = Cons::new(sym::REQUIRE, feature.untag(cx), cx).into();
+        let current = env.var(sym::CURRENT_LOAD_LIST).map_or(NIL, |x| x.bind(cx));
+        if member(dependency, current.try_into()?)?.is_nil() {
+            crate::lread::loadhist_attach(dependency, env, cx)?;
+        }
+    }
+    if has_feature(feature.untag(cx), env, cx) {
         return Ok(feature.untag(cx));
     }
     let file = match filename {
@@ -516,10 +584,85 @@ pub(crate) fn require<'ob>(
     };
     let file = file.into_obj(cx);
     root!(file, cx);
-    match crate::lread::load(file, noerror, None, cx, env) {
-        Ok(_) => Ok(feature.untag(cx)),
-        Err(e) => Err(e),
+    if !crate::lread::load(file, noerror, None, cx, env)? {
+        return Ok(sym::NIL);
+    }
+    let feature = feature.untag(cx);
+    if !has_feature(feature, env, cx) {
+        let message =
+            format!("Loading file {} failed to provide feature `{feature}'", file.bind(cx));
+        return Err(LispError::error(&message, cx).into());
     }
+    Ok(feature)
+}
+
+/// Undo the definitions that `load-history` recorded for the file that
+/// provided `feature`. Unless `force` is non-nil, this fails if another loaded
+/// file requires the feature.
+#[defun]
+pub(crate) fn unload_feature(
+    feature: Symbol,
+    force: OptionalFlag,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<()> {
+    if !has_feature(feature, env, cx) {
+        let message = format!("{feature} is not a currently loaded feature");
+        return Err(LispError::error(&message, cx).into());
+    }
+    let history = env.var(sym::LOAD_HISTORY).map_or(NIL, |x| x.bind(cx));
+    let history: List = history.try_into()?;
+    let provided: Object = Cons::new(sym::PROVIDE, feature, cx).into();
+    let required: Object = Cons::new(sym::REQUIRE, feature, cx).into();
+    let mut library = None;
+    let mut dependents = Vec::new();
+    for entry in history.elements() {
+        let entry: &Cons = entry?.try_into()?;
+        let definitions: List = entry.cdr().try_into()?;
+        if library.is_none() && !member(provided, definitions)?.is_nil() {
+            library = Some(entry);
+        } else if !member(required, definitions)?.is_nil() {
+            dependents.push(entry.car());
+        }
+    }
+    if force.is_none() && !dependents.is_empty() {
+        let dependents = slice_into_list(&dependents, None, cx);
+        let message = format!("Loaded libraries {dependents} depend on {feature}");
+        return Err(LispError::error(&message, cx).into());
+    }
+    let Some(library) = library else {
+        // provided outside of any file, so there is nothing else to undo
+        let features = env.var(sym::FEATURES).map_or(NIL, |x| x.bind(cx));
+        let features = delq(feature.into(), features.try_into()?)?;
+        return env.set_var(sym::FEATURES, features, cx);
+    };
+    for definition in library.cdr().as_list()? {
+        let definition = definition?;
+        if let ObjectType::Symbol(var) = definition.untag() {
+            env.unset_var(var, cx);
+            continue;
+        }
+        match definition.as_cons_pair() {
+            Ok((sym::PROVIDE, ObjectType::Symbol(name))) => {
+                let features = env.var(sym::FEATURES).map_or(NIL, |x| x.bind(cx));
+                let features = delq(name.into(), features.try_into()?)?;
+                env.set_var(sym::FEATURES, features, cx)?;
+            }
+            // restore the autoload that the definition replaced
+            Ok((sym::DEFUN | sym::AUTOLOAD, ObjectType::Symbol(name))) => {
+                let autoload = crate::data::get(name, sym::AUTOLOAD, env, cx);
+                if autoload.is_nil() {
+                    name.unbind_func();
+                } else {
+                    let autoload = Cons::new(sym::AUTOLOAD, autoload, cx);
+                    crate::data::fset(name, autoload.into())?;
+                }
+            }
+            _ => {}
+        }
+    }
+    let history = delq(library.into(), history)?;
+    env.set_var(sym::LOAD_HISTORY, history, cx)
 }
 
 #[defun]
@@ -835,6 +978,9 @@ pub(crate) fn clear_string(string: &LispString) -> Result<Object> {
 
 defsym!(KW_TEST);
 defsym!(KW_DOCUMENTATION);
+defsym!(VARIABLE_DOCUMENTATION);
+defsym!(SUBFEATURES);
+defvar!(FEATURES);
 
 #[defun]
 pub(crate) fn make_hash_table<'ob>(
@@ -956,20 +1102,16 @@ fn copy_sequence<'ob>(arg: Object<'ob>, cx: &'ob Context) -> Result<Object<'ob>>
 }
 
 #[defun]
-fn substring(string: &str, from: Option<usize>, to: Option<usize>) -> Result<String> {
-    if from.unwrap_or_default() > string.len() || to.unwrap_or_default() > string.len() {
+pub(crate) fn substring(string: &str, from: Option<i64>, to: Option<i64>) -> Result<String> {
+    // indices are in characters and negative values count from the end
+    let len = string.chars().count() as i64;
+    let index = |x: i64| if x < 0 { x + len } else { x };
+    let beg = index(from.unwrap_or(0));
+    let end = index(to.unwrap_or(len));
+    if beg < 0 || end > len || beg > end {
         bail!("substring args out of range for {string} : {from:?} {to:?}");
     }
-    let new_string = match (from, to) {
-        (None, None) => string,
-        (None, Some(t)) => &string[..t],
-        (Some(f), None) => &string[f..],
-        (Some(f), Some(t)) => {
-            let range = if f > t { t..f } else { f..t };
-            &string[range]
-        }
-    };
-    Ok(new_string.to_owned())
+    Ok(string.chars().skip(beg as usize).take((end - beg) as usize).collect())
 }
 
 defsym!(MD5);
@@ -1052,6 +1194,18 @@ mod test {
         // assert_lisp("(base64-encode-string \"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum\" t)", "\"TG9yZW0gaXBzdW0gZG9sb3Igc2l0IGFtZXQsIGNvbnNlY3RldHVyIGFkaXBpc2NpbmcgZWxpdCwg\nc2VkIGRvIGVpdXNtb2QgdGVtcG9yIGluY2lkaWR1bnQgdXQgbGFib3JlIGV0IGRvbG9yZSBtYWdu\nYSBhbGlxdWEuIFV0IGVuaW0gYWQgbWluaW0gdmVuaWFtLCBxdWlzIG5vc3RydWQgZXhlcmNpdGF0\naW9uIHVsbGFtY28gbGFib3JpcyBuaXNpIHV0IGFsaXF1aXAgZXggZWEgY29tbW9kbyBjb25zZXF1\nYXQuIER1aXMgYXV0ZSBpcnVyZSBkb2xvciBpbiByZXByZWhlbmRlcml0IGluIHZvbHVwdGF0ZSB2\nZWxpdCBlc3NlIGNpbGx1bSBkb2xvcmUgZXUgZnVnaWF0IG51bGxhIHBhcmlhdHVyLiBFeGNlcHRl\ndXIgc2ludCBvY2NhZWNhdCBjdXBpZGF0YXQgbm9uIHByb2lkZW50LCBzdW50IGluIGN1bHBhIHF1\naSBvZmZpY2lhIGRlc2VydW50IG1vbGxpdCBhbmltIGlkIGVzdCBsYWJvcnVt\"");
     }
 
+    #[test]
+    fn test_features() {
+        assert_lisp(
+            "(progn (provide 'fns-test '(sub)) (list (featurep 'fns-test) (featurep 'fns-test 'sub) (featurep 'fns-test 'other) (featurep 'fns-missing) (require 'fns-test)))",
+            "(t t nil nil fns-test)",
+        );
+        assert_lisp(
+            "(progn (setq after-load-alist (list (list 'fns-after #'(lambda () (setq fns-after-ran t))))) (provide 'fns-after) fns-after-ran)",
+            "t",
+        );
+    }
+
     #[test]
     fn test_take() {
         assert_lisp("(take 2 '(1 2 3 4))", "(1 2)");
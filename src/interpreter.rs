@@ -2,10 +2,13 @@
 use crate::{
     core::{
         cons::{Cons, ElemStreamIter, IntoArray},
-        env::{sym, CallFrame, Env},
+        env::{sym, CallFrame, ConditionHandler, Env},
         error::{Type, TypeError},
         gc::{Context, Rt, Rto, Slot},
-        object::{Function, Gc, List, ListType, Object, ObjectType, Symbol, TagType, NIL, TRUE},
+        object::{
+            FnArgs, Function, FunctionType, Gc, List, ListType, Object, ObjectType, SubrFn, Symbol,
+            TagType, NIL, TRUE,
+        },
     },
     data::LispError,
     eval::{add_trace, ErrorType, EvalError, EvalResult},
@@ -15,14 +18,90 @@ use anyhow::Context as _;
 use anyhow::Result as AnyResult;
 use anyhow::{bail, ensure};
 use fallible_streaming_iterator::FallibleStreamingIterator;
-use rune_core::macros::{bail_err, call, error, rebind, root};
+use rune_core::macros::{bail_err, call, rebind, root};
 use rune_macros::defun;
 
+/// A form that is evaluated by the interpreter instead of being called with
+/// evaluated arguments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SpecialForm {
+    Quote,
+    Function,
+    Let,
+    LetStar,
+    If,
+    And,
+    Or,
+    Cond,
+    While,
+    Progn,
+    Prog1,
+    Prog2,
+    Setq,
+    Defvar,
+    Defconst,
+    Interactive,
+    Catch,
+    ConditionCase,
+    SaveCurrentBuffer,
+    SaveExcursion,
+    UnwindProtect,
+}
+
+const fn special_form(name: &'static str, form: SpecialForm, required: u16) -> SubrFn {
+    SubrFn {
+        subr: |_, _, _| unreachable!("special forms can't be called"),
+        args: FnArgs { required, optional: 0, rest: true, advice: false },
+        name,
+        special_form: Some(form),
+    }
+}
+
+/// The special forms, which are installed in the function cells of their
+/// symbols by `init_symbols`. `inline` is the same as `progn`.
+pub(crate) const SPECIAL_FORMS: [(Symbol, &SubrFn); 22] = {
+    use SpecialForm as S;
+    const PROGN: SubrFn = special_form("progn", S::Progn, 0);
+    [
+        (sym::QUOTE, &special_form("quote", S::Quote, 1)),
+        (sym::FUNCTION, &special_form("function", S::Function, 1)),
+        (sym::LET, &special_form("let", S::Let, 1)),
+        (sym::LET_STAR, &special_form("let*", S::LetStar, 1)),
+        (sym::IF, &special_form("if", S::If, 2)),
+        (sym::AND, &special_form("and", S::And, 0)),
+        (sym::OR, &special_form("or", S::Or, 0)),
+        (sym::COND, &special_form("cond", S::Cond, 0)),
+        (sym::WHILE, &special_form("while", S::While, 1)),
+        (sym::PROGN, &PROGN),
+        (sym::INLINE, &PROGN),
+        (sym::PROG1, &special_form("prog1", S::Prog1, 1)),
+        (sym::PROG2, &special_form("prog2", S::Prog2, 2)),
+        (sym::SETQ, &special_form("setq", S::Setq, 0)),
+        (sym::DEFVAR, &special_form("defvar", S::Defvar, 1)),
+        (sym::DEFCONST, &special_form("defconst", S::Defconst, 2)),
+        (sym::INTERACTIVE, &special_form("interactive", S::Interactive, 0)),
+        (sym::CATCH, &special_form("catch", S::Catch, 1)),
+        (sym::CONDITION_CASE, &special_form("condition-case", S::ConditionCase, 2)),
+        (
+            sym::SAVE_CURRENT_BUFFER,
+            &special_form("save-current-buffer", S::SaveCurrentBuffer, 0),
+        ),
+        (sym::SAVE_EXCURSION, &special_form("save-excursion", S::SaveExcursion, 0)),
+        (sym::UNWIND_PROTECT, &special_form("unwind-protect", S::UnwindProtect, 1)),
+    ]
+};
+
 struct Interpreter<'brw, 'rt> {
     vars: &'brw mut Rt<Vec<Slot<&'rt Cons>>>,
     env: &'brw mut Rt<Env<'rt>>,
+    /// Whether variables are bound lexically. If false, every variable is
+    /// bound dynamically like in old elisp code.
+    lexical: bool,
 }
 
+/// Evaluate `form` with dynamic binding if `lexical` is nil. Otherwise it is
+/// either `t` or an alist of lexical bindings, where a bare symbol declares
+/// the variable special.
 #[defun]
 pub(crate) fn eval<'ob>(
     form: &Rto<Object>,
@@ -32,26 +111,55 @@ pub(crate) fn eval<'ob>(
 ) -> Result<Object<'ob>, anyhow::Error> {
     cx.garbage_collect(false);
     root!(vars, new(Vec<Slot<&Cons>>), cx);
-    if let Some(ObjectType::Cons(cons)) = lexical.map(|x| x.untag(cx)) {
-        for var in cons.elements() {
-            if let ObjectType::Cons(binding) = var?.untag() {
-                vars.push(binding);
-            }
+    let specials = env.local_specials.len();
+    let lexical = lexical.map_or(NIL, |x| x.bind(cx));
+    for var in lexical.as_list().into_iter().flatten() {
+        match var?.untag() {
+            ObjectType::Cons(binding) => vars.push(binding),
+            ObjectType::Symbol(sym) if sym != sym::TRUE => env.local_specials.push(sym),
+            _ => {}
         }
     }
-    let mut interpreter = Interpreter { vars, env };
-    interpreter.eval_form(form, cx).map_err(Into::into)
+    // the first binding in the alist has the highest priority
+    vars.reverse();
+    let mut interpreter = Interpreter { vars, env, lexical: !lexical.is_nil() };
+    let result = interpreter.eval_form(form, cx).map_err(Into::into);
+    interpreter.env.local_specials.truncate(specials);
+    result
+}
+
+/// Evaluate a top-level form, such as one read from a file. It uses lexical
+/// binding unless `lexical-binding` is nil, and unlike [eval] the variables
+/// declared special with `(defvar VAR)` stay special for the later forms.
+pub(crate) fn eval_toplevel<'ob>(
+    form: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>, anyhow::Error> {
+    cx.garbage_collect(false);
+    let lexical = env.var(sym::LEXICAL_BINDING).is_none_or(|x| !x.bind(cx).is_nil());
+    root!(vars, new(Vec<Slot<&Cons>>), cx);
+    Interpreter { vars, env, lexical }.eval_form(form, cx).map_err(Into::into)
 }
 
 impl Interpreter<'_, '_> {
     fn eval_form<'ob>(&mut self, rt: &Rto<Object>, cx: &'ob mut Context) -> EvalResult<'ob> {
-        match rt.untag(cx) {
+        let result = match rt.untag(cx) {
             ObjectType::Symbol(sym) => self.var_ref(sym, cx),
             ObjectType::Cons(_) => {
                 let x = rt.try_as().unwrap();
                 self.eval_sexp(x, cx)
             }
             _ => Ok(rt.bind(cx)),
+        };
+        let mut err = match result {
+            Ok(x) => return Ok(rebind!(x, cx)),
+            Err(e) => e,
+        };
+        match crate::eval::run_signal_handlers(&mut err, self.env, cx) {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Err(err),
+            Err(e) => Err(e),
         }
     }
 
@@ -63,35 +171,68 @@ impl Interpreter<'_, '_> {
         let cons = cons.bind(cx);
         let forms = cons.cdr();
         root!(forms, cx);
-        match cons.car().untag() {
-            ObjectType::Symbol(sym) => match sym {
-                sym::QUOTE => self.quote(forms.bind(cx), cx),
-                sym::LET => self.eval_let(forms, true, cx),
-                sym::LET_STAR => self.eval_let(forms, false, cx),
-                sym::IF => self.eval_if(forms, cx),
-                sym::AND => self.eval_and(forms, cx),
-                sym::OR => self.eval_or(forms, cx),
-                sym::COND => self.eval_cond(forms, cx),
-                sym::WHILE => self.eval_while(forms, cx),
-                sym::PROGN | sym::INLINE => self.eval_progn(forms, cx),
-                sym::PROG1 => self.eval_progx(forms, 1, cx),
-                sym::PROG2 => self.eval_progx(forms, 2, cx),
-                sym::SETQ => self.setq(forms, cx),
-                sym::DEFVAR | sym::DEFCONST => self.defvar(forms, cx),
-                sym::FUNCTION => self.eval_function(forms, cx),
-                sym::INTERACTIVE => Ok(NIL), // TODO: implement
-                sym::CATCH => self.catch(forms, cx),
-                sym::THROW => self.throw(forms.bind(cx), cx),
-                sym::CONDITION_CASE => self.condition_case(forms, cx),
-                sym::SAVE_CURRENT_BUFFER => self.save_current_buffer(forms, cx),
-                sym::SAVE_EXCURSION => self.save_excursion(forms, cx),
-                sym::UNWIND_PROTECT => self.unwind_protect(forms, cx),
-                _ => {
-                    root!(sym, cx);
-                    self.eval_call(sym, forms, cx)
-                }
-            },
-            other => Err(error!("Invalid Function: {other}")),
+        let ObjectType::Symbol(sym) = cons.car().untag() else {
+            bail_err!(LispError::invalid_function(cons.car(), cx))
+        };
+        if let Some(FunctionType::SubrFn(func)) = sym.follow_indirect(cx).map(|x| x.untag()) {
+            if let Some(form) = func.special_form {
+                crate::eval::check_eval_depth(self.env, cx)?;
+                // Special forms get an unevaluated frame so that they appear in
+                // the backtrace
+                let depth = self.env.binding_depth();
+                self.env.stack.push(&*forms);
+                self.env.stack.push_frame(1, depth);
+                self.env.stack.set_frame_function(sym.into(), false);
+                let mut err = match self.eval_special_form(form, forms, cx) {
+                    Ok(x) => {
+                        self.env.stack.pop_frame();
+                        return Ok(rebind!(x, cx));
+                    }
+                    Err(e) => e,
+                };
+                // enter the debugger before the frame is removed
+                let result = crate::eval::run_signal_handlers(&mut err, self.env, cx);
+                self.env.stack.pop_frame();
+                return match result {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => Err(err),
+                    Err(e) => Err(e),
+                };
+            }
+        }
+        root!(sym, cx);
+        self.eval_call(sym, forms, cx)
+    }
+
+    fn eval_special_form<'ob>(
+        &mut self,
+        form: SpecialForm,
+        forms: &Rto<Object>,
+        cx: &'ob mut Context,
+    ) -> EvalResult<'ob> {
+        use SpecialForm as S;
+        match form {
+            S::Quote => self.quote(forms.bind(cx), cx),
+            S::Let => self.eval_let(forms, true, cx),
+            S::LetStar => self.eval_let(forms, false, cx),
+            S::If => self.eval_if(forms, cx),
+            S::And => self.eval_and(forms, cx),
+            S::Or => self.eval_or(forms, cx),
+            S::Cond => self.eval_cond(forms, cx),
+            S::While => self.eval_while(forms, cx),
+            S::Progn => self.eval_progn(forms, cx),
+            S::Prog1 => self.eval_progx(forms, 1, cx),
+            S::Prog2 => self.eval_progx(forms, 2, cx),
+            S::Setq => self.setq(forms, cx),
+            S::Defvar | S::Defconst => self.defvar(forms, cx),
+            S::Function => self.eval_function(forms, cx),
+            // the spec is only read by `call-interactively'
+            S::Interactive => Ok(NIL),
+            S::Catch => self.catch(forms, cx),
+            S::ConditionCase => self.condition_case(forms, cx),
+            S::SaveCurrentBuffer => self.save_current_buffer(forms, cx),
+            S::SaveExcursion => self.save_excursion(forms, cx),
+            S::UnwindProtect => self.unwind_protect(forms, cx),
         }
     }
 
@@ -108,9 +249,10 @@ impl Interpreter<'_, '_> {
                 if let ErrorType::Throw(id) = e.error {
                     if let Some((throw_tag, data)) = self.env.get_exception(id) {
                         let catch_tag = self.env.catch_stack.last().unwrap();
-                        // TODO: Remove binds
                         if catch_tag == throw_tag {
-                            return Ok(data.bind(cx));
+                            let data = data.bind(cx);
+                            self.env.catch_stack.pop();
+                            return Ok(data);
                         }
                     }
                 }
@@ -122,21 +264,6 @@ impl Interpreter<'_, '_> {
         result
     }
 
-    fn throw<'ob>(&mut self, obj: Object, cx: &'ob Context) -> EvalResult<'ob> {
-        let [tag, value] = match obj.into_array()? {
-            Ok(x) => x,
-            Err(e) => bail_err!(LispError::arg_cnt(sym::THROW, 2, e, cx)),
-        };
-
-        // Need to check now that there is a catch, because we may have a
-        // condition-case along the unwind path
-        if self.env.catch_stack.iter().any(|x| x.bind(cx) == tag) {
-            Err(EvalError::throw(tag, value, self.env))
-        } else {
-            Err(error!("No catch for {tag}"))
-        }
-    }
-
     fn defvar<'ob>(&mut self, obj: &Rto<Object>, cx: &'ob mut Context) -> EvalResult<'ob> {
         rooted_iter!(forms, obj, cx);
         // (defvar x ...)                 // (defvar)
@@ -145,14 +272,23 @@ impl Interpreter<'_, '_> {
         };
         let name: Symbol = sym.bind(cx).try_into()?;
         root!(name, cx);
-        let value = match forms.next()? {
-            // (defvar x y)
-            Some(value) => rebind!(self.eval_form(value, cx)?),
-            // (defvar x)
-            None => NIL,
+        let Some(value) = forms.next()? else {
+            // (defvar x) only makes x special in the current lexical scope
+            if self.lexical && !self.is_special(name.bind(cx)) {
+                self.env.local_specials.push(name.bind(cx));
+            }
+            return Ok(name.bind(cx).into());
         };
-        self.env.defvar(name.bind(cx), value)?;
-        Ok(value)
+        // (defvar x y)
+        let value = rebind!(self.eval_form(value, cx)?);
+        self.env.defvar(name.bind(cx), value, cx)?;
+        crate::lread::loadhist_attach(name.bind(cx).into(), self.env, cx)?;
+        Ok(name.bind(cx).into())
+    }
+
+    /// Whether `var` is bound dynamically by `let`.
+    fn is_special(&self, var: Symbol) -> bool {
+        !self.lexical || var.is_special() || self.env.local_specials.iter().any(|x| *x == var)
     }
 
     fn eval_call<'ob>(
@@ -162,30 +298,31 @@ impl Interpreter<'_, '_> {
         cx: &'ob mut Context,
     ) -> EvalResult<'ob> {
         let Some(func) = sym.bind(cx).follow_indirect(cx) else {
-            bail_err!("Invalid function: {sym}")
+            bail_err!(LispError::void_function(sym.bind(cx), cx))
         };
         root!(func, cx);
 
-        match func.bind(cx).as_cons_pair() {
-            Ok((sym::AUTOLOAD, _)) => {
-                crate::eval::autoload_do_load(func.cast(), None, None, self.env, cx)
-                    .map_err(|e| add_trace(e, "autoload", &[]))?;
-                func.set(sym.bind(cx).follow_indirect(cx).unwrap());
-            }
-            Ok((sym::MACRO, mcro)) => {
-                let iter = args.bind(cx).into_list()?;
-                let mut frame = CallFrame::new(self.env);
-                for arg in iter {
-                    frame.push_arg(arg?);
-                }
-                root!(mcro, mcro.tag(), cx);
-                let name = sym.bind(cx).name().to_owned();
-                let value = mcro.call(&mut frame, Some(&name), cx)?;
-                drop(frame);
-                root!(value, cx);
-                return self.eval_form(value, cx);
+        if let Ok((sym::AUTOLOAD, _)) = func.bind(cx).as_cons_pair() {
+            let fundef = Object::from(func.bind(cx));
+            root!(fundef, cx);
+            root!(name, sym.bind(cx).tag(), cx);
+            let loaded = crate::eval::autoload_do_load(fundef, Some(name), None, self.env, cx)
+                .map_err(|e| add_trace(e, "autoload", &[]))?;
+            func.set(Function::try_from(loaded)?);
+        }
+        // an autoload may have defined a macro
+        if let Ok((sym::MACRO, mcro)) = func.bind(cx).as_cons_pair() {
+            let iter = args.bind(cx).into_list()?;
+            let mut frame = CallFrame::new(self.env);
+            for arg in iter {
+                frame.push_arg(arg?);
             }
-            _ => {}
+            root!(mcro, mcro.tag(), cx);
+            let name = sym.bind(cx).name().to_owned();
+            let value = mcro.call(&mut frame, Some(&name), cx)?;
+            drop(frame);
+            root!(value, cx);
+            return self.eval_form(value, cx);
         }
 
         rooted_iter!(iter, args, cx);
@@ -196,15 +333,12 @@ impl Interpreter<'_, '_> {
         }
         let frame = &mut CallFrame::new(self.env);
         frame.push_arg_slice(Rt::bind_slice(args, cx));
+        frame.stack.set_frame_function(sym.bind(cx).into(), true);
         let name = sym.bind(cx).name().to_owned();
         func.call(frame, Some(&name), cx)
     }
 
-    fn eval_function<'ob>(
-        &mut self,
-        obj: &Rto<Object<'ob>>,
-        cx: &'ob mut Context,
-    ) -> EvalResult<'ob> {
+    fn eval_function<'ob>(&mut self, obj: &Rto<Object>, cx: &'ob mut Context) -> EvalResult<'ob> {
         let form = match obj.bind(cx).into_array()? {
             Ok([x]) => x,
             Err(e) => bail_err!(LispError::arg_cnt(sym::FUNCTION, 1, e, cx)),
@@ -213,11 +347,18 @@ impl Interpreter<'_, '_> {
         let Ok((sym::LAMBDA, doc)) = form.bind(cx).as_cons_pair() else {
             return Ok(form.bind(cx));
         };
+        // with dynamic binding a lambda does not capture anything
+        if !self.lexical {
+            return Ok(form.bind(cx));
+        }
         root!(doc, doc.tag(), cx);
         let body = rebind!(self.replace_doc_symbol(doc, cx)?);
         let env = {
             let vars = self.vars.bind_ref(cx);
             let mut tail = Object::from(Cons::new1(true, cx));
+            for var in self.env.local_specials.iter() {
+                tail = Cons::new(var.bind(cx), tail, cx).into();
+            }
             for var in vars {
                 tail = Cons::new(**var, tail, cx).into();
             }
@@ -225,8 +366,7 @@ impl Interpreter<'_, '_> {
         };
         // This function will trim the environment down to only what is actually
         // captured in the closure
-        if let Some(closure_fn) = self.env.vars.get(sym::INTERNAL_MAKE_INTERPRETED_CLOSURE_FUNCTION)
-        {
+        if let Some(closure_fn) = self.env.var(sym::INTERNAL_MAKE_INTERPRETED_CLOSURE_FUNCTION) {
             if closure_fn.bind(cx) != sym::NIL {
                 let closure_fn: Result<&Rto<Function>, _> = closure_fn.try_as();
                 if let Ok(closure_fn) = closure_fn {
@@ -401,8 +541,8 @@ impl Interpreter<'_, '_> {
                     root!(var, cx);
                     root!(val, cx);
                     let val = rebind!(self.eval_form(val, cx)?);
-                    self.var_set(var.bind(cx), val, cx)?;
                     last_value.set(val);
+                    self.var_set(var, last_value, cx)?;
                 }
                 (_, Some(_)) => bail_err!(TypeError::new(Type::Symbol, var)),
                 (_, None) => bail_err!(LispError::arg_cnt(sym::SETQ, arg_cnt, arg_cnt + 1, cx)),
@@ -432,22 +572,28 @@ impl Interpreter<'_, '_> {
             let mut iter = self.vars.iter().rev();
             match iter.find_map(|cons| (cons.car(cx) == sym).then(|| cons.cdr(cx))) {
                 Some(value) => Ok(value),
-                None => match self.env.vars.get(sym) {
+                None => match self.env.var(sym) {
                     Some(v) => Ok(v.bind(cx)),
-                    None => Err(error!("Void variable: {sym}")),
+                    None => Err(LispError::void_variable(sym, cx).into()),
                 },
             }
         }
     }
 
-    fn var_set(&mut self, name: Symbol, new_value: Object, cx: &Context) -> AnyResult<()> {
+    fn var_set(
+        &mut self,
+        name: &Rto<Symbol>,
+        new_value: &Rto<Object>,
+        cx: &mut Context,
+    ) -> AnyResult<()> {
         let mut iter = self.vars.iter().rev();
-        match iter.find(|cons| (cons.car(cx) == name)) {
+        match iter.find(|cons| (cons.car(cx) == name.bind(cx))) {
             Some(value) => {
+                let new_value = new_value.bind(cx);
                 value.bind(cx).set_cdr(new_value).expect("variables should never be immutable");
                 Ok(())
             }
-            None => self.env.set_var(name, new_value),
+            None => crate::data::set_variable(name, new_value, self.env, cx),
         }
     }
 
@@ -466,6 +612,7 @@ impl Interpreter<'_, '_> {
     ) -> EvalResult<'ob> {
         rooted_iter!(iter, form, cx);
         let prev_len = self.vars.len();
+        let prev_specials = self.env.local_specials.len();
         // (let x ...)                   // (let)
         let Some(obj) = iter.next()? else { bail_err!(LispError::arg_cnt(sym::LET, 1, 0, cx)) };
         let varbind_count = if parallel {
@@ -473,11 +620,21 @@ impl Interpreter<'_, '_> {
         } else {
             self.let_bind_serial(obj, cx)
         }?;
-        let obj = rebind!(self.implicit_progn(iter, cx)?);
-        // Remove old bindings
+        let result = self.implicit_progn(iter, cx);
+        // Remove old bindings, even if we are exiting non-locally
         self.vars.truncate(prev_len);
-        self.env.unbind(varbind_count, cx);
-        Ok(obj)
+        self.env.local_specials.truncate(prev_specials);
+        match result {
+            Ok(x) => {
+                root!(x, cx);
+                crate::data::unbind(varbind_count, self.env, cx)?;
+                Ok(x.bind(cx))
+            }
+            Err(e) => {
+                crate::data::unbind(varbind_count, self.env, cx)?;
+                Err(e)
+            }
+        }
     }
 
     fn let_bind_serial(&mut self, form: &Rto<Object>, cx: &mut Context) -> Result<u16, EvalError> {
@@ -489,13 +646,17 @@ impl Interpreter<'_, '_> {
                 ObjectType::Cons(_) => {
                     let cons = binding.as_cons();
                     let val = rebind!(self.let_bind_value(cons, cx)?);
+                    root!(val, cx);
                     let var: Symbol =
                         cons.untag(cx).car().try_into().context("let variable must be a symbol")?;
-                    varbind_count += self.create_let_binding(var, val, cx);
+                    root!(var, cx);
+                    varbind_count += self.create_let_binding(var, val, cx)?;
                 }
                 // (let (x))
                 ObjectType::Symbol(sym) => {
-                    varbind_count += self.create_let_binding(sym, NIL, cx);
+                    root!(sym, cx);
+                    root!(val, NIL, cx);
+                    varbind_count += self.create_let_binding(sym, val, cx)?;
                 }
                 // (let (1))
                 x => bail_err!(TypeError::new(Type::Cons, x)),
@@ -530,20 +691,33 @@ impl Interpreter<'_, '_> {
             }
         }
         let mut sum = 0;
-        for (var, val) in let_bindings.bind_ref(cx) {
-            sum += self.create_let_binding(**var, **val, cx);
+        for binding in let_bindings.iter() {
+            let (var, val) = &**binding;
+            let binding = self.create_let_binding(var, val, cx);
+            match binding {
+                Ok(count) => sum += count,
+                Err(e) => {
+                    crate::data::unbind(sum, self.env, cx)?;
+                    return Err(e.into());
+                }
+            }
         }
         Ok(sum)
     }
 
-    fn create_let_binding(&mut self, var: Symbol, val: Object, cx: &Context) -> u16 {
-        if var.is_special() {
-            self.env.varbind(var, val, cx);
+    fn create_let_binding(
+        &mut self,
+        var: &Rto<Symbol>,
+        val: &Rto<Object>,
+        cx: &mut Context,
+    ) -> AnyResult<u16> {
+        if self.is_special(var.bind(cx)) {
+            crate::data::specbind(var, val, self.env, cx)?;
             // return 1 if the variable is bound
-            1
+            Ok(1)
         } else {
-            self.vars.push(Cons::new(var, val, cx));
-            0
+            self.vars.push(Cons::new(var.bind(cx), val.bind(cx), cx));
+            Ok(0)
         }
     }
 
@@ -598,14 +772,13 @@ impl Interpreter<'_, '_> {
     }
 
     fn save_excursion<'ob>(&mut self, form: &Rto<Object>, cx: &'ob mut Context) -> EvalResult<'ob> {
-        let point = self.env.current_buffer.get().text.cursor();
-        let buffer = self.env.current_buffer.get().lisp_buffer(cx);
-        root!(buffer, cx);
-        let result = rebind!(self.eval_progn(form, cx)?);
-        self.env.set_buffer(buffer.bind(cx));
-        let buf = self.env.current_buffer.get_mut();
-        buf.text.set_cursor(point.chars());
-        Ok(result)
+        self.env.record_excursion(cx);
+        let result = match self.eval_progn(form, cx) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e),
+        };
+        self.env.unbind(1, cx);
+        result
     }
 
     fn save_current_buffer<'ob>(
@@ -613,11 +786,13 @@ impl Interpreter<'_, '_> {
         form: &Rto<Object>,
         cx: &'ob mut Context,
     ) -> EvalResult<'ob> {
-        let buffer = self.env.current_buffer.get().lisp_buffer(cx);
-        root!(buffer, cx);
-        let result = rebind!(self.eval_progn(form, cx)?);
-        self.env.set_buffer(buffer.bind(cx));
-        Ok(result)
+        self.env.record_current_buffer(cx);
+        let result = match self.eval_progn(form, cx) {
+            Ok(x) => Ok(rebind!(x, cx)),
+            Err(e) => Err(e),
+        };
+        self.env.unbind(1, cx);
+        result
     }
 
     fn condition_case<'ob>(&mut self, form: &Rto<Object>, cx: &'ob mut Context) -> EvalResult<'ob> {
@@ -629,65 +804,52 @@ impl Interpreter<'_, '_> {
         let Some(bodyform) = forms.next()? else {
             bail_err!(LispError::arg_cnt(sym::CONDITION_CASE, 2, 1, cx))
         };
-        let err = match self.eval_form(bodyform, cx) {
-            Ok(x) => return Ok(rebind!(x, cx)),
+        // make the handlers visible to the debugger while the body runs
+        let depth = self.env.condition_stack.len();
+        for clause in form.bind(cx).as_list()?.skip(2) {
+            if let ObjectType::Cons(cons) = clause?.untag() {
+                if cons.car() != sym::KW_SUCCESS {
+                    let spec = ConditionHandler::Case(Slot::new(cons.car()));
+                    self.env.condition_stack.push(spec);
+                }
+            }
+        }
+        let result = self.eval_form(bodyform, cx);
+        self.env.condition_stack.truncate(depth);
+        let err = match result {
+            Ok(value) => {
+                root!(value, cx);
+                while let Some(handler) = forms.next()? {
+                    if let ObjectType::Cons(cons) = handler.untag(cx) {
+                        if cons.car() == sym::KW_SUCCESS {
+                            return self.condition_case_clause(var, value, handler, cx);
+                        }
+                    }
+                }
+                return Ok(value.bind(cx));
+            }
             Err(e) => e,
         };
-        if matches!(err.error, ErrorType::Throw(_)) {
+        let Some(condition) = err.condition(self.env, cx) else { return Err(err) };
+        if !err.is_visible_to(depth) {
             return Err(err);
         }
+        let condition: Object = condition.into();
+        root!(condition, cx);
         while let Some(handler) = forms.next()? {
             match handler.untag(cx) {
                 ObjectType::Cons(cons) => {
-                    // Check that conditions match
-                    let condition = cons.car();
-                    match condition.untag() {
-                        ObjectType::Symbol(sym::ERROR | sym::VOID_VARIABLE) => {}
-                        // TODO: Remove this once error handling is correctly implemented
-                        ObjectType::Symbol(s) if s.name() == "cl--generic-cyclic-definition" => {}
-                        ObjectType::Cons(conditions) => {
-                            for condition in conditions {
-                                let condition = condition?;
-                                // TODO: Handle different error symbols
-                                if condition != sym::DEBUG && condition != sym::ERROR {
-                                    bail_err!("non-error conditions {condition} not yet supported")
-                                }
-                            }
-                        }
-                        _ => bail_err!("Invalid condition handler: {condition}"),
+                    let spec = cons.car();
+                    if spec != sym::KW_SUCCESS
+                        && crate::eval::handles_condition(
+                            spec,
+                            condition.as_cons().untag(cx),
+                            self.env,
+                            cx,
+                        )
+                    {
+                        return self.condition_case_clause(var, condition, handler, cx);
                     }
-
-                    // Call handlers with error
-                    let error = match err.error {
-                        ErrorType::Signal(id) => {
-                            let Some((sym, data)) = self.env.get_exception(id) else {
-                                unreachable!("Exception not found")
-                            };
-                            Cons::new(sym, data, cx)
-                        }
-                        ErrorType::Err(err) => {
-                            let err_str = format!("{err}");
-                            if let Ok(lisp_error) = err.downcast::<LispError>() {
-                                lisp_error.bind(cx)
-                            } else {
-                                // TODO: Need to remove the anyhow branch once
-                                // full errors are implemented
-                                Cons::new(sym::ERROR, err_str, cx)
-                            }
-                        }
-                        _ => unreachable!("Error type throw was not handled"),
-                    };
-
-                    let binding = Cons::new(var, error, cx);
-                    self.vars.push(binding);
-                    let list: List = match cons.cdr().try_into() {
-                        Ok(x) => x,
-                        Err(_) => return Ok(NIL),
-                    };
-                    rooted_iter!(handlers, list, cx);
-                    let result = self.implicit_progn(handlers, cx)?;
-                    self.vars.pop();
-                    return Ok(result);
                 }
                 ObjectType::NIL => {}
                 invalid => bail_err!("Invalid condition handler: {invalid}"),
@@ -695,6 +857,35 @@ impl Interpreter<'_, '_> {
         }
         Err(err)
     }
+
+    /// Evaluate the body of the condition-case `clause` with `var` bound to
+    /// `value`.
+    fn condition_case_clause<'ob>(
+        &mut self,
+        var: &Rto<Object>,
+        value: &Rto<Object>,
+        clause: &Rto<Object>,
+        cx: &'ob mut Context,
+    ) -> EvalResult<'ob> {
+        let var = var.bind(cx);
+        let bound = !var.is_nil();
+        if bound {
+            self.vars.push(Cons::new(var, value.bind(cx), cx));
+        }
+        let body = clause.as_cons().untag(cx).cdr();
+        let result = match body.try_into() {
+            Ok(list) => {
+                let list: List = list;
+                rooted_iter!(forms, list, cx);
+                self.implicit_progn(forms, cx)
+            }
+            Err(_) => Ok(NIL),
+        };
+        if bound {
+            self.vars.pop();
+        }
+        result
+    }
 }
 
 pub(crate) fn call_closure<'ob>(
@@ -706,55 +897,127 @@ pub(crate) fn call_closure<'ob>(
 ) -> EvalResult<'ob> {
     cx.garbage_collect(false);
     let closure: &Cons = closure.untag(cx);
-    match closure.car().untag() {
-        ObjectType::Symbol(sym::CLOSURE) => {
-            rooted_iter!(forms, closure.cdr(), cx);
-            let args = Rt::bind_slice(&env.stack[..arg_cnt], cx);
-            let vars = bind_variables(&mut forms, args, name, cx)?;
-            debug!("call vars: {vars:?}");
-            root!(vars, cx);
-            Interpreter { vars, env }.implicit_progn(forms, cx)
+    let is_closure = match closure.car().untag() {
+        ObjectType::Symbol(sym::CLOSURE) => true,
+        // a plain lambda is called with dynamic binding
+        ObjectType::Symbol(sym::LAMBDA) => false,
+        other => return Err(TypeError::new(Type::Func, other).into()),
+    };
+    rooted_iter!(forms, closure.cdr(), cx);
+    let args = Rt::bind_slice(&env.stack[..arg_cnt], cx);
+    let CallBindings { lexical, vars, dynamic, specials } =
+        bind_variables(&mut forms, args, is_closure, name, cx)?;
+    debug!("call vars: {vars:?}");
+    root!(vars, cx);
+    root!(dynamic, cx);
+    let prev_specials = env.local_specials.len();
+    for var in specials {
+        // closures created in this scope capture the same declarations
+        if !env.local_specials.iter().any(|x| *x == var) {
+            env.local_specials.push(var);
+        }
+    }
+    let mut varbind_count = 0;
+    let mut result = Ok(NIL);
+    for binding in dynamic.iter() {
+        let binding: &Cons = binding.bind(cx);
+        let var: Symbol = binding.car().try_into()?;
+        let value = binding.cdr();
+        root!(var, cx);
+        root!(value, cx);
+        if let Err(e) = crate::data::specbind(var, value, env, cx) {
+            result = Err(e.into());
+            break;
+        }
+        varbind_count += 1;
+    }
+    if result.is_ok() {
+        result = Interpreter { vars, env, lexical }.implicit_progn(forms, cx);
+    }
+    env.local_specials.truncate(prev_specials);
+    match result {
+        Ok(x) => {
+            root!(x, cx);
+            crate::data::unbind(varbind_count, env, cx)?;
+            Ok(x.bind(cx))
+        }
+        Err(e) => {
+            crate::data::unbind(varbind_count, env, cx)?;
+            Err(e)
         }
-        other => Err(TypeError::new(Type::Func, other).into()),
     }
 }
 
+/// The variables bound by a call to an interpreted function.
+struct CallBindings<'a> {
+    /// Whether the body is evaluated with lexical binding
+    lexical: bool,
+    /// The lexical bindings, including the ones captured by the closure
+    vars: Vec<&'a Cons>,
+    /// The arguments that are bound dynamically
+    dynamic: Vec<&'a Cons>,
+    /// The variables declared special in the closure environment
+    specials: Vec<Symbol<'a>>,
+}
+
 fn bind_variables<'a>(
     forms: &mut ElemStreamIter<'_>,
     args: &[Object<'a>],
+    is_closure: bool,
     name: &str,
     cx: &'a Context,
-) -> AnyResult<Vec<&'a Cons>> {
-    // Add closure environment to variables
-    // (closure ((x . 1) (y . 2) t) ...)
-    //          ^^^^^^^^^^^^^^^^^^^
-    let Some(env) = forms.next()? else { bail!("Closure missing environment") };
-    let mut vars = parse_closure_env(env.bind(cx))?;
+) -> AnyResult<CallBindings<'a>> {
+    let mut bindings = CallBindings {
+        lexical: false,
+        vars: Vec::new(),
+        dynamic: Vec::new(),
+        specials: Vec::new(),
+    };
+    if is_closure {
+        // Add closure environment to variables
+        // (closure ((x . 1) (y . 2) t) ...)
+        //          ^^^^^^^^^^^^^^^^^^^
+        let Some(env) = forms.next()? else { bail!("Closure missing environment") };
+        // A closure with an empty environment uses dynamic binding
+        bindings.lexical = !env.bind(cx).is_nil();
+        parse_closure_env(env.bind(cx), &mut bindings)?;
+    }
 
     // Add function arguments to variables
     // (closure (t) (x y &rest z) ...)
     //              ^^^^^^^^^^^^^
     let Some(arg_list) = forms.next()? else { bail!("Closure missing argument list") };
-    bind_args(arg_list.bind(cx), args, &mut vars, name, cx)?;
-    Ok(vars)
+    let mut arg_bindings = Vec::new();
+    bind_args(arg_list.bind(cx), args, &mut arg_bindings, name, cx)?;
+    for binding in arg_bindings {
+        let special = matches!(binding.car().untag(), ObjectType::Symbol(x) if x.is_special());
+        if bindings.lexical && !special {
+            bindings.vars.push(binding);
+        } else {
+            bindings.dynamic.push(binding);
+        }
+    }
+    Ok(bindings)
 }
 
-fn parse_closure_env(obj: Object) -> AnyResult<Vec<&Cons>> {
+fn parse_closure_env<'a>(obj: Object<'a>, bindings: &mut CallBindings<'a>) -> AnyResult<()> {
     let forms = obj.as_list()?;
-    let mut env = Vec::new();
+    let start = bindings.vars.len();
     for form in forms {
         match form?.untag() {
             ObjectType::Cons(pair) => {
-                env.push(pair);
+                bindings.vars.push(pair);
             }
             ObjectType::TRUE => break,
+            // (closure (x t) ...) means that x is special
+            ObjectType::Symbol(sym) => bindings.specials.push(sym),
             x => bail!("Invalid closure environment member: {x}"),
         }
     }
     // The highest priority bindings are at the start of the closure list, but
     // the end of the enviroment vector
-    env.reverse();
-    Ok(env)
+    bindings.vars[start..].reverse();
+    Ok(())
 }
 
 fn bind_args<'a>(
@@ -833,11 +1096,12 @@ pub(crate) fn assert_lisp(compare: &str, expect: &str) {
     let cx = &mut Context::new(roots);
     sym::init_symbols();
     root!(env, new(Env), cx);
+    crate::data::init_errors(env, cx);
     println!("Test String: {compare}");
     let compare = {
         let obj = crate::reader::read(compare, cx).unwrap().0;
         root!(obj, cx);
-        rebind!(eval(obj, None, env, cx).unwrap())
+        rebind!(eval_toplevel(obj, env, cx).unwrap())
     };
     let expect = crate::reader::read(expect, cx).unwrap().0;
     assert_eq!(compare, expect);
@@ -856,10 +1120,11 @@ mod test {
     {
         sym::init_symbols();
         root!(env, new(Env), cx);
+        crate::data::init_errors(env, cx);
         println!("Test String: {test_str}");
         let obj = crate::reader::read(test_str, cx).unwrap().0;
         root!(obj, cx);
-        let compare = rebind!(eval(obj, None, env, cx).unwrap());
+        let compare = rebind!(eval_toplevel(obj, env, cx).unwrap());
         let expect: Object = expect.into_obj(cx).as_obj_copy();
         assert_eq!(compare, expect);
     }
@@ -869,7 +1134,7 @@ mod test {
         println!("Test String: {test_str}");
         let obj = crate::reader::read(test_str, cx).unwrap().0;
         root!(obj, cx);
-        assert!(eval(obj, None, env, cx).is_err());
+        assert!(eval_toplevel(obj, env, cx).is_err());
     }
 
     #[test]
@@ -902,6 +1167,32 @@ mod test {
         check_interpreter("(let* ((x 1) (y x)) y)", 1, cx);
     }
 
+    #[test]
+    fn dynamic_binding() {
+        assert_lisp("(eval '(let ((x 1)) (boundp 'x)) nil)", "t");
+        assert_lisp("(eval '(let ((x 1)) (boundp 'x)) t)", "nil");
+        assert_lisp("(eval '(+ x y) '((x . 1) (y . 2) (x . 3)))", "3");
+        assert_lisp(
+            "(eval '(let ((f (let ((x 1)) #'(lambda () (boundp 'x))))) (funcall f)) nil)",
+            "nil",
+        );
+        assert_lisp("(eval '(funcall #'(lambda (x) (symbol-value 'x)) 2) nil)", "2");
+        assert_lisp("(eval '(funcall '(closure nil (x) (symbol-value 'x)) 3) t)", "3");
+        // (defvar x) makes x special only in the current scope
+        assert_lisp(
+            "(progn (defvar dyn_local1) (list (let ((dyn_local1 1)) (boundp 'dyn_local1)) (special-variable-p 'dyn_local1) (boundp 'dyn_local1)))",
+            "(t nil nil)",
+        );
+        assert_lisp(
+            "(progn (let () (defvar dyn_local2)) (let ((dyn_local2 1)) (boundp 'dyn_local2)))",
+            "nil",
+        );
+        assert_lisp(
+            "(progn (setq dyn_local3 1) (defvar dyn_local3 2) (list (special-variable-p 'dyn_local3) dyn_local3))",
+            "(t 1)",
+        );
+    }
+
     #[test]
     fn dyn_variables() {
         let roots = &RootSet::default();
@@ -1105,6 +1396,256 @@ mod test {
         check_error("(condition-case nil (if) 5 (error 7))", cx);
     }
 
+    #[test]
+    fn test_condition_dispatch() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        check_interpreter("(condition-case nil (signal 'foo '(1)) (t 7))", 7, cx);
+        check_interpreter(
+            "(progn (put 'test-err 'error-conditions '(test-err error))
+               (condition-case e (signal 'test-err '(5)) ((arith-error test-err) (car (cdr e)))))",
+            5,
+            cx,
+        );
+        check_interpreter(
+            "(progn (put 'test-err 'error-conditions '(test-err error))
+               (condition-case nil
+                   (condition-case nil (signal 'test-err nil) (arith-error 1))
+                 (error 2)))",
+            2,
+            cx,
+        );
+        // debug alone is not a condition
+        check_interpreter(
+            "(condition-case nil (condition-case nil (if) (debug 1)) (error 2))",
+            2,
+            cx,
+        );
+        check_interpreter("(condition-case x (+ 1 2) (error 0) (:success (* x 2)))", 6, cx);
+        check_interpreter("(condition-case nil (if) (:success 1) (error 2))", 2, cx);
+        check_interpreter("(catch 1 (condition-case nil (throw 1 2) (t 3)))", 2, cx);
+        check_interpreter("(condition-case nil int-test-unbound (void-variable 1))", 1, cx);
+    }
+
+    #[test]
+    fn test_debugger() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        let setup = "(setq debug-on-error t debugger #'(lambda (_ c) (throw 'debugger-return 4)))";
+        let check = |form: &str, expect, cx: &mut Context| {
+            check_interpreter(&format!("(progn {setup} {form})"), expect, cx);
+        };
+        // the debugger can return a value in place of the error
+        check("(+ 1 (car 5))", 5, cx);
+        check("(+ 1 test-void-var)", 5, cx);
+        // not entered for errors that are handled
+        check("(condition-case nil (+ 1 (car 5)) (error 0))", 0, cx);
+        check("(condition-case nil (+ 1 (car 5)) ((debug error) 0))", 5, cx);
+        check(
+            "(let ((debug-on-signal t)) (condition-case nil (+ 1 (car 5)) (error 0)))",
+            5,
+            cx,
+        );
+        check(
+            "(let ((debug-on-error nil)) (condition-case nil (+ 1 (car 5)) (error 0)))",
+            0,
+            cx,
+        );
+        check(
+            "(let ((debug-on-error '(arith-error))) (condition-case nil (car 5) (t 0)))",
+            0,
+            cx,
+        );
+        // returning normally lets the error continue
+        check_interpreter(
+            "(progn (setq debug-on-signal t debugger #'(lambda (_ c) (setq test-entered (car c))))
+               (condition-case nil (car 5) (error test-entered)))",
+            sym::WRONG_TYPE_ARGUMENT,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // special forms have unevaluated frames and rest args are a list
+        check_interpreter(
+            "(progn (defalias 'test-bt-f #'(lambda (a &rest b) (if t (test-bt-g a))))
+               (defalias 'test-bt-g #'(lambda (a)
+                 (list (backtrace-frame--internal #'list 0 'test-bt-g)
+                       (backtrace-frame--internal #'list 1 'test-bt-g)
+                       (backtrace-frame--internal #'list 2 'test-bt-g))))
+               (equal (test-bt-f 1 2 3)
+                      '((t test-bt-g (1) nil) (nil if (t (test-bt-g a)) nil) (t test-bt-f (1 2 3) nil))))",
+            true,
+            cx,
+        );
+        check_interpreter(
+            "(let ((n 0)) (mapbacktrace #'(lambda (&rest _) (setq n (1+ n))) 'test-bt-none) n)",
+            0,
+            cx,
+        );
+        // evaluated in the dynamic environment of the frame
+        check_interpreter(
+            "(progn (defvar test-bt-var 1)
+               (defalias 'test-bt-h #'(lambda () (list (backtrace-eval 'test-bt-var 1 'test-bt-h) test-bt-var)))
+               (let ((test-bt-var 2)) (let ((test-bt-var 3)) (equal (test-bt-h) '(2 3)))))",
+            true,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_handler_bind() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        // handlers run before the dynamic bindings are unwound
+        check_interpreter(
+            "(progn (defvar test-hb-var 1)
+               (condition-case nil
+                   (handler-bind-1 #'(lambda () (let ((test-hb-var 2)) (car 1)))
+                                   '(error) #'(lambda (_) (setq test-hb-seen test-hb-var)))
+                 (error test-hb-seen)))",
+            2,
+            cx,
+        );
+        // a handler that returns normally lets the error continue
+        check_interpreter(
+            "(condition-case nil
+                 (handler-bind-1 #'(lambda () (car 1)) '(error) #'(lambda (_) 1))
+               (error 2))",
+            2,
+            cx,
+        );
+        // not run when an inner condition-case handles the error
+        check_interpreter(
+            "(handler-bind-1 #'(lambda () (condition-case nil (car 1) (error 3)))
+                             '(error) #'(lambda (_) (throw 10 4)))",
+            3,
+            cx,
+        );
+        // handlers can exit non-locally
+        check_interpreter(
+            "(catch 10 (handler-bind-1 #'(lambda () (car 1))
+                                            '(error) #'(lambda (_) (throw 10 5))))",
+            5,
+            cx,
+        );
+        // errors signaled by a handler skip the handlers it was muting
+        check_interpreter(
+            "(progn (put 'test-hb-a 'error-conditions '(test-hb-a))
+               (put 'test-hb-b 'error-conditions '(test-hb-b))
+               (condition-case nil
+                   (handler-bind-1
+                    #'(lambda () (condition-case nil (signal 'test-hb-a nil) (test-hb-b 'inner)))
+                    '(test-hb-a) #'(lambda (_) (signal 'test-hb-b nil)))
+                 (test-hb-b 6)))",
+            6,
+            cx,
+        );
+        check_interpreter(
+            "(condition-case nil
+                 (handler-bind-1 #'(lambda () (car 1)) '(error) #'(lambda (_) (car 2)))
+               (error 7))",
+            7,
+            cx,
+        );
+        // the first handler is tried first
+        check_interpreter(
+            "(catch 10 (handler-bind-1 #'(lambda () (signal 'arith-error nil))
+                                            '(arith-error) #'(lambda (_) (throw 10 8))
+                                            '(error) #'(lambda (_) (throw 10 9))))",
+            8,
+            cx,
+        );
+    }
+
+    #[test]
+    fn test_error_conditions() {
+        assert_lisp(
+            "(condition-case err (car 1) (wrong-type-argument err))",
+            "(wrong-type-argument listp 1)",
+        );
+        assert_lisp(
+            "(condition-case err (aref [1] 3) (args-out-of-range err))",
+            "(args-out-of-range [1] 3)",
+        );
+        assert_lisp(
+            "(condition-case err (test-no-such-fn) (void-function err))",
+            "(void-function test-no-such-fn)",
+        );
+        assert_lisp(
+            "(condition-case err test-no-such-var (void-variable err))",
+            "(void-variable test-no-such-var)",
+        );
+        assert_lisp(
+            "(condition-case err (setq t 1) (setting-constant err))",
+            "(setting-constant t)",
+        );
+        assert_lisp("(condition-case err (throw 10 1) (no-catch err))", "(no-catch 10 1)");
+        assert_lisp("(condition-case nil (read-from-string \"(1\") (end-of-file 'eof))", "eof");
+        assert_lisp(
+            "(condition-case err (read-from-string \")\") (invalid-read-syntax err))",
+            "(invalid-read-syntax \")\")",
+        );
+        // conditions are inherited from the parent errors
+        assert_lisp("(condition-case nil (signal 'overflow-error nil) (arith-error 1))", "1");
+        assert_lisp("(condition-case nil (signal 'file-missing nil) (file-error 1))", "1");
+        assert_lisp("(condition-case nil (signal 'quit nil) (error 1) (quit 2))", "2");
+        // symbols that are not errors only match `t'
+        assert_lisp("(condition-case nil (condition-case nil (signal 'test-no-such-error nil) (error 1)) (t 2))", "2");
+    }
+
+    #[test]
+    fn test_max_lisp_eval_depth() {
+        // this runs on a test thread, which has a smaller stack than the main
+        // thread, so that the native stack guard is tested as well
+        assert_lisp(
+            "(progn (defalias 'test-recurse #'(lambda (n) (if (= n 0) 0 (1+ (test-recurse (1- n))))))
+               (test-recurse 30))",
+            "30",
+        );
+        assert_lisp(
+            "(progn (setq max-lisp-eval-depth 200)
+               (defalias 'test-recurse #'(lambda (n) (test-recurse (1+ n))))
+               (condition-case err (test-recurse 0) (excessive-lisp-nesting (car err))))",
+            "excessive-lisp-nesting",
+        );
+        // handlers get some room to run after the limit is exceeded
+        assert_lisp(
+            "(progn (setq max-lisp-eval-depth 100)
+               (defalias 'test-recurse #'(lambda (n) (test-recurse (1+ n))))
+               (defalias 'test-nest #'(lambda (n) (if (= n 0) 'room (test-nest (1- n)))))
+               (list (condition-case err
+                         (handler-bind-1 #'(lambda () (test-recurse 0))
+                                         '(excessive-lisp-nesting)
+                                         #'(lambda (_) (signal 'arith-error (list (test-nest 20)))))
+                       (arith-error (car (cdr err))))
+                     max-lisp-eval-depth))",
+            "(room 100)",
+        );
+        // a runaway recursion signals an error instead of overflowing the stack
+        assert_lisp(
+            "(progn (setq max-lisp-eval-depth 1000000)
+               (defalias 'test-recurse #'(lambda (n) (test-recurse (1+ n))))
+               (condition-case nil (test-recurse 0) (recursion-error 'deep)))",
+            "deep",
+        );
+        // and its handlers can use the stack that is held back for them
+        assert_lisp(
+            "(progn (setq max-lisp-eval-depth 1000000)
+               (defalias 'test-recurse #'(lambda (n) (test-recurse (1+ n))))
+               (defalias 'test-nest #'(lambda (n) (if (= n 0) 'room (test-nest (1- n)))))
+               (condition-case err
+                   (handler-bind-1 #'(lambda () (test-recurse 0))
+                                   '(excessive-lisp-nesting)
+                                   #'(lambda (_) (signal 'arith-error (list (test-nest 40)))))
+                 (arith-error (car (cdr err)))))",
+            "room",
+        );
+    }
+
     #[test]
     fn test_throw_catch() {
         let roots = &RootSet::default();
@@ -1,21 +1,111 @@
 //! Keymap handling.
-use crate::core::object::Object;
+use crate::core::{
+    env::{sym, Env},
+    gc::{Context, Rt, Rto},
+    object::{FunctionType, Gc, Object, ObjectType, Symbol, TagType, NIL},
+};
+use anyhow::Result;
+use rune_core::macros::{list, root};
 use rune_macros::defun;
 
+/// The keymap that `object` stands for, or nil if it is not one. A symbol
+/// stands for the keymap in its function cell. If that is an autoload of type
+/// `keymap`, its file is loaded when `autoload` is set, and the symbol itself
+/// is returned otherwise.
+pub(crate) fn get_keymap<'ob>(
+    object: &Rto<Object>,
+    autoload: bool,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    if autoload {
+        if let Some((fundef, name)) = keymap_autoload(object.bind(cx), cx)? {
+            root!(fundef, cx);
+            root!(name, cx);
+            crate::eval::autoload_do_load(fundef, Some(name), None, env, cx)?;
+        }
+    }
+    let obj = object.bind(cx);
+    let ObjectType::Symbol(symbol) = obj.untag() else {
+        return Ok(if is_keymap(obj) { obj } else { NIL });
+    };
+    match symbol.follow_indirect(cx) {
+        Some(func) if is_keymap(func.into()) => Ok(func.into()),
+        Some(_) if keymap_autoload(obj, cx)?.is_some() => Ok(obj),
+        _ => Ok(NIL),
+    }
+}
+
+/// The autoload of type `keymap` in the function cell of `obj`, along with the
+/// symbol, if `obj` is a symbol that has one.
+fn keymap_autoload<'ob>(
+    obj: Object<'ob>,
+    cx: &'ob Context,
+) -> Result<Option<(Object<'ob>, Gc<Symbol<'ob>>)>> {
+    let ObjectType::Symbol(symbol) = obj.untag() else { return Ok(None) };
+    let Some(func) = symbol.follow_indirect(cx) else { return Ok(None) };
+    match func.untag() {
+        // (autoload FILE DOCSTRING INTERACTIVE TYPE)
+        FunctionType::Cons(cons)
+            if cons.car() == sym::AUTOLOAD && crate::fns::nth(4, cons.into())? == sym::KEYMAP =>
+        {
+            Ok(Some((cons.into(), symbol.tag())))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn is_keymap(obj: Object) -> bool {
+    matches!(obj.untag(), ObjectType::Cons(cons) if cons.car() == sym::KEYMAP)
+}
+
+#[defun]
+fn keymapp(object: &Rto<Object>, env: &mut Rt<Env>, cx: &mut Context) -> Result<bool> {
+    Ok(!get_keymap(object, false, env, cx)?.is_nil())
+}
+
 // TODO: implement keymaps
 #[defun]
-fn make_keymap(_string: Option<&str>) {}
+fn make_keymap<'ob>(string: Option<Object<'ob>>, cx: &'ob Context) -> Object<'ob> {
+    make_sparse_keymap(string, cx)
+}
 
 #[defun]
-fn make_sparse_keymap(_string: Option<&str>) {}
+fn make_sparse_keymap<'ob>(string: Option<Object<'ob>>, cx: &'ob Context) -> Object<'ob> {
+    match string {
+        Some(prompt) if !prompt.is_nil() => list![sym::KEYMAP, prompt; cx],
+        _ => list![sym::KEYMAP; cx],
+    }
+}
 
 #[defun]
-fn use_global_map(_keymap: Object) {}
+fn use_global_map(keymap: &Rto<Object>, env: &mut Rt<Env>, cx: &mut Context) -> Result<()> {
+    get_keymap(keymap, true, env, cx)?;
+    Ok(())
+}
 
 #[defun]
-fn set_keymap_parent<'ob>(_keymap: Object<'ob>, _parent: Object<'ob>) {}
+fn set_keymap_parent<'ob>(
+    keymap: &Rto<Object<'ob>>,
+    parent: &Rto<Object<'ob>>,
+    env: &mut Rt<Env>,
+    cx: &'ob mut Context,
+) -> Result<Object<'ob>> {
+    get_keymap(keymap, true, env, cx)?;
+    get_keymap(parent, true, env, cx)?;
+    Ok(parent.bind(cx))
+}
 
 #[defun]
-pub(crate) fn define_key<'ob>(_keymap: Object<'ob>, _key: Object<'ob>, _def: Object<'ob>) {}
+pub(crate) fn define_key(
+    keymap: &Rto<Object>,
+    _key: &Rto<Object>,
+    _def: &Rto<Object>,
+    env: &mut Rt<Env>,
+    cx: &mut Context,
+) -> Result<()> {
+    get_keymap(keymap, true, env, cx)?;
+    Ok(())
+}
 
 defvar!(MINIBUFFER_LOCAL_MAP);
@@ -1,4 +1,7 @@
 //! Loading elisp from files and strings.
+pub(crate) mod cache;
+pub(crate) mod loaddefs;
+
 use crate::core::cons::Cons;
 use crate::core::env::{sym, Env};
 use crate::core::error::{Type, TypeError};
@@ -7,15 +10,17 @@ use crate::core::object::{
     Function, Gc, LispString, Object, ObjectType, OptionalFlag, Symbol, TagType, WithLifetime, NIL,
     TRUE,
 };
+use crate::data::LispError;
 use crate::reader;
 use crate::{interpreter, rooted_iter};
-use anyhow::{anyhow, Context as _};
+use anyhow::Context as _;
 use anyhow::{bail, ensure, Result};
 use fallible_streaming_iterator::FallibleStreamingIterator;
 use rune_core::macros::{call, rebind, root};
 use rune_macros::defun;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 fn check_lower_bounds(idx: Option<i64>, len: usize) -> Result<usize> {
     let len = len as i64;
@@ -60,15 +65,50 @@ pub(crate) fn read_from_string<'ob>(
     Ok(Cons::new(obj, new_pos as i64, cx).into())
 }
 
-pub(crate) fn load_internal(contents: &str, cx: &mut Context, env: &mut Rt<Env>) -> Result<bool> {
+/// Whether the first line of `contents` has a `-*- lexical-binding: t -*-`
+/// cookie. Like in Emacs, a `#!` line before it is skipped.
+pub(crate) fn lexical_binding_cookie(contents: &str) -> bool {
+    let mut lines = contents.lines();
+    let mut line = lines.next().unwrap_or_default();
+    if line.starts_with("#!") {
+        line = lines.next().unwrap_or_default();
+    }
+    if !line.starts_with(';') {
+        return false;
+    }
+    let Some((_, rest)) = line.split_once("-*-") else { return false };
+    let Some((vars, _)) = rest.split_once("-*-") else { return false };
+    vars.split(';')
+        .filter_map(|x| x.split_once(':'))
+        .any(|(var, value)| var.trim() == "lexical-binding" && value.trim() != "nil")
+}
+
+/// Read and evaluate each form in `contents`. Forms are eagerly macroexpanded
+/// if `expand` is true, which is not needed for byte-compiled files.
+pub(crate) fn load_internal(
+    contents: &str,
+    expand: bool,
+    cx: &mut Context,
+    env: &mut Rt<Env>,
+) -> Result<bool> {
+    // variables declared with `(defvar VAR)` are special until the end of the
+    // file
+    let specials = env.local_specials.len();
+    let result = load_forms(contents, expand, cx, env);
+    env.local_specials.truncate(specials);
+    result
+}
+
+fn load_forms(contents: &str, expand: bool, cx: &mut Context, env: &mut Rt<Env>) -> Result<bool> {
     let mut pos = 0;
     let macroexpand: Option<Function> = None;
     root!(macroexpand, cx);
     if let Some(fun) = sym::INTERNAL_MACROEXPAND_FOR_LOAD.func(cx) {
-        macroexpand.set(Some(fun));
+        macroexpand.set(Some(fun).filter(|_| expand));
     }
     loop {
-        let (obj, new_pos) = match reader::read(&contents[pos..], cx) {
+        let load_file_name = env.var(sym::LOAD_FILE_NAME).map_or(NIL, |x| x.bind(cx));
+        let (obj, new_pos) = match reader::read_in_file(&contents[pos..], load_file_name, cx) {
             Ok((obj, pos)) => (obj, pos),
             Err(reader::Error::EmptyStream) => return Ok(true),
             Err(mut e) => {
@@ -85,7 +125,7 @@ pub(crate) fn load_internal(contents: &str, cx: &mut Context, env: &mut Rt<Env>)
         let result = if let Some(fun) = macroexpand.as_ref() {
             eager_expand(obj, fun, env, cx)
         } else {
-            interpreter::eval(obj, None, env, cx)
+            interpreter::eval_toplevel(obj, env, cx)
         };
         if let Err(e) = result {
             let content = &contents[pos..(new_pos + pos)];
@@ -118,27 +158,62 @@ fn eager_expand<'ob>(
     }
     let result = call!(macroexpand, val, TRUE; name, env, cx)?;
     root!(result, cx);
-    interpreter::eval(result, None, env, cx)
+    interpreter::eval_toplevel(result, env, cx)
+}
+
+/// Append `suffix` to the file name of `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|x| x.modified()).ok()
 }
 
-fn file_in_path(file: &str, path: &str) -> Option<PathBuf> {
-    let path = Path::new(path).join(file);
-    if path.exists() {
-        Some(path)
+/// Find `file` in `dir`, trying each of `suffixes` and then the name itself.
+/// If `prefer_newer` is true, the most recently modified of the candidates is
+/// used instead of the first.
+fn file_in_path(file: &str, dir: &str, suffixes: &[String], prefer_newer: bool) -> Option<PathBuf> {
+    let path = Path::new(dir).join(file);
+    let mut candidates = suffixes
+        .iter()
+        .map(|suffix| with_suffix(&path, suffix))
+        .chain(std::iter::once(path.clone()))
+        .filter(|x| x.is_file());
+    if prefer_newer {
+        candidates.reduce(|a, b| if modified(&b) > modified(&a) { b } else { a })
     } else {
-        let with_ext = path.with_extension("el");
-        with_ext.exists().then_some(with_ext)
+        candidates.next()
+    }
+}
+
+/// The values of `load-suffixes` and `load-prefer-newer`.
+fn load_suffixes(cx: &Context, env: &Rt<Env>) -> Result<(Vec<String>, bool)> {
+    let mut suffixes = Vec::new();
+    if let Some(list) = env.var(sym::LOAD_SUFFIXES) {
+        for suffix in list.bind(cx).as_list().context("`load-suffixes' was not a list")? {
+            let suffix: &str = suffix?.try_into().context("Found non-string in `load-suffixes'")?;
+            suffixes.push(suffix.to_owned());
+        }
     }
+    let prefer_newer = env.var(sym::LOAD_PREFER_NEWER).is_some_and(|x| x.bind(cx) != NIL);
+    Ok((suffixes, prefer_newer))
 }
 
 fn find_file_in_load_path(file: &str, cx: &Context, env: &Rt<Env>) -> Result<PathBuf> {
-    let load_path = env.vars.get(sym::LOAD_PATH).unwrap();
+    let (suffixes, prefer_newer) = load_suffixes(cx, env)?;
+    if let Some(x) = file_in_path(file, "", &suffixes, prefer_newer) {
+        return Ok(x);
+    }
+    let load_path = env.var(sym::LOAD_PATH).unwrap();
     let paths = load_path.bind(cx).as_list().context("`load-path' was not a list")?;
     let mut final_file = None;
     for path in paths {
         match path?.untag() {
             ObjectType::String(path) => {
-                if let Some(x) = file_in_path(file, path) {
+                if let Some(x) = file_in_path(file, path, &suffixes, prefer_newer) {
                     final_file = Some(x);
                     break;
                 }
@@ -149,7 +224,15 @@ fn find_file_in_load_path(file: &str, cx: &Context, env: &Rt<Env>) -> Result<Pat
             }
         }
     }
-    final_file.ok_or_else(|| anyhow!("Unable to find file `{file}' in load-path"))
+    final_file.ok_or_else(|| {
+        let missing = std::io::ErrorKind::NotFound.into();
+        LispError::file_error("Cannot open load file", file, &missing, cx).into()
+    })
+}
+
+/// Whether `file` is byte-compiled.
+fn is_compiled(file: &Path) -> bool {
+    file.extension().is_some_and(|x| x == "elc")
 }
 
 #[defun]
@@ -163,16 +246,22 @@ pub(crate) fn load(
     let noerror = noerror.is_some();
     let nomessage = nomessage.is_some();
     let file: &str = file.untag(cx);
-    let final_file = if Path::new(file).exists() {
-        PathBuf::from(file)
-    } else {
-        match find_file_in_load_path(file, cx, env) {
-            Ok(x) => x,
-            Err(e) => {
-                return if noerror { Ok(false) } else { Err(e) };
-            }
+    let final_file = match find_file_in_load_path(file, cx, env) {
+        Ok(x) => x,
+        Err(e) => {
+            return if noerror { Ok(false) } else { Err(e) };
         }
     };
+    let compiled = is_compiled(&final_file);
+    if compiled && !nomessage {
+        let source = final_file.with_extension("el");
+        if modified(&source) > modified(&final_file) {
+            println!(
+                "Source file `{}' newer than byte-compiled file; using older file",
+                source.display()
+            );
+        }
+    }
 
     let filename = String::from(file);
     if !nomessage {
@@ -188,23 +277,149 @@ pub(crate) fn load(
         None => NIL,
     };
     root!(prev_load_file, cx);
-    let result = match fs::read_to_string(&final_file)
-        .with_context(|| format!("Couldn't open file {:?}", final_file.as_os_str()))
-    {
-        Ok(content) => load_internal(&content, cx, env),
+    let result = match fs::read_to_string(&final_file).map_err(|e| {
+        let path = final_file.to_string_lossy();
+        LispError::file_error("Opening input file", &path, &e, cx).into()
+    }) {
+        Ok(content) => {
+            let lexical = if lexical_binding_cookie(&content) { TRUE } else { NIL };
+            env.varbind(sym::LEXICAL_BINDING, lexical, cx);
+            env.varbind(sym::CURRENT_LOAD_LIST, NIL, cx);
+            let load_path = env.var(sym::LOAD_PATH).map_or(NIL, |x| x.bind(cx));
+            let result = match cache::lookup(&final_file, &content, load_path) {
+                Some(cached) => load_internal(&cached, false, cx, env),
+                None => load_internal(&content, !compiled, cx, env),
+            };
+            let result = result.and_then(|x| build_load_history(env, cx).map(|_| x));
+            env.unbind(2, cx);
+            result
+        }
         Err(e) => match noerror {
             true => Ok(false),
             false => Err(e),
         },
     };
 
+    // Run the `eval-after-load' forms and `after-load-functions' for the file
+    // while `load-file-name' is still bound to it
+    let result = match (result, sym::DO_AFTER_LOAD_EVALUATION.func(cx)) {
+        (Ok(true), Some(func)) => {
+            root!(func, cx);
+            let file = cx.add(final_file.to_string_lossy().to_string());
+            root!(file, cx);
+            call!(func, &*file; env, cx).map(|_| true).map_err(Into::into)
+        }
+        (result, _) => result,
+    };
+    env.vars.insert(sym::LOAD_FILE_NAME, &*prev_load_file);
     if !nomessage && result.is_ok() {
         println!("Loading {filename} Done");
     }
-    env.vars.insert(sym::LOAD_FILE_NAME, &*prev_load_file);
     result
 }
 
+/// Record `x` in `current-load-list`, which becomes the `load-history` entry
+/// of the file that is being loaded.
+pub(crate) fn loadhist_attach(x: Object, env: &mut Rt<Env>, cx: &Context) -> Result<()> {
+    let list = env.var(sym::CURRENT_LOAD_LIST).map_or(NIL, |x| x.bind(cx));
+    env.set_var(sym::CURRENT_LOAD_LIST, Cons::new(x, list, cx).into(), cx)
+}
+
+/// Add what `current-load-list` recorded to `load-history` as the entry for
+/// the current `load-file-name`, replacing the entry of an earlier load.
+fn build_load_history(env: &mut Rt<Env>, cx: &Context) -> Result<()> {
+    let file = env.var(sym::LOAD_FILE_NAME).map_or(NIL, |x| x.bind(cx));
+    let current = env.var(sym::CURRENT_LOAD_LIST).map_or(NIL, |x| x.bind(cx));
+    let history = env.var(sym::LOAD_HISTORY).map_or(NIL, |x| x.bind(cx));
+    let mut entries = vec![Cons::new(file, crate::fns::nreverse(current.try_into()?)?, cx).into()];
+    for entry in history.as_list()? {
+        let entry = entry?;
+        if !matches!(entry.untag(), ObjectType::Cons(cons) if cons.car() == file) {
+            entries.push(entry);
+        }
+    }
+    let history = crate::fns::slice_into_list(&entries, None, cx);
+    env.set_var(sym::LOAD_HISTORY, history, cx)
+}
+
+/// Evaluate the text of `buffer`, which defaults to the current buffer. Like
+/// `load`, lexical binding is only used if the buffer has a cookie for it.
+#[defun]
+pub(crate) fn eval_buffer(
+    buffer: Option<&Rto<Object>>,
+    _printflag: Option<&Rto<Object>>,
+    cx: &mut Context,
+    env: &mut Rt<Env>,
+) -> Result<()> {
+    let buffer = match buffer.map(|x| x.bind(cx)) {
+        Some(buffer) if !buffer.is_nil() => Some(crate::buffer::resolve_buffer(buffer, cx)?),
+        _ => None,
+    };
+    env.record_current_buffer(cx);
+    if let Some(buffer) = buffer {
+        env.set_buffer(buffer);
+    }
+    let contents = env.current_buffer.get().text.to_string();
+    let lexical = if lexical_binding_cookie(&contents) { TRUE } else { NIL };
+    env.varbind(sym::LEXICAL_BINDING, lexical, cx);
+    let result = load_internal(&contents, true, cx, env);
+    env.unbind(2, cx);
+    result.map(|_| ())
+}
+
+/// Write the autoloads for the `;;;###autoload` cookies in the `.el` files of
+/// `dir`, a directory or list of directories, and their subdirectories to
+/// `output-file`. Files in `excluded-files` are skipped.
+#[defun]
+fn loaddefs_generate(
+    dir: Object,
+    output_file: &str,
+    excluded_files: Option<Object>,
+    cx: &Context,
+) -> Result<()> {
+    let mut dirs = match dir.untag() {
+        ObjectType::String(dir) => vec![PathBuf::from(dir.as_ref())],
+        _ => {
+            let mut dirs = Vec::new();
+            for dir in dir.as_list()? {
+                let dir: &str = dir?.try_into()?;
+                dirs.push(PathBuf::from(dir));
+            }
+            dirs
+        }
+    };
+    let output = Path::new(output_file);
+    let mut excluded = vec![output.to_owned()];
+    for file in excluded_files.unwrap_or_default().as_list()? {
+        let file: &str = file?.try_into()?;
+        excluded.push(PathBuf::from(file));
+    }
+    let canonical = |x: &Path| x.canonicalize().unwrap_or_else(|_| x.to_owned());
+    let excluded: Vec<_> = excluded.iter().map(|x| canonical(x)).collect();
+    let mut files = Vec::new();
+    while let Some(dir) = dirs.pop() {
+        let entries =
+            fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let name = path.file_name().unwrap().to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if name.ends_with(".el") && !excluded.contains(&canonical(&path)) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    let contents = loaddefs::generate(&files, output, cx)?;
+    fs::write(output, contents).with_context(|| format!("Failed to write {output_file}"))?;
+    Ok(())
+}
+
 #[defun]
 pub(crate) fn intern<'ob>(string: &str, cx: &'ob Context) -> Symbol<'ob> {
     crate::core::env::intern(string, cx)
@@ -233,11 +448,19 @@ pub(crate) fn intern_soft(string: Object, obarray: OptionalFlag) -> Result<Symbo
 }
 
 defsym!(INTERNAL_MACROEXPAND_FOR_LOAD);
+defsym!(BYTE_COMPILE);
+defsym!(BYTE_OPTIMIZE);
+defsym!(DEFUN);
+defsym!(DEFMACRO);
+defsym!(DEFSUBST);
+defsym!(DO_AFTER_LOAD_EVALUATION);
 defvar!(LEXICAL_BINDING, true);
 defvar!(CURRENT_LOAD_LIST);
 defvar!(LOAD_HISTORY);
 defvar!(LOAD_PATH, list![format!("{}/lisp", env!("CARGO_MANIFEST_DIR"))]);
 defvar!(LOAD_FILE_NAME);
+defvar!(LOAD_SUFFIXES, list![".elc", ".el"]);
+defvar!(LOAD_PREFER_NEWER, false);
 defvar!(BYTE_BOOLEAN_VARS);
 defvar!(MACROEXP__DYNVARS);
 defvar!(AFTER_LOAD_ALIST);
@@ -249,17 +472,229 @@ mod test {
     use crate::core::gc::RootSet;
     use rune_core::macros::root;
 
+    #[test]
+    fn test_lexical_binding_cookie() {
+        assert!(lexical_binding_cookie(";;; foo.el --- Foo -*- lexical-binding:t -*-\n"));
+        assert!(lexical_binding_cookie(
+            "#!/usr/bin/env emacs\n;; -*- mode: lisp; lexical-binding: t; -*-"
+        ));
+        assert!(!lexical_binding_cookie(";;; -*- lexical-binding: nil -*-"));
+        assert!(!lexical_binding_cookie(";;; foo.el\n;; -*- lexical-binding: t -*-"));
+        assert!(!lexical_binding_cookie("(setq x 1) ; -*- lexical-binding: t -*-"));
+    }
+
     #[test]
     fn test_load() {
         let roots = &RootSet::default();
         let cx = &mut Context::new(roots);
         sym::init_symbols();
         root!(env, new(Env), cx);
-        load_internal("(setq foo 1) (setq bar 2) (setq baz 1.5)", cx, env).unwrap();
+        load_internal("(setq foo 1) (setq bar 2) (setq baz 1.5)", true, cx, env).unwrap();
 
         let obj = reader::read("(+ foo bar baz)", cx).unwrap().0;
         root!(obj, cx);
-        let val = interpreter::eval(obj, None, env, cx).unwrap();
+        let val = interpreter::eval_toplevel(obj, env, cx).unwrap();
         assert_eq!(val, 4.5);
     }
+
+    #[test]
+    fn test_load_compiled() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        root!(env, new(Env), cx);
+        let elc = ";ELC\x1d\0\0\0
+#@5 doc\x1f
+(defalias 'load-test #[257 \"\\211\\207\" [] 2 (#$ . 4)])
+";
+        load_internal(elc, false, cx, env).unwrap();
+        let obj = reader::read("(load-test 5)", cx).unwrap().0;
+        root!(obj, cx);
+        let val = interpreter::eval_toplevel(obj, env, cx).unwrap();
+        assert_eq!(val, 5);
+    }
+
+    #[test]
+    fn test_load_suffixes() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        root!(env, new(Env), cx);
+        crate::core::env::init_variables(cx, env);
+        let dir = std::env::temp_dir().join(format!("rune-load-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("foo.el");
+        let compiled = dir.join("foo.elc");
+        fs::write(&compiled, "").unwrap();
+        fs::write(&source, "").unwrap();
+        let newer = modified(&compiled).unwrap() + std::time::Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(newer)
+            .unwrap();
+
+        let file = dir.join("foo");
+        let file = file.to_str().unwrap();
+        assert_eq!(find_file_in_load_path(file, cx, env).unwrap(), compiled);
+        env.vars.insert(sym::LOAD_PREFER_NEWER, TRUE);
+        assert_eq!(find_file_in_load_path(file, cx, env).unwrap(), source);
+        let explicit = source.to_str().unwrap();
+        env.vars.insert(sym::LOAD_PREFER_NEWER, NIL);
+        assert_eq!(find_file_in_load_path(explicit, cx, env).unwrap(), source);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_history() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        root!(env, new(Env), cx);
+        crate::core::env::init_variables(cx, env);
+        crate::data::init_errors(env, cx);
+        let dir = std::env::temp_dir().join(format!("rune-history-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let library = "(defvar history-test-var 1)
+(defalias 'history-test-fn #'(lambda () 1))
+(provide 'history-test)";
+        fs::write(dir.join("history-test.el"), library).unwrap();
+        let user = "(require 'history-test)\n(provide 'history-user)";
+        fs::write(dir.join("history-user.el"), user).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let mut eval = |form: &str| {
+            let obj = reader::read(form, cx).unwrap().0;
+            root!(obj, cx);
+            interpreter::eval_toplevel(obj, env, cx).unwrap().to_string()
+        };
+        let require = format!("(require 'history-test \"{dir}/history-test\")");
+        assert_eq!(eval(&require), "history-test");
+        assert_eq!(
+            eval("(list (featurep 'history-test) history-test-var (history-test-fn))"),
+            "(t 1 1)"
+        );
+        let entry = format!("(cdr (assoc \"{dir}/history-test.el\" load-history))");
+        assert_eq!(
+            eval(&entry),
+            "(history-test-var (defun . history-test-fn) (provide . history-test))"
+        );
+        // loading it again replaces the entry
+        assert_eq!(eval(&format!("(load \"{dir}/history-test\")")), "t");
+        assert_eq!(eval("(length load-history)"), "1");
+
+        let require = format!("(require 'history-user \"{dir}/history-user\")");
+        assert_eq!(eval(&require), "history-user");
+        assert_eq!(
+            eval("(condition-case err (unload-feature 'history-test) (error 'failed))"),
+            "failed"
+        );
+        assert_eq!(eval("(unload-feature 'history-test t)"), "nil");
+        assert_eq!(
+            eval("(list (featurep 'history-test) (boundp 'history-test-var) (fboundp 'history-test-fn))"),
+            "(nil nil nil)"
+        );
+        assert_eq!(eval("(length load-history)"), "1");
+
+        eval("(defalias 'do-after-load-evaluation #'(lambda (file) (setq after-load-file (list file load-file-name))))");
+        assert_eq!(eval(&format!("(load \"{dir}/history-test.el\")")), "t");
+        let file = format!("\"{dir}/history-test.el\"");
+        assert_eq!(eval("after-load-file"), format!("({file} {file})"));
+        assert_eq!(eval("load-file-name"), "nil");
+        eval("(fmakunbound 'do-after-load-evaluation)");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_autoload() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        root!(env, new(Env), cx);
+        crate::core::env::init_variables(cx, env);
+        crate::data::init_errors(env, cx);
+        let dir = std::env::temp_dir().join(format!("rune-autoload-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        let library = ";;; -*- lexical-binding: t -*-
+;;;###autoload
+(defun autoload-test-fn (x) \"Double X.\" (* 2 x))
+;;;###autoload
+(defmacro autoload-test-macro (x) (list 'quote x))
+;;;###autoload (defvar autoload-test-var 3)
+(provide 'autoload-test)";
+        fs::write(dir.join("lib/autoload-test.el"), library).unwrap();
+        fs::write(dir.join("lib/autoload-empty.el"), "(defvar autoload-empty t)").unwrap();
+        let broken = "(defun autoload-test-broken () 1) (provide 'autoload-broken) (car 1)";
+        fs::write(dir.join("lib/autoload-broken.el"), broken).unwrap();
+        let keymap = "(fset 'autoload-test-map (make-sparse-keymap))";
+        fs::write(dir.join("lib/autoload-keymap.el"), keymap).unwrap();
+        fs::create_dir_all(dir.join("lib/sub")).unwrap();
+        let nested = ";;;###autoload\n(defun autoload-test-nested () 5)";
+        fs::write(dir.join("lib/sub/autoload-nested.el"), nested).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let mut eval = |form: &str| {
+            let obj = reader::read(form, cx).unwrap().0;
+            root!(obj, cx);
+            interpreter::eval_toplevel(obj, env, cx).unwrap().to_string()
+        };
+        // defun and defmacro are defined in subr.el
+        eval(
+            "(defalias 'defun (cons 'macro #'(lambda (name args &rest body)
+  (list 'defalias (list 'quote name) (list 'function (cons 'lambda (cons args body)))))))",
+        );
+        eval(
+            "(defalias 'defmacro (cons 'macro #'(lambda (name args &rest body)
+  (list 'defalias (list 'quote name)
+    (list 'cons ''macro (list 'function (cons 'lambda (cons args body))))))))",
+        );
+        eval(&format!("(loaddefs-generate \"{dir}/lib\" \"{dir}/autoload-defs.el\")"));
+        let defs = fs::read_to_string(format!("{dir}/autoload-defs.el")).unwrap();
+        assert!(defs
+            .contains("(autoload 'autoload-test-fn \"lib/autoload-test\" \"Double X.\" nil nil)"));
+        assert!(defs.contains("(provide 'autoload-defs)"));
+        assert!(defs.contains("(autoload 'autoload-test-nested \"lib/sub/autoload-nested\""));
+        eval(&format!("(setq load-path (cons \"{dir}\" load-path))"));
+        assert_eq!(eval("(load \"autoload-defs\")"), "t");
+        assert_eq!(eval("autoload-test-var"), "3");
+        assert_eq!(eval("(car (symbol-function 'autoload-test-macro))"), "autoload");
+        assert_eq!(eval("(macroexpand '(autoload-test-macro (a)))"), "(quote (a))");
+        assert_eq!(eval("(list (featurep 'autoload-test) (autoload-test-fn 2))"), "(t 4)");
+
+        eval("(autoload 'autoload-test-missing \"lib/autoload-empty\")");
+        assert_eq!(
+            eval("(condition-case err (autoload-test-missing) (error (car (cdr err))))"),
+            format!("\"Autoloading file lib/autoload-empty failed to define function autoload-test-missing\"")
+        );
+        // macro-only loads don't check the definition
+        eval("(autoload 'autoload-test-missing-macro \"lib/autoload-empty\" nil nil 'macro)");
+        assert_eq!(
+            eval("(autoload-do-load (symbol-function 'autoload-test-missing-macro) 'autoload-test-missing-macro 'macro)"),
+            "nil"
+        );
+        assert_eq!(eval("(autoload-test-nested)"), "5");
+        // a failed load is undone
+        eval("(autoload 'autoload-test-broken \"lib/autoload-broken\")");
+        assert_eq!(
+            eval("(condition-case err (autoload-test-broken) (error (car err)))"),
+            "wrong-type-argument"
+        );
+        assert_eq!(eval("(car (symbol-function 'autoload-test-broken))"), "autoload");
+        assert_eq!(eval("(featurep 'autoload-broken)"), "nil");
+        // keymap autoloads are loaded once the keymap is used
+        eval("(autoload 'autoload-test-map \"lib/autoload-keymap\" nil nil 'keymap)");
+        assert_eq!(
+            eval("(list (keymapp 'autoload-test-map) (functionp 'autoload-test-map))"),
+            "(t nil)"
+        );
+        assert_eq!(eval("(car (symbol-function 'autoload-test-map))"), "autoload");
+        eval("(define-key 'autoload-test-map \"a\" 'ignore)");
+        assert_eq!(eval("(symbol-function 'autoload-test-map)"), "(keymap)");
+        // an existing definition is not replaced
+        assert_eq!(eval("(autoload 'autoload-test-fn \"lib/autoload-empty\")"), "nil");
+        eval("(fmakunbound 'defun)");
+        eval("(fmakunbound 'defmacro)");
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,510 @@
+//! Cache of byte-compiled bootstrap libraries.
+//!
+//! While the cache is enabled, each `.el` file that is loaded is looked up in
+//! the cache directory under a key made from the hash of its source, of the
+//! running binary and of the files in `load-path`. The last covers the macros
+//! from other files that were expanded when it was compiled, since which
+//! files those come from is not tracked. If it is found, the compiled file is loaded instead of
+//! the source. Otherwise the source is loaded as usual and the file is
+//! compiled into the cache by [`compile_pending`] once the byte compiler
+//! itself has been loaded.
+use crate::core::cons::Cons;
+use crate::core::env::{sym, Env};
+use crate::core::gc::{Context, Rt, Slot};
+use crate::core::object::{ByteFn, FunctionType, Object, ObjectType, NIL, TRUE};
+use crate::reader;
+use anyhow::{Context as _, Result};
+use rune_core::macros::{call, list, root};
+use std::fmt::Write as _;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+struct Cache {
+    dir: PathBuf,
+    /// Identifies the running binary, so that entries written by a different
+    /// build are not used.
+    binary: u64,
+    /// Source files loaded this session that are not in the cache yet, with
+    /// the entry that each is compiled to.
+    pending: Vec<(PathBuf, PathBuf)>,
+    /// The directories in `load-path` when [`load_path_id`] was last called,
+    /// and its result.
+    load_path: Option<(Vec<PathBuf>, u64)>,
+}
+
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+/// The cache directory to use: `$RUNE_CACHE_DIR`, or `rune` in the user's
+/// cache directory.
+pub(crate) fn default_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("RUNE_CACHE_DIR") {
+        return Some(dir.into());
+    }
+    let cache = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache.join("rune"))
+}
+
+fn binary_id() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    if let Ok(meta) = std::env::current_exe().and_then(fs::metadata) {
+        meta.len().hash(&mut hasher);
+        meta.modified().ok().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Start using the cache in `dir` for files that are loaded.
+pub(crate) fn enable(dir: PathBuf) {
+    let cache = Cache { dir, binary: binary_id(), pending: Vec::new(), load_path: None };
+    *CACHE.lock().unwrap() = Some(cache);
+}
+
+/// Stop using the cache. Files that have not been compiled yet are dropped.
+pub(crate) fn disable() {
+    *CACHE.lock().unwrap() = None;
+}
+
+/// Identifies the Lisp files in `dirs` by their names, sizes and modification
+/// times. The directories are not searched recursively.
+fn load_path_id(dirs: &[PathBuf]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        let mut files: Vec<_> = entries
+            .filter_map(|x| x.ok())
+            .filter(|x| x.path().extension().is_some_and(|x| x == "el"))
+            .filter_map(|x| Some((x.file_name(), x.metadata().ok()?)))
+            .map(|(name, meta)| (name, meta.len(), meta.modified().ok()))
+            .collect();
+        files.sort();
+        dir.hash(&mut hasher);
+        files.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The path of the cache entry for `source`. Only files that end in `.el`
+/// are cached.
+fn entry(cache: &mut Cache, source: &Path, contents: &[u8], load_path: Object) -> Option<PathBuf> {
+    if source.extension().is_none_or(|x| x != "el") {
+        return None;
+    }
+    let stem = source.file_stem()?.to_str()?;
+    let dirs: Vec<PathBuf> = load_path
+        .as_list()
+        .ok()?
+        .filter_map(|x| match x.ok()?.untag() {
+            ObjectType::String(dir) => Some(PathBuf::from(dir.as_ref())),
+            _ => None,
+        })
+        .collect();
+    let load_path_id = match &cache.load_path {
+        Some((prev, id)) if *prev == dirs => *id,
+        _ => {
+            let id = load_path_id(&dirs);
+            cache.load_path = Some((dirs, id));
+            id
+        }
+    };
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    cache.binary.hash(&mut hasher);
+    load_path_id.hash(&mut hasher);
+    Some(cache.dir.join(format!("{stem}-{:016x}.elc", hasher.finish())))
+}
+
+/// Return the contents of the compiled version of `source` if it is in the
+/// cache. Otherwise the file is remembered so that it can be compiled later.
+pub(super) fn lookup(source: &Path, contents: &str, load_path: Object) -> Option<String> {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.as_mut()?;
+    let entry = entry(cache, source, contents.as_bytes(), load_path)?;
+    match fs::read_to_string(&entry) {
+        Ok(compiled) => Some(compiled),
+        Err(_) => {
+            cache.pending.push((source.to_owned(), entry));
+            None
+        }
+    }
+}
+
+/// Compile the files that were loaded from source into the cache. This needs
+/// `byte-compile` to be defined. Failures are reported but do not stop the
+/// other files from being compiled.
+pub(crate) fn compile_pending(cx: &mut Context, env: &mut Rt<Env>) {
+    let Some(pending) = CACHE.lock().unwrap().as_mut().map(|x| std::mem::take(&mut x.pending))
+    else {
+        return;
+    };
+    if sym::BYTE_COMPILE.func(cx).is_none() {
+        return;
+    }
+    // Compile without the optimizer, the same way that the compiler compiles
+    // itself when it is loaded, so that cached functions match those.
+    env.varbind(sym::BYTE_OPTIMIZE, NIL, cx);
+    for (source, entry) in pending {
+        if let Err(e) = compile_file(&source, &entry, cx, env) {
+            eprintln!("Failed to cache {}: {e}", source.display());
+        }
+    }
+    env.unbind(1, cx);
+}
+
+fn compile_file(source: &Path, entry: &Path, cx: &mut Context, env: &mut Rt<Env>) -> Result<()> {
+    let contents = fs::read_to_string(source)?;
+    let mut out = format!(";ELC\n;;; Compiled from {}\n", source.display());
+    // Compile with the same kind of binding that the file is loaded with
+    let lexical = if super::lexical_binding_cookie(&contents) { TRUE } else { NIL };
+    env.varbind(sym::LEXICAL_BINDING, lexical, cx);
+    let result = compile_forms(&contents, &mut out, cx, env);
+    env.unbind(1, cx);
+    result?;
+    remove_stale(entry);
+    fs::create_dir_all(entry.parent().unwrap())?;
+    // Write to a temporary file first so that a partial entry is never read
+    let tmp = entry.with_extension(format!("elc.{}", std::process::id()));
+    fs::write(&tmp, out)?;
+    fs::rename(&tmp, entry)?;
+    Ok(())
+}
+
+fn compile_forms(
+    contents: &str,
+    out: &mut String,
+    cx: &mut Context,
+    env: &mut Rt<Env>,
+) -> Result<()> {
+    // `(defvar VAR)` only makes VAR special for the rest of the file, so the
+    // ones seen so far are added to each form that is compiled on its own.
+    root!(defvars, new(Vec<Slot<Object>>), cx);
+    let mut pos = 0;
+    loop {
+        let (form, len) = match reader::read(&contents[pos..], cx) {
+            Ok(x) => x,
+            Err(reader::Error::EmptyStream) => break,
+            Err(e) => return Err(e.into()),
+        };
+        pos += len;
+        root!(form, cx);
+        if is_local_defvar(form.bind(cx)) {
+            defvars.push(form.bind(cx));
+        }
+        match compile_form(form, defvars, cx, env) {
+            Some(compiled) => out.push_str(&compiled),
+            None => {
+                let form = form.bind(cx);
+                print(form, out).with_context(|| format!("Unable to print {form}"))?;
+            }
+        }
+        out.push('\n');
+    }
+    Ok(())
+}
+
+/// Remove entries for older versions of the file that `entry` is for.
+fn remove_stale(entry: &Path) {
+    let (Some(dir), Some(name)) = (entry.parent(), entry.file_name().and_then(|x| x.to_str()))
+    else {
+        return;
+    };
+    let Some((stem, _)) = name.rsplit_once('-') else { return };
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for old in entries.filter_map(|x| x.ok()) {
+        let old_name = old.file_name();
+        let Some(old_name) = old_name.to_str() else { continue };
+        if old_name != name && old_name.rsplit_once('-').is_some_and(|x| x.0 == stem) {
+            let _ = fs::remove_file(old.path());
+        }
+    }
+}
+
+/// Top level forms that are worth compiling. Everything else is only run once
+/// and is kept as source.
+fn is_definition(form: Object) -> bool {
+    let Ok((head, _)) = form.as_cons_pair() else { return false };
+    matches!(head, sym::DEFUN | sym::DEFMACRO | sym::DEFSUBST)
+}
+
+/// Whether `form` is a `(defvar VAR)` without a value.
+fn is_local_defvar(form: Object) -> bool {
+    let Ok((sym::DEFVAR, ObjectType::Cons(rest))) = form.as_cons_pair() else { return false };
+    matches!(rest.car().untag(), ObjectType::Symbol(_)) && rest.cdr().is_nil()
+}
+
+/// If `form` is a `defun` of a function that is already byte-compiled, return
+/// the name and the definition.
+fn defined_byte_code<'ob>(
+    form: Object<'ob>,
+    cx: &'ob Context,
+) -> Option<(Object<'ob>, &'ob ByteFn)> {
+    let mut iter = form.as_list().ok()?;
+    if iter.next()?.ok()? != sym::DEFUN {
+        return None;
+    }
+    let name = iter.next()?.ok()?;
+    let ObjectType::Symbol(symbol) = name.untag() else { return None };
+    match symbol.func(cx)?.untag() {
+        FunctionType::ByteFn(func) => Some((name, func)),
+        _ => None,
+    }
+}
+
+/// Compile `form` into a call to a byte-compiled function. Return `None` if the
+/// form is not a definition or the result can't be read back.
+fn compile_form(
+    form: &Rt<Slot<Object>>,
+    defvars: &Rt<Vec<Slot<Object>>>,
+    cx: &mut Context,
+    env: &mut Rt<Env>,
+) -> Option<String> {
+    if !is_definition(form.bind(cx)) {
+        return None;
+    }
+    // Functions that were compiled while they were loaded, like the ones the
+    // compiler compiles itself, are kept as they are so that they don't
+    // change depending on whether they came from the cache.
+    if let Some(func) = defined_byte_code(form.bind(cx), cx) {
+        let mut out = String::new();
+        print(form.bind(cx), &mut out).ok()?;
+        let (name, func) = func;
+        out.push_str("\n(defalias '");
+        print(name, &mut out).ok()?;
+        out.push(' ');
+        print(func.into(), &mut out).ok()?;
+        out.push(')');
+        return Some(out);
+    }
+    let byte_compile = sym::BYTE_COMPILE.func(cx)?;
+    root!(byte_compile, cx);
+    let body = defvars
+        .iter()
+        .rev()
+        .fold(list![form.bind(cx); cx], |acc, x| Cons::new(x.bind(cx), acc, cx).into());
+    let lambda: Object = Cons::new(sym::LAMBDA, Cons::new(NIL, body, cx), cx).into();
+    root!(lambda, cx);
+    let compiled = call!(byte_compile, &*lambda; env, cx).ok()?;
+    let ObjectType::ByteFn(func) = compiled.untag() else { return None };
+    if !is_executable(func) {
+        return None;
+    }
+    let mut out = String::from("(funcall ");
+    print(func.into(), &mut out).ok()?;
+    out.push(')');
+    Some(out)
+}
+
+/// Whether the VM can run `func` and the functions in its constants. Loading
+/// the source is better than failing later on.
+fn is_executable(func: &ByteFn) -> bool {
+    crate::bytecode::is_executable(func)
+        && func.consts().iter().all(|x| match x.untag() {
+            ObjectType::ByteFn(func) => is_executable(func),
+            _ => true,
+        })
+}
+
+/// Print `obj` so that the reader will read it back as an equal object. Fails
+/// for objects that have no read syntax.
+pub(super) fn print(obj: Object, out: &mut String) -> Result<()> {
+    let mut budget = 1_000_000;
+    print_readable(obj, out, &mut budget)
+}
+
+fn print_readable(obj: Object, out: &mut String, budget: &mut usize) -> Result<()> {
+    // Guards against circular objects
+    *budget = budget.checked_sub(1).context("Object is too large or circular")?;
+    match obj.untag() {
+        ObjectType::Int(x) => write!(out, "{x}")?,
+        ObjectType::Float(x) => {
+            let x = **x;
+            anyhow::ensure!(x.is_finite(), "Non-finite float {x}");
+            write!(out, "{x:?}")?;
+        }
+        ObjectType::Symbol(sym) => {
+            anyhow::ensure!(sym.interned(), "Uninterned symbol {sym}");
+            print_symbol(sym.name(), out)?;
+        }
+        ObjectType::String(string) => {
+            out.push('"');
+            for c in string.chars() {
+                if matches!(c, '"' | '\\') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push('"');
+        }
+        ObjectType::ByteString(string) => {
+            out.push('"');
+            for &byte in string.iter() {
+                match byte {
+                    b'"' | b'\\' => write!(out, "\\{}", byte as char)?,
+                    0x80.. => write!(out, "\\{byte:o}")?,
+                    _ => out.push(byte as char),
+                }
+            }
+            out.push('"');
+        }
+        ObjectType::Cons(cons) => {
+            out.push('(');
+            print_readable(cons.car(), out, budget)?;
+            let mut tail = cons.cdr();
+            loop {
+                match tail.untag() {
+                    ObjectType::NIL => break,
+                    ObjectType::Cons(cons) => {
+                        *budget = budget.checked_sub(1).context("List is too long or circular")?;
+                        out.push(' ');
+                        print_readable(cons.car(), out, budget)?;
+                        tail = cons.cdr();
+                    }
+                    _ => {
+                        out.push_str(" . ");
+                        print_readable(tail, out, budget)?;
+                        break;
+                    }
+                }
+            }
+            out.push(')');
+        }
+        ObjectType::Vec(vec) => {
+            out.push('[');
+            print_elements(vec.iter().map(|x| x.get()), out, budget)?;
+            out.push(']');
+        }
+        ObjectType::ByteFn(func) => {
+            out.push_str("#[");
+            match func.dynamic_args() {
+                Some(arglist) => print_readable(arglist, out, budget)?,
+                None => write!(out, "{}", func.args.into_arg_spec())?,
+            }
+            out.push_str(" \"");
+            for byte in func.codes() {
+                write!(out, "\\{byte:o}")?;
+            }
+            out.push_str("\" [");
+            print_elements(func.consts().iter().copied(), out, budget)?;
+            write!(out, "] {}", func.depth)?;
+            // the docstring slot comes before the interactive spec
+            if func.is_command() {
+                out.push_str(" nil ");
+                print_readable(func.interactive(), out, budget)?;
+            }
+            out.push(']');
+        }
+        _ => anyhow::bail!("No read syntax for {obj}"),
+    }
+    Ok(())
+}
+
+fn print_elements<'ob>(
+    objects: impl Iterator<Item = Object<'ob>>,
+    out: &mut String,
+    budget: &mut usize,
+) -> Result<()> {
+    for (i, obj) in objects.enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        print_readable(obj, out, budget)?;
+    }
+    Ok(())
+}
+
+/// Print a symbol name, escaping characters that would be read differently.
+fn print_symbol(name: &str, out: &mut String) -> Result<()> {
+    anyhow::ensure!(!name.is_empty(), "Symbol with an empty name");
+    // Names that would be read as numbers
+    if name.parse::<f64>().is_ok() || name.parse::<i64>().is_ok() || name == "." {
+        out.push('\\');
+    }
+    for (i, c) in name.chars().enumerate() {
+        let special = c <= ' '
+            || matches!(c, '(' | ')' | '[' | ']' | '"' | '\'' | ';' | '`' | ',' | '#' | '\\')
+            || (i == 0 && c == '?');
+        if special {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::env::intern;
+    use crate::core::gc::RootSet;
+
+    fn round_trip(obj: Object, cx: &Context) -> String {
+        let mut out = String::new();
+        print(obj, &mut out).unwrap();
+        let (read, len) = reader::read(&out, cx).unwrap();
+        assert_eq!(len, out.len(), "{out}");
+        assert!(crate::fns::equal(obj, read), "{obj} printed as {out} read as {read}");
+        out
+    }
+
+    #[test]
+    fn test_print_readable() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        sym::init_symbols();
+        root!(env, new(Env), cx);
+        for form in [
+            "(1 -2 (3.5 . 4) [a \"b\\\"c\\\\\"] nil t)",
+            r#"#[513 "\300\1\2\42\207" [cons] 5]"#,
+            r#"#[(x &rest y) "\10\207" [x] 1]"#,
+            r#""\377\0""#,
+        ] {
+            let (obj, _) = reader::read(form, cx).unwrap();
+            round_trip(obj, cx);
+        }
+        for (name, printed) in
+            [("1", "\\1"), ("1.5", "\\1.5"), ("a b", "a\\ b"), ("?x", "\\?x"), ("x?", "x?")]
+        {
+            assert_eq!(round_trip(intern(name, cx).into(), cx), printed);
+        }
+        // the interactive spec of a command is kept, even when it is nil
+        for form in [r#"#[0 "\300\207" [nil] 1 nil "p"]"#, r#"#[0 "\300\207" [nil] 1 nil nil]"#] {
+            let (command, _) = reader::read(form, cx).unwrap();
+            let mut out = String::new();
+            print(command, &mut out).unwrap();
+            let (read, _) = reader::read(&out, cx).unwrap();
+            assert!(crate::eval::commandp(read, None, env, cx).unwrap(), "{out}");
+        }
+        // objects with no read syntax
+        let mut out = String::new();
+        let uninterned = crate::core::object::Symbol::new_uninterned("x", cx);
+        assert!(print(uninterned.into(), &mut out).is_err());
+        let circular = list![1, 2; cx];
+        circular.as_cons().cdr().as_cons().set_cdr(circular).unwrap();
+        assert!(print(circular, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_load_path_id() {
+        let dir = std::env::temp_dir().join(format!("rune-load-path-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dirs = [dir.clone()];
+        fs::write(dir.join("a.el"), "(defmacro a () 1)").unwrap();
+        let before = load_path_id(&dirs);
+        assert_eq!(before, load_path_id(&dirs));
+        // other files are ignored
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        assert_eq!(before, load_path_id(&dirs));
+        // a changed dependency changes the key
+        fs::write(dir.join("a.el"), "(defmacro a () 22)").unwrap();
+        let changed = load_path_id(&dirs);
+        assert_ne!(before, changed);
+        fs::write(dir.join("b.el"), "").unwrap();
+        assert_ne!(changed, load_path_id(&dirs));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
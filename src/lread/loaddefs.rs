@@ -0,0 +1,213 @@
+//! Generation of loaddefs files from `;;;###autoload` cookies.
+//!
+//! Like `loaddefs-gen.el` in Emacs, the form following each cookie is turned
+//! into an `autoload` call if it defines a function or macro, and copied as is
+//! otherwise. A cookie followed by a form on the same line copies that form.
+//! Libraries are named relative to the directory of the generated file, so it
+//! can be loaded from `load-path` and autoload the libraries next to it.
+
+use super::cache::print;
+use crate::core::gc::Context;
+use crate::core::object::{Object, ObjectType, NIL, TRUE};
+use crate::reader;
+use anyhow::{Context as _, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const COOKIE: &str = ";;;###autoload";
+
+/// Generate the contents of the loaddefs file `output` from `files`.
+pub(super) fn generate(files: &[PathBuf], output: &Path, cx: &Context) -> Result<String> {
+    let name = output.file_name().and_then(|x| x.to_str()).unwrap_or("loaddefs.el");
+    let feature = name.strip_suffix(".el").unwrap_or(name);
+    let output_dir = output.parent().unwrap_or(Path::new(""));
+    let mut out = String::new();
+    writeln!(
+        out,
+        ";;; {name} --- automatically extracted autoloads  -*- lexical-binding: t -*-"
+    )?;
+    out.push_str(";;\n;;; Code:\n\n");
+    for file in files {
+        let contents = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let library = library_name(file, output_dir);
+        let entries = scan(&contents, &library, cx)
+            .with_context(|| format!("Failed to scan {}", file.display()))?;
+        if entries.is_empty() {
+            continue;
+        }
+        let ext = file.extension().and_then(|x| x.to_str()).unwrap_or("el");
+        writeln!(out, ";;; Generated autoloads from {library}.{ext}\n")?;
+        for entry in entries {
+            writeln!(out, "{entry}\n")?;
+        }
+    }
+    out.push_str(";;; End of scraped data\n\n");
+    writeln!(out, "(provide '{feature})\n")?;
+    out.push_str(";; Local Variables:\n;; version-control: never\n");
+    out.push_str(";; no-byte-compile: t\n;; no-update-autoloads: t\n;; End:\n\n");
+    writeln!(out, ";;; {name} ends here")?;
+    Ok(out)
+}
+
+/// The name to load `file` by, relative to `dir` and without the extension.
+fn library_name(file: &Path, dir: &Path) -> String {
+    let relative = file.strip_prefix(dir).unwrap_or(file.file_name().unwrap().as_ref());
+    let relative = relative.with_extension("");
+    let parts: Vec<_> = relative.iter().map(|x| x.to_string_lossy()).collect();
+    parts.join("/")
+}
+
+/// Return the autoload entries for the cookies in `contents`.
+fn scan(contents: &str, library: &str, cx: &Context) -> Result<Vec<String>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while let Some(idx) = contents[pos..].find(COOKIE) {
+        let start = pos + idx;
+        let after = start + COOKIE.len();
+        let line_end = contents[after..].find('\n').map_or(contents.len(), |x| after + x);
+        pos = line_end;
+        // cookies only count at the start of a line
+        if start != 0 && !contents[..start].ends_with('\n') {
+            continue;
+        }
+        let rest = contents[after..line_end].trim();
+        if !rest.is_empty() {
+            entries.push(rest.to_owned());
+            continue;
+        }
+        let (form, len) = match reader::read(&contents[line_end..], cx) {
+            Ok(x) => x,
+            // a cookie with nothing after it is ignored
+            Err(reader::Error::EmptyStream) => break,
+            Err(mut e) => {
+                e.update_pos(line_end);
+                return Err(e.into());
+            }
+        };
+        pos = line_end + len;
+        match make_autoload(form, library, cx)? {
+            Some(entry) => entries.push(entry),
+            None => entries.push(contents[line_end..pos].trim().to_owned()),
+        }
+    }
+    Ok(entries)
+}
+
+/// Turn a definition into the form that autoloads it. Return `None` for forms
+/// that should be copied as they are.
+fn make_autoload(form: Object, library: &str, cx: &Context) -> Result<Option<String>> {
+    let Ok(elems) = form.as_list() else { return Ok(None) };
+    let Ok(elems) = elems.collect::<Result<Vec<_>, _>>() else { return Ok(None) };
+    let [head, name, rest @ ..] = &elems[..] else { return Ok(None) };
+    let (ObjectType::Symbol(head), ObjectType::Symbol(_)) = (head.untag(), name.untag()) else {
+        return Ok(None);
+    };
+    let mut out = String::new();
+    let (doc, interactive, kind) = match head.name() {
+        // (defun NAME ARGS [DOCSTRING] [DECLARE] [INTERACTIVE] BODY...)
+        "defun" | "defun*" | "cl-defun" | "defsubst" | "cl-defsubst" | "define-inline" => {
+            let (doc, body) = docstring(rest.get(1..).unwrap_or_default());
+            (doc, is_interactive(body), NIL)
+        }
+        "defmacro" | "defmacro*" | "cl-defmacro" => {
+            let (doc, _) = docstring(rest.get(1..).unwrap_or_default());
+            (doc, false, TRUE)
+        }
+        // (define-minor-mode MODE DOC ...)
+        "define-minor-mode" | "define-globalized-minor-mode" => (docstring(rest).0, true, NIL),
+        // (define-derived-mode CHILD PARENT NAME [DOCSTRING] ...)
+        "define-derived-mode" => (docstring(rest.get(2..).unwrap_or_default()).0, true, NIL),
+        // (defcustom NAME STANDARD DOC ...)
+        "defcustom" => {
+            out.push_str("(defvar ");
+            print(*name, &mut out)?;
+            for x in rest.iter().take(2) {
+                out.push(' ');
+                print(*x, &mut out)?;
+            }
+            out.push(')');
+            return Ok(Some(out));
+        }
+        _ => return Ok(None),
+    };
+    out.push_str("(autoload '");
+    print(*name, &mut out)?;
+    out.push(' ');
+    print(cx.add(library), &mut out)?;
+    out.push(' ');
+    print(doc.unwrap_or(NIL), &mut out)?;
+    out.push_str(if interactive { " t " } else { " nil " });
+    print(kind, &mut out)?;
+    out.push(')');
+    Ok(Some(out))
+}
+
+/// Split the docstring off the front of `body`. A string that is the only
+/// form is the return value, not a docstring.
+fn docstring<'a, 'ob>(body: &'a [Object<'ob>]) -> (Option<Object<'ob>>, &'a [Object<'ob>]) {
+    match body {
+        [doc, rest @ ..] if !rest.is_empty() && matches!(doc.untag(), ObjectType::String(_)) => {
+            (Some(*doc), rest)
+        }
+        _ => (None, body),
+    }
+}
+
+/// Whether `body` starts with an `interactive` form, after any declarations.
+fn is_interactive(body: &[Object]) -> bool {
+    let head = |x: &Object| match x.as_cons_pair() {
+        Ok((head, _)) => head.name().to_owned(),
+        Err(_) => String::new(),
+    };
+    body.iter()
+        .map(head)
+        .find(|x| x != "declare")
+        .is_some_and(|x| x == "interactive")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::gc::RootSet;
+
+    #[test]
+    fn test_scan() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let contents = r#";;; -*- lexical-binding: t -*-
+;;;###autoload
+(defun foo-cmd (x)
+  "Do foo."
+  (interactive)
+  x)
+
+;;;###autoload
+(defmacro foo-macro (x) "Expand foo." x)
+
+;;;###autoload
+(defcustom foo-option 1 "An option." :type 'integer)
+
+;;;###autoload (put 'foo-cmd 'safe t)
+
+;;;###autoload
+(defalias 'foo-alias #'foo-cmd)
+
+(defun foo-private () nil)
+;;;###autoload"#;
+        let entries = scan(contents, "dir/foo", cx).unwrap();
+        assert_eq!(
+            entries,
+            [
+                r#"(autoload 'foo-cmd "dir/foo" "Do foo." t nil)"#,
+                r#"(autoload 'foo-macro "dir/foo" "Expand foo." nil t)"#,
+                r#"(defvar foo-option 1 "An option.")"#,
+                "(put 'foo-cmd 'safe t)",
+                "(defalias 'foo-alias #'foo-cmd)",
+            ]
+        );
+        assert_eq!(library_name(Path::new("/a/b/c.el"), Path::new("/a")), "b/c");
+        assert_eq!(library_name(Path::new("/x/c.el"), Path::new("/a")), "c");
+    }
+}
@@ -8,9 +8,11 @@ mod alloc;
 mod arith;
 mod buffer;
 mod bytecode;
+mod callint;
 mod casefiddle;
 mod character;
 mod chartab;
+mod cmds;
 mod data;
 mod dired;
 mod editfns;
@@ -25,6 +27,8 @@ mod keymap;
 mod library;
 mod lisp;
 mod lread;
+mod minibuf;
+mod nadvice;
 mod print;
 mod reader;
 mod search;
@@ -34,7 +38,7 @@ mod timefns;
 use crate::core::{
     env::{intern, sym, Env},
     gc::{Context, RootSet, Rt},
-    object::{Gc, LispString, NIL},
+    object::{Gc, LispString, Object, NIL},
 };
 use crate::eval::EvalError;
 use clap::Parser;
@@ -52,10 +56,20 @@ struct Args {
     no_bootstrap: bool,
     #[arg(long)]
     eval_stdin: bool,
+    #[arg(long, value_name = "SYMBOL")]
+    disassemble: Option<String>,
+    /// Don't use or update the cache of byte-compiled bootstrap files
+    #[arg(long)]
+    no_cache: bool,
+    #[cfg(feature = "vm_profile")]
+    #[arg(long, value_name = "FILE")]
+    vm_profile: Option<String>,
 }
 
 fn main() -> Result<(), ()> {
     let args = Args::parse();
+    #[cfg(feature = "vm_profile")]
+    let _profile = args.vm_profile.clone().map(bytecode::profile::DumpOnExit);
 
     let roots = &RootSet::default();
     let cx = &mut Context::new(roots);
@@ -63,7 +77,9 @@ fn main() -> Result<(), ()> {
 
     sym::init_symbols();
     crate::core::env::init_variables(cx, env);
-    crate::data::defalias(intern("not", cx), (sym::NULL).into(), None)
+    crate::data::init_errors(env, cx);
+    crate::buffer::init_buffer_locals(env, cx);
+    crate::data::defalias(intern("not", cx), (sym::NULL).into(), None, env, cx)
         .expect("null should be defined");
 
     if args.eval_stdin {
@@ -71,13 +87,17 @@ fn main() -> Result<(), ()> {
     }
 
     if !args.no_bootstrap {
-        bootstrap(env, cx)?;
+        bootstrap(!args.no_cache, env, cx)?;
     }
 
     for file in args.load {
         load(&file, cx, env)?;
     }
 
+    if let Some(name) = args.disassemble {
+        disassemble(&name, cx, env)?;
+    }
+
     if args.repl {
         repl(env, cx);
     }
@@ -91,6 +111,7 @@ fn parens_closed(buffer: &str) -> bool {
 }
 
 fn repl(env: &mut Rt<Env>, cx: &mut Context) {
+    env.vars.insert(sym::DEBUGGER, Object::from(sym::REPL_DEBUGGER));
     let mut buffer = String::new();
     let stdin = io::stdin();
     loop {
@@ -116,14 +137,20 @@ fn repl(env: &mut Rt<Env>, cx: &mut Context) {
         };
 
         root!(obj, cx);
-        match interpreter::eval(obj, None, env, cx) {
+        // the debugger can abort back to here
+        env.catch_stack.push(Object::from(sym::TOP_LEVEL));
+        let result = interpreter::eval_toplevel(obj, env, cx);
+        env.catch_stack.pop();
+        match result {
             Ok(val) => println!("{val}"),
-            Err(e) => {
-                eprintln!("Error: {e}");
-                if let Ok(e) = e.downcast::<EvalError>() {
+            Err(e) => match e.downcast::<EvalError>() {
+                Ok(e) if e.is_throw_to(sym::TOP_LEVEL, env, cx) => println!("Quit to top level"),
+                Ok(e) => {
+                    eprintln!("Error: {e}");
                     e.print_backtrace();
                 }
-            }
+                Err(e) => eprintln!("Error: {e}"),
+            },
         }
         buffer.clear();
     }
@@ -147,6 +174,21 @@ fn load(file: &str, cx: &mut Context, env: &mut Rt<Env>) -> Result<(), ()> {
     }
 }
 
+fn disassemble(name: &str, cx: &mut Context, env: &mut Rt<Env>) -> Result<(), ()> {
+    let symbol = intern(name, cx);
+    match bytecode::disassemble(symbol.into(), None, env, cx) {
+        Ok(listing) => {
+            let listing: &str = listing.try_into().expect("disassemble should return a string");
+            print!("{listing}");
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            Err(())
+        }
+    }
+}
+
 fn eval_stdin(cx: &mut Context, env: &mut Rt<Env>) -> Result<(), ()> {
     let mut buffer = String::new();
     let mut point = 0;
@@ -166,7 +208,7 @@ fn eval_stdin(cx: &mut Context, env: &mut Rt<Env>) -> Result<(), ()> {
         };
 
         root!(obj, cx);
-        match interpreter::eval(obj, None, env, cx) {
+        match interpreter::eval_toplevel(obj, env, cx) {
             Ok(val) => println!(";; ELPROP_START:{count}\n{val}\n;; ELPROP_END\n"),
             Err(e) => println!(";; ELPROP_START:{count}\nError: {e}\n;; ELPROP_END\n"),
         }
@@ -180,9 +222,18 @@ fn eval_stdin(cx: &mut Context, env: &mut Rt<Env>) -> Result<(), ()> {
     Err(())
 }
 
-fn bootstrap(env: &mut Rt<Env>, cx: &mut Context) -> Result<(), ()> {
+fn bootstrap(use_cache: bool, env: &mut Rt<Env>, cx: &mut Context) -> Result<(), ()> {
     buffer::get_buffer_create(cx.add("*scratch*"), Some(NIL), cx).unwrap();
-    load("bootstrap.el", cx, env)
+    let cache_dir = use_cache.then(lread::cache::default_dir).flatten();
+    if let Some(dir) = cache_dir {
+        lread::cache::enable(dir);
+    }
+    let result = load("bootstrap.el", cx, env);
+    if result.is_ok() {
+        lread::cache::compile_pending(cx, env);
+    }
+    lread::cache::disable();
+    result
 }
 
 #[test]
@@ -0,0 +1,142 @@
+//! Reading input from the minibuffer.
+use crate::core::{
+    env::{sym, Env},
+    gc::{Context, Rt},
+    object::{Object, ObjectType, NIL},
+};
+use crate::data::LispError;
+use anyhow::{bail, Result};
+use rune_core::macros::list;
+use rune_macros::defun;
+use std::io::Write;
+
+/// Read a line of input for `prompt`. Input is taken from
+/// `minibuffer-input-queue` while it is non-empty, which lets commands be
+/// run from scripts and tests. Otherwise a line is read from stdin, the way
+/// Emacs reads the minibuffer in batch mode.
+pub(crate) fn read_input(prompt: &str, env: &mut Rt<Env>, cx: &Context) -> Result<String> {
+    let queue = env.var(sym::MINIBUFFER_INPUT_QUEUE).map_or(NIL, |x| x.bind(cx));
+    if let ObjectType::Cons(queue) = queue.untag() {
+        let input: &str = queue.car().try_into()?;
+        env.set_var(sym::MINIBUFFER_INPUT_QUEUE, queue.cdr(), cx)?;
+        return Ok(input.to_owned());
+    }
+    print!("{prompt}");
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        let error = list![sym::END_OF_FILE, "Error reading from stdin"; cx];
+        bail!(LispError::new(error.try_into().unwrap()));
+    }
+    if line.ends_with('\n') {
+        line.pop();
+    }
+    Ok(line)
+}
+
+/// The first element of `default` if it is a list of defaults.
+fn first_default(default: Object) -> Object {
+    match default.untag() {
+        ObjectType::Cons(cons) => cons.car(),
+        _ => default,
+    }
+}
+
+#[defun]
+#[expect(clippy::too_many_arguments)]
+pub(crate) fn read_from_minibuffer<'ob>(
+    prompt: &str,
+    _initial_contents: Option<Object>,
+    _keymap: Option<Object>,
+    read: Option<Object>,
+    _hist: Option<Object>,
+    default_value: Option<Object<'ob>>,
+    _inherit_input_method: Option<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Object<'ob>> {
+    let input = read_input(prompt, env, cx)?;
+    if read.is_none_or(|x| x.is_nil()) {
+        return Ok(cx.add(input));
+    }
+    let input = match default_value.map(first_default) {
+        Some(default) if input.is_empty() => match default.untag() {
+            ObjectType::String(default) => default.to_string(),
+            _ => return Ok(default),
+        },
+        _ => input,
+    };
+    match crate::reader::read(&input, cx) {
+        Ok((obj, _)) => Ok(obj),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[defun]
+pub(crate) fn read_string<'ob>(
+    prompt: &str,
+    _initial_input: Option<Object>,
+    _history: Option<Object>,
+    default_value: Option<Object<'ob>>,
+    _inherit_input_method: Option<Object>,
+    env: &mut Rt<Env>,
+    cx: &'ob Context,
+) -> Result<Object<'ob>> {
+    let input = read_input(prompt, env, cx)?;
+    match default_value.map(first_default) {
+        Some(default) if input.is_empty() && !default.is_nil() => Ok(default),
+        _ => Ok(cx.add(input)),
+    }
+}
+
+#[defun]
+pub(crate) fn read_buffer(
+    prompt: &str,
+    def: Option<Object>,
+    require_match: Option<Object>,
+    _predicate: Option<Object>,
+    env: &mut Rt<Env>,
+    cx: &Context,
+) -> Result<String> {
+    let default = match def.map(first_default).map(|x| x.untag()) {
+        Some(ObjectType::String(name)) => Some(name.to_string()),
+        Some(ObjectType::Buffer(buffer)) => Some(env.with_buffer(buffer, |b| b.name())?),
+        _ => None,
+    };
+    let must_exist = require_match.is_some_and(|x| !x.is_nil());
+    loop {
+        let input = read_input(prompt, env, cx)?;
+        let name = match &default {
+            Some(default) if input.is_empty() => default.clone(),
+            _ => input,
+        };
+        // like the minibuffer, ask again until an existing buffer is given
+        if !must_exist || !crate::buffer::get_buffer(cx.add(name.as_str()), cx)?.is_nil() {
+            return Ok(name);
+        }
+    }
+}
+
+defvar!(MINIBUFFER_INPUT_QUEUE);
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::assert_lisp;
+
+    #[test]
+    fn test_read_from_minibuffer() {
+        assert_lisp(
+            "(progn (setq minibuffer-input-queue '(\"foo\" \"\" \"(1 2)\" \"\"))
+               (list (read-string \"a: \") (read-string \"b: \" nil nil \"def\")
+                     (read-from-minibuffer \"c: \" nil nil t) (read-from-minibuffer \"d: \" nil nil t nil \"x\")
+                     minibuffer-input-queue))",
+            "(\"foo\" \"def\" (1 2) x nil)",
+        );
+        assert_lisp(
+            "(progn (get-buffer-create \"test-read-buffer\")
+               (setq minibuffer-input-queue '(\"test-no-such-buffer\" \"test-read-buffer\"))
+               (read-buffer \"Buffer: \" nil t))",
+            "\"test-read-buffer\"",
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Native calling of advice objects.
+//!
+//! `nadvice.el` represents advice as OClosures of type `advice`, with the
+//! slots `car` (the piece of advice), `cdr` (the function being advised), `how`
+//! and `props`. Each kind of advice has a body such as `(apply car cdr r)`,
+//! which would be interpreted on every call. Instead, [`call`] combines the
+//! two functions directly according to `how`. Only closures are checked for
+//! advice, so calls to builtins without advice are not slowed down.
+use crate::core::{
+    cons::Cons,
+    env::{sym, CallFrame},
+    gc::{Context, Rt, Rto},
+    object::{Function, Object, ObjectType},
+};
+use crate::eval::EvalResult;
+use rune_core::macros::{call, rebind, root};
+
+/// How a piece of advice is combined with the function it advises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum How {
+    Around,
+    Before,
+    After,
+    Override,
+    AfterUntil,
+    AfterWhile,
+    BeforeUntil,
+    BeforeWhile,
+    FilterArgs,
+    FilterReturn,
+}
+
+impl How {
+    fn from_keyword(how: Object) -> Option<Self> {
+        let how = match how.untag() {
+            ObjectType::Symbol(sym::KW_AROUND) => Self::Around,
+            ObjectType::Symbol(sym::KW_BEFORE) => Self::Before,
+            ObjectType::Symbol(sym::KW_AFTER) => Self::After,
+            ObjectType::Symbol(sym::KW_OVERRIDE) => Self::Override,
+            ObjectType::Symbol(sym::KW_AFTER_UNTIL) => Self::AfterUntil,
+            ObjectType::Symbol(sym::KW_AFTER_WHILE) => Self::AfterWhile,
+            ObjectType::Symbol(sym::KW_BEFORE_UNTIL) => Self::BeforeUntil,
+            ObjectType::Symbol(sym::KW_BEFORE_WHILE) => Self::BeforeWhile,
+            ObjectType::Symbol(sym::KW_FILTER_ARGS) => Self::FilterArgs,
+            ObjectType::Symbol(sym::KW_FILTER_RETURN) => Self::FilterReturn,
+            _ => return None,
+        };
+        Some(how)
+    }
+}
+
+/// If `closure` is an advice object, return its piece of advice, the advised
+/// function and how they are combined. An interpreted OClosure stores its type
+/// as the first entry of its environment, followed by its slots in order.
+pub(crate) fn advice_slots<'ob>(closure: &'ob Cons) -> Option<(Function<'ob>, Function<'ob>, How)> {
+    if closure.car() != sym::CLOSURE {
+        return None;
+    }
+    let ObjectType::Cons(rest) = closure.cdr().untag() else { return None };
+    let env = rest.car();
+    let mut slots = env.as_list().ok()?;
+    let mut slot = |name: Object| match slots.next()?.ok()?.untag() {
+        ObjectType::Cons(slot) if slot.car() == name => Some(slot.cdr()),
+        _ => None,
+    };
+    if slot(sym::KW_TYPE.into())? != sym::ADVICE {
+        return None;
+    }
+    let advice = slot(sym::CAR.into())?.try_into().ok()?;
+    let func = slot(sym::CDR.into())?.try_into().ok()?;
+    let how = How::from_keyword(slot(sym::HOW.into())?)?;
+    Some((advice, func, how))
+}
+
+/// Call `advice` and `func`, the slots of an advice object, with the arguments
+/// of `frame`, combining them according to `how`. This is not inlined into
+/// `Function::call`, so that calling other functions does not take more stack
+/// space.
+#[inline(never)]
+pub(crate) fn call<'ob, 'a>(
+    advice: &Rto<Function<'a>>,
+    func: &Rto<Function<'a>>,
+    how: How,
+    frame: &mut CallFrame<'_, '_>,
+    cx: &'ob mut Context,
+) -> EvalResult<'ob> {
+    match how {
+        How::Around => call_with_args(advice, Some(func), frame, cx),
+        How::Override => call_with_args(advice, None, frame, cx),
+        How::Before => {
+            call_with_args(advice, None, frame, cx)?;
+            call_with_args(func, None, frame, cx)
+        }
+        How::After => {
+            let result = rebind!(call_with_args(func, None, frame, cx)?);
+            root!(result, cx);
+            call_with_args(advice, None, frame, cx)?;
+            Ok(result.bind(cx))
+        }
+        How::AfterUntil | How::AfterWhile | How::BeforeUntil | How::BeforeWhile => {
+            let (first, second) = match how {
+                How::AfterUntil | How::AfterWhile => (func, advice),
+                _ => (advice, func),
+            };
+            let until = matches!(how, How::AfterUntil | How::BeforeUntil);
+            let result = rebind!(call_with_args(first, None, frame, cx)?);
+            root!(result, cx);
+            if result.bind(cx).is_nil() == until {
+                call_with_args(second, None, frame, cx)
+            } else {
+                Ok(result.bind(cx))
+            }
+        }
+        How::FilterArgs => {
+            let args = Rt::bind_slice(frame.arg_slice(), cx);
+            let args = crate::fns::slice_into_list(args, None, cx);
+            let args = rebind!(call!(advice, args; frame, cx)?, cx);
+            let mut call_frame = CallFrame::new(frame);
+            for arg in args.as_list()? {
+                call_frame.push_arg(arg?);
+            }
+            func.call(&mut call_frame, None, cx)
+        }
+        How::FilterReturn => {
+            let result = rebind!(call_with_args(func, None, frame, cx)?);
+            call!(advice, result; frame, cx)
+        }
+    }
+}
+
+/// Call `func` with the arguments of `frame`, preceded by `first` if given.
+fn call_with_args<'ob>(
+    func: &Rto<Function>,
+    first: Option<&Rto<Function>>,
+    frame: &mut CallFrame<'_, '_>,
+    cx: &'ob mut Context,
+) -> EvalResult<'ob> {
+    let arg_cnt = frame.arg_count();
+    let end = frame.stack.len();
+    if let Some(first) = first {
+        frame.stack.push(Object::from(first.bind(cx)));
+    }
+    frame.stack.extend_as_vec_from_within(end - arg_cnt..end);
+    let count = frame.stack.len() - end;
+    let call_frame = &mut CallFrame::new_with_args(frame, count);
+    func.call(call_frame, None, cx)
+}
+
+defsym!(ADVICE);
+defsym!(HOW);
+defsym!(KW_TYPE);
+defsym!(KW_AROUND);
+defsym!(KW_BEFORE);
+defsym!(KW_AFTER);
+defsym!(KW_OVERRIDE);
+defsym!(KW_AFTER_UNTIL);
+defsym!(KW_AFTER_WHILE);
+defsym!(KW_BEFORE_UNTIL);
+defsym!(KW_BEFORE_WHILE);
+defsym!(KW_FILTER_ARGS);
+defsym!(KW_FILTER_RETURN);
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::assert_lisp;
+
+    fn advised(how: &str, advice: &str, args: &str) -> String {
+        // the body is never run when the advice is called natively
+        format!(
+            "(funcall '(closure ((:type . advice) (car . {advice}) (cdr . (lambda (x) (* x 10)))
+                                 (how . {how}) (props))
+                          (&rest r) 'interpreted)
+                      {args})"
+        )
+    }
+
+    #[test]
+    fn test_advice() {
+        assert_lisp(&advised(":around", "(lambda (f x) (1+ (funcall f x)))", "2"), "21");
+        let before = advised(":before", "(lambda (x) (put 'nadvice-before 'arg x))", "2");
+        assert_lisp(&format!("(list {before} (get 'nadvice-before 'arg))"), "(20 2)");
+        let after = advised(":after", "(lambda (x) (put 'nadvice-after 'arg x))", "2");
+        assert_lisp(&format!("(list {after} (get 'nadvice-after 'arg))"), "(20 2)");
+        assert_lisp(&advised(":override", "(lambda (x) (list x))", "2"), "(2)");
+        assert_lisp(&advised(":before-while", "(lambda (x) nil)", "2"), "nil");
+        assert_lisp(&advised(":before-until", "(lambda (x) x)", "2"), "2");
+        assert_lisp(&advised(":after-while", "(lambda (x) (list x))", "2"), "(2)");
+        assert_lisp(&advised(":after-until", "(lambda (x) (list x))", "2"), "20");
+        assert_lisp(&advised(":filter-args", "(lambda (args) (list (1+ (car args))))", "2"), "30");
+        assert_lisp(&advised(":filter-return", "(lambda (x) (1+ x))", "2"), "21");
+        // unknown kinds of advice fall back to the body
+        assert_lisp(&advised(":unknown", "(lambda (x) x)", "2"), "interpreted");
+    }
+
+    #[test]
+    fn test_advised_subr() {
+        // advice on a subr is seen by a byte-compiled caller and goes away when
+        // the original definition is restored, as `advice-add` and
+        // `advice-remove` do
+        assert_lisp(
+            r#"(let ((orig (symbol-function 'copysign))
+                     (caller #[0 "\300\301\302\"\207" [copysign 2.0 -1.0] 3]))
+                 (fset 'copysign
+                       (list 'closure
+                             (list '(:type . advice) (cons 'car #'(lambda (x) (* x 10)))
+                                   (cons 'cdr orig) '(how . :filter-return) '(props))
+                             '(&rest r) ''interpreted))
+                 (let ((advised (funcall caller)))
+                   (fset 'copysign orig)
+                   (list advised (funcall caller))))"#,
+            "(-20.0 -2.0)",
+        );
+    }
+}
@@ -1,13 +1,99 @@
 //! Printing utilities.
-use crate::core::object::Object;
+use crate::core::{
+    env::{sym, Env},
+    gc::{Context, Rt},
+    object::{Object, ObjectType, NIL},
+};
 use rune_macros::defun;
 
+/// Print `obj` the way `princ` does, which is without quoting strings.
+fn princ_to_string(obj: Object) -> String {
+    match obj.untag() {
+        ObjectType::String(string) => string.to_string(),
+        _ => obj.to_string(),
+    }
+}
+
 #[defun]
-fn error_message_string(obj: Object) -> String {
-    // TODO: implement
-    format!("Error: {obj}")
+fn error_message_string(obj: Object, env: &Rt<Env>, cx: &Context) -> String {
+    let (errname, data) = match obj.untag() {
+        ObjectType::Cons(cons) => (cons.car(), cons.cdr()),
+        _ => (NIL, NIL),
+    };
+    // `error` carries its own message, and other errors take it from the
+    // `error-message' property of the error symbol
+    let (mut message, mut tail, file_error) = match errname.untag() {
+        ObjectType::Symbol(sym::ERROR) => {
+            let (message, tail) = match data.untag() {
+                ObjectType::Cons(cons) => (cons.car(), cons.cdr()),
+                _ => (NIL, NIL),
+            };
+            (message, tail, false)
+        }
+        ObjectType::Symbol(name) => {
+            let conditions = crate::data::get(name, sym::ERROR_CONDITIONS, env, cx);
+            let file_error = conditions
+                .as_list()
+                .is_ok_and(|mut x| x.any(|x| x.is_ok_and(|x| x == sym::FILE_ERROR)));
+            (crate::data::get(name, sym::ERROR_MESSAGE, env, cx), data, file_error)
+        }
+        _ => (NIL, data, false),
+    };
+    // the data of a file error is all strings and the first one is the message
+    if file_error {
+        if let ObjectType::Cons(cons) = tail.untag() {
+            message = cons.car();
+            tail = cons.cdr();
+        }
+    }
+
+    let mut string = String::new();
+    let mut separator = Some(": ");
+    match message.untag() {
+        ObjectType::String(msg) if msg.is_empty() => separator = None,
+        ObjectType::String(msg) => string.push_str(msg),
+        _ => string.push_str("peculiar error"),
+    }
+    let use_princ = file_error || errname == sym::END_OF_FILE || errname == sym::USER_ERROR;
+    for item in tail.as_list().into_iter().flatten() {
+        let Ok(item) = item else { break };
+        if let Some(separator) = separator {
+            string.push_str(separator);
+        }
+        separator = Some(", ");
+        let item = if use_princ { princ_to_string(item) } else { item.to_string() };
+        string.push_str(&item);
+    }
+    string
 }
 
 defvar!(PRINT_LENGTH);
 defvar!(PRINT_LEVEL);
 defvar_bool!(PRINT_ESCAPE_NEWLINES, false);
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::assert_lisp;
+
+    #[test]
+    fn test_error_message_string() {
+        assert_lisp("(error-message-string '(error \"Foo\"))", "\"Foo\"");
+        assert_lisp("(error-message-string '(error \"Foo\" bar 1))", "\"Foo: bar, 1\"");
+        assert_lisp(
+            "(error-message-string '(wrong-type-argument listp 1))",
+            "\"Wrong type argument: listp, 1\"",
+        );
+        assert_lisp(
+            "(error-message-string '(args-out-of-range \"ab\" 5))",
+            "\"Args out of range: \\\"ab\\\", 5\"",
+        );
+        assert_lisp("(error-message-string '(user-error \"Oops\"))", "\"Oops\"");
+        assert_lisp(
+            "(error-message-string '(file-missing \"Opening input file\" \"No such file or directory\" \"/foo\"))",
+            "\"Opening input file: No such file or directory, /foo\"",
+        );
+        assert_lisp("(error-message-string '(end-of-file))", "\"End of file during parsing\"");
+        assert_lisp("(error-message-string '(test-unknown-error 1))", "\"peculiar error: 1\"");
+        assert_lisp("(error-message-string nil)", "\"peculiar error\"");
+    }
+}
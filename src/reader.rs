@@ -1,8 +1,9 @@
 //! Lisp reader that reads an object from a string.
 use crate::core::{
+    cons::Cons,
     env::{intern, sym},
     gc::Context,
-    object::{Object, Symbol},
+    object::{IntoObject, Object, ObjectType, Symbol, NIL},
 };
 use crate::fns;
 use rune_core::macros::list;
@@ -26,6 +27,7 @@ pub(crate) enum Error {
     UnknownMacroCharacter(char, usize),
     ParseInt(u8, usize),
     MalformedUnicdoe(usize),
+    InvalidByteCode(usize),
     EmptyStream,
 }
 
@@ -39,6 +41,7 @@ impl Display for Error {
             Error::ExtraCloseBracket(i) => write!(f, "Extra Closing brace: at {i}"),
             Error::UnexpectedChar(chr, i) => write!(f, "Unexpected character {chr}: at {i}"),
             Error::MalformedUnicdoe(i) => write!(f, "Malformed unicode: at {i}"),
+            Error::InvalidByteCode(i) => write!(f, "Invalid byte-code object: at {i}"),
             Error::EmptyStream => write!(f, "Empty Stream"),
             Error::ExtraItemInCdr(i) => write!(f, "Extra item in cdr: at {i}"),
             Error::MissingQuotedItem(i) => write!(f, "Missing element after quote: at {i}"),
@@ -67,11 +70,30 @@ impl Error {
             | Error::ExtraCloseBracket(i)
             | Error::MissingQuotedItem(i)
             | Error::UnknownMacroCharacter(_, i)
+            | Error::InvalidByteCode(i)
             | Error::ParseInt(_, i) => Some(i),
             Error::EmptyStream => None,
         }
     }
 
+    /// The Lisp condition for this error. Input that ends in the middle of an
+    /// object signals `end-of-file`, and anything else is
+    /// `invalid-read-syntax`.
+    pub(crate) fn condition<'ob>(&self, cx: &'ob Context) -> &'ob Cons {
+        let data = match self {
+            Error::MissingCloseParen(_)
+            | Error::MissingCloseBracket(_)
+            | Error::MissingStringDel(_)
+            | Error::MissingQuotedItem(_)
+            | Error::EmptyStream => return Cons::new1(sym::END_OF_FILE, cx),
+            Error::ExtraCloseParen(_) => ")".to_owned(),
+            Error::ExtraCloseBracket(_) => "]".to_owned(),
+            Error::UnexpectedChar(chr, _) => chr.to_string(),
+            x => x.to_string(),
+        };
+        Cons::new(sym::INVALID_READ_SYNTAX, Cons::new1(data, cx), cx)
+    }
+
     pub(crate) fn update_pos(&mut self, offset: usize) {
         if let Some(pos) = self.mut_pos() {
             *pos += offset;
@@ -164,8 +186,17 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    /// Skip whitespace and comments until the next valid read character.
+    /// Skip whitespace, comments and skipped byte blocks until the next valid
+    /// read character.
     fn skip_till_char(&mut self) {
+        self.skip_whitespace();
+        while self.skip_bytes() {
+            self.skip_whitespace();
+        }
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_whitespace(&mut self) {
         let mut in_comment = false;
         let valid_char = |chr: char| {
             if in_comment {
@@ -185,6 +216,38 @@ impl<'a> Tokenizer<'a> {
         self.skip_till(valid_char);
     }
 
+    /// Skip a `#@N` block, which is used in `.elc` files to hide the `N` bytes
+    /// following the number from the reader. The character that terminates
+    /// the number counts as the first of them. `#@00` skips to the end of the
+    /// input. Return false if the next characters do not start such a block.
+    fn skip_bytes(&mut self) -> bool {
+        let mut iter = self.iter.clone();
+        let (Some((_, '#')), Some((_, '@'))) = (iter.next(), iter.next()) else {
+            return false;
+        };
+        let mut count: usize = 0;
+        let mut digits = 0;
+        let mut end = self.slice.len();
+        for (idx, chr) in iter.by_ref() {
+            match chr.to_digit(10) {
+                Some(digit) => {
+                    count = count.saturating_mul(10).saturating_add(digit as usize);
+                    digits += 1;
+                }
+                None => {
+                    end = if count == 0 { idx } else { idx.saturating_add(count) };
+                    break;
+                }
+            }
+        }
+        if digits == 2 && count == 0 {
+            end = self.slice.len();
+        }
+        self.iter = iter;
+        while self.iter.next_if(|(idx, _)| *idx < end).is_some() {}
+        true
+    }
+
     fn get_string(&mut self, open_delim_pos: usize) -> Result<Token<'a>> {
         let mut skip = false;
         let idx_chr = self.iter.find(|(_, chr)| !escaped(&mut skip, *chr) && *chr == '"');
@@ -318,31 +381,90 @@ fn parse_symbol<'a>(slice: &str, cx: &'a Context) -> Object<'a> {
     }
 }
 
+/// Raw bytes are represented the same way as in Emacs, as the characters
+/// after the last unicode code point.
+const RAW_BYTE_BASE: u32 = 0x3FFF00;
+
+/// Read the digits of a numeric escape in `radix`, reading at most `max`
+/// digits. `code` is the value of any digits already read.
+fn escape_code(chars: &mut Peekable<str::Chars>, radix: u32, max: usize, mut code: u32) -> u32 {
+    for _ in 0..max {
+        match chars.peek().and_then(|c| c.to_digit(radix)) {
+            Some(digit) => {
+                code = code.saturating_mul(radix).saturating_add(digit);
+                chars.next();
+            }
+            None => break,
+        }
+    }
+    code
+}
+
+/// Apply the control modifier to `c`, as in `\C-a` or `\^a`.
+fn control_char(c: char) -> char {
+    match c {
+        '?' => '\x7F',
+        c if c.is_ascii_alphabetic() || ('@'..='_').contains(&c) => {
+            char::from(c.to_ascii_uppercase() as u8 & 0x1F)
+        }
+        c => c,
+    }
+}
+
 /// process escape characters in the string slice and return the resulting
-/// string.
+/// string. A string that contains raw bytes from octal or hex escapes and no
+/// other non-ASCII characters is read as a unibyte string.
 fn unescape_string<'a>(string: &str, cx: &'a Context) -> Object<'a> {
-    let mut escaped = false;
-    let unescape = |c: char| {
-        if escaped {
-            escaped = false;
-            // TODO: Handle unicode, hex, and octal escapes
-            match c {
-                'n' => Some('\n'),
-                't' => Some('\t'),
-                'r' => Some('\r'),
-                '\n' | ' ' => None,
-                _ => Some(c),
-            }
-        } else if c == '\\' {
-            escaped = true;
-            None
-        } else {
-            Some(c)
+    let mut chars = string.chars().peekable();
+    let mut codes: Vec<u32> = Vec::with_capacity(string.len());
+    let raw_byte =
+        |code: u32| if (0x80..0x100).contains(&code) { RAW_BYTE_BASE + code } else { code };
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            codes.push(c.into());
+            continue;
         }
-    };
+        let Some(c) = chars.next() else { break };
+        let code = match c {
+            'n' => '\n'.into(),
+            't' => '\t'.into(),
+            'r' => '\r'.into(),
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0C,
+            'v' => 0x0B,
+            'e' => 0x1B,
+            'd' => 0x7F,
+            's' => ' '.into(),
+            '\n' | ' ' => continue,
+            '0'..='7' => raw_byte(escape_code(&mut chars, 8, 2, c as u32 - '0' as u32)),
+            'x' => raw_byte(escape_code(&mut chars, 16, usize::MAX, 0)),
+            'u' => escape_code(&mut chars, 16, 4, 0),
+            'U' => escape_code(&mut chars, 16, 8, 0),
+            '^' => match chars.next() {
+                Some(c) => control_char(c).into(),
+                None => break,
+            },
+            'C' if chars.peek() == Some(&'-') => {
+                chars.next();
+                match chars.next() {
+                    Some(c) => control_char(c).into(),
+                    None => break,
+                }
+            }
+            c => c.into(),
+        };
+        codes.push(code);
+    }
+    let has_raw = codes.iter().any(|&c| c >= RAW_BYTE_BASE);
+    if has_raw && codes.iter().all(|c| !(0x80..RAW_BYTE_BASE).contains(c)) {
+        let bytes: Vec<u8> = codes.iter().map(|&c| c as u8).collect();
+        return cx.add(bytes);
+    }
     let mut new = cx.string_with_capacity(string.len());
-    for c in string.chars().filter_map(unescape) {
-        new.push(c);
+    for code in codes {
+        let code = if code >= RAW_BYTE_BASE { code - RAW_BYTE_BASE } else { code };
+        new.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
     }
     cx.add(new)
 }
@@ -370,6 +492,8 @@ struct Reader<'a, 'ob> {
     tokens: Tokenizer<'a>,
     /// New objects are allocated in the context.
     cx: &'ob Context<'ob>,
+    /// The value of `#$`, the name of the file being loaded.
+    load_file_name: Object<'ob>,
 }
 
 impl<'a, 'ob> Reader<'a, 'ob> {
@@ -465,6 +589,8 @@ impl<'a, 'ob> Reader<'a, 'ob> {
                 }
                 None => Err(Error::MissingQuotedItem(pos)),
             },
+            Some('[') => self.read_byte_code(pos),
+            Some('$') => Ok(self.load_file_name),
             Some('b') => self.read_radix(pos, 2),
             Some('o') => self.read_radix(pos, 8),
             Some('x') => self.read_radix(pos, 16),
@@ -494,6 +620,37 @@ impl<'a, 'ob> Reader<'a, 'ob> {
         }
     }
 
+    /// Read a byte-code literal of the form
+    /// ```lisp
+    /// #[ARGLIST BYTE-CODE CONSTANTS DEPTH DOCSTRING INTERACTIVE-SPEC]
+    /// ```
+    fn read_byte_code(&mut self, pos: usize) -> Result<Object<'ob>> {
+        let vec = self.read_vec(pos)?;
+        let ObjectType::Vec(vec) = vec.untag() else { unreachable!("read_vec returns a vector") };
+        let invalid = Error::InvalidByteCode(pos);
+        let (arglist, codes, consts, depth) = match &**vec {
+            [arglist, codes, consts, depth, ..] => {
+                (arglist.get(), codes.get(), consts.get(), depth.get())
+            }
+            _ => return Err(invalid),
+        };
+        let codes: Vec<u8> = match codes.untag() {
+            ObjectType::ByteString(codes) => codes.to_vec(),
+            ObjectType::String(codes) => codes
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| invalid))
+                .collect::<Result<_>>()?,
+            _ => return Err(invalid),
+        };
+        let ObjectType::Vec(consts) = consts.untag() else { return Err(invalid) };
+        let Ok(depth) = usize::try_from(depth) else { return Err(invalid) };
+        let elements: Vec<Object> = vec.iter().skip(4).map(|x| x.get()).collect();
+        let codes = codes.into_obj(self.cx).untag();
+        crate::alloc::make_byte_code(arglist, codes, consts, depth, &elements, self.cx)
+            .map(|x| x.into())
+            .map_err(|_| invalid)
+    }
+
     fn read_sexp(&mut self, token: Token<'a>) -> Result<Object<'ob>> {
         match token {
             Token::OpenParen(i) => self.read_list(i),
@@ -515,7 +672,16 @@ impl<'a, 'ob> Reader<'a, 'ob> {
 /// read a lisp object from `slice`. Return the object and index of next
 /// remaining character in the slice.
 pub(crate) fn read<'ob>(slice: &str, cx: &'ob Context) -> Result<(Object<'ob>, usize)> {
-    let mut reader = Reader { tokens: Tokenizer::new(slice), cx };
+    read_in_file(slice, NIL, cx)
+}
+
+/// Like [`read`], but `#$` reads as `load_file_name`.
+pub(crate) fn read_in_file<'ob>(
+    slice: &str,
+    load_file_name: Object<'ob>,
+    cx: &'ob Context,
+) -> Result<(Object<'ob>, usize)> {
+    let mut reader = Reader { tokens: Tokenizer::new(slice), cx, load_file_name };
     match reader.tokens.next() {
         Some(Ok(t)) => reader.read_sexp(t).map(|x| (x, reader.tokens.cur_pos())),
         Some(Err(e)) => Err(e),
@@ -633,6 +799,53 @@ mod test {
 baz""#,
             cx
         );
+        check_reader!("A\x07\x1b\x7f", r#""\101\a\e\^?""#, cx);
+        check_reader!("\u{e9}x", r#""\u00e9\x78""#, cx);
+        check_reader!(vec![0xC0_u8, b'!', 0x87], r#""\300!\x87""#, cx);
+        // raw bytes in a multibyte string
+        check_reader!("\u{e9}\u{80}", r#""\u00e9\200""#, cx);
+    }
+
+    #[test]
+    fn test_read_byte_code() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        sym::init_symbols();
+        // (lambda (x) x)
+        let (obj, _) = read(r#"#[257 "\207" [] 2 "doc"]"#, cx).unwrap();
+        let ObjectType::ByteFn(func) = obj.untag() else { panic!("expected byte-code: {obj}") };
+        assert_eq!(func.codes(), [0x87]);
+        assert_eq!(func.depth, 2);
+        assert_eq!(func.args.required, 1);
+        assert_error(r#"#[257 "\207" []]"#, Error::InvalidByteCode(0), cx);
+        // dynamic binding code has a lambda list
+        let (obj, _) = read(r#"#[(x &optional y) "\10\207" [x] 1]"#, cx).unwrap();
+        let ObjectType::ByteFn(func) = obj.untag() else { panic!("expected byte-code: {obj}") };
+        assert_eq!(func.dynamic_args().unwrap().to_string(), "(x &optional y)");
+        assert_eq!((func.args.required, func.args.optional), (1, 1));
+        assert_error(r#"#[(1) "\207" [] 2]"#, Error::InvalidByteCode(0), cx);
+    }
+
+    #[test]
+    fn test_skip_bytes() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        check_reader!(2, "#@4 foo2", cx);
+        check_reader!(list![1, 2; cx], "(1 #@3 \x1f\n2)", cx);
+        check_reader!(1, "#@0 1", cx);
+        assert_error("#@00 1 2", Error::EmptyStream, cx);
+        let (obj, pos) = read("#@2 a 3", cx).unwrap();
+        assert_eq!((obj, pos), (cx.add(3), 7));
+    }
+
+    #[test]
+    fn test_load_file_name() {
+        let roots = &RootSet::default();
+        let cx = &Context::new(roots);
+        let file = cx.add("foo.elc");
+        let expect: Object = Cons::new(file, 3, cx).into();
+        assert_eq!(read_in_file("(#$ . 3)", file, cx).unwrap().0, expect);
+        check_reader!(false, "#$", cx);
     }
 
     #[test]
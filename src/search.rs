@@ -93,13 +93,13 @@ fn lisp_regex_to_rust(regexp: &str) -> String {
     let mut chars = regexp.char_indices();
     while let Some((idx, ch)) = chars.next() {
         match ch {
-            // Invert the escaping of parens. i.e. \( => ( and ( => \(
-            '(' | ')' | '{' | '}' => {
+            // Invert the escaping of parens and alternation. i.e. \( => ( and ( => \(
+            '(' | ')' | '{' | '}' | '|' => {
                 norm_regex.push('\\');
                 norm_regex.push(ch);
             }
             '\\' => match chars.next() {
-                Some((_, c @ '('..=')' | c @ '{' | c @ '}')) => norm_regex.push(c),
+                Some((_, c @ '('..=')' | c @ '{' | c @ '}' | c @ '|')) => norm_regex.push(c),
                 Some((_, '`')) => norm_regex += "\\A",
                 Some((_, '\'')) => norm_regex += "\\z",
                 Some((_, c)) => {
@@ -148,13 +148,13 @@ fn set_match_data<'ob>(list: List, _reseat: OptionalFlag, env: &mut Rt<Env>) ->
 #[defun]
 fn match_beginning<'ob>(subexp: usize, env: &Rt<Env>, cx: &'ob Context) -> Result<Object<'ob>> {
     let list = env.match_data.bind(cx).as_list()?;
-    Ok(list.fallible().nth(subexp)?.unwrap_or_default())
+    Ok(list.fallible().nth(subexp * 2)?.unwrap_or_default())
 }
 
 #[defun]
 fn match_end<'ob>(subexp: usize, env: &Rt<Env>, cx: &'ob Context) -> Result<Object<'ob>> {
     let list = env.match_data.bind(cx).as_list()?;
-    Ok(list.fallible().nth(subexp + 1)?.unwrap_or_default())
+    Ok(list.fallible().nth(subexp * 2 + 1)?.unwrap_or_default())
 }
 
 #[defun]
@@ -185,6 +185,7 @@ mod test {
         assert_eq!(lisp_regex_to_rust("\\foo"), "\\foo");
         assert_eq!(lisp_regex_to_rust("\\(foo\\)"), "(foo)");
         assert_eq!(lisp_regex_to_rust("(foo)"), "\\(foo\\)");
+        assert_eq!(lisp_regex_to_rust("\\(a\\|b\\)|"), "(a|b)\\|");
         assert_eq!(lisp_regex_to_rust("\\`"), "\\A");
         assert_eq!(lisp_regex_to_rust("\\'"), "\\z");
         assert_eq!(lisp_regex_to_rust("[[:word:]]"), "[a-zA-Z]");
@@ -202,4 +203,16 @@ mod test {
         let result = replace_match(newtext, None, None, Some(string), None, env, cx).unwrap();
         assert_eq!(result, "foo quux baz");
     }
+
+    #[test]
+    fn test_match_subexp() {
+        let roots = &RootSet::default();
+        let cx = &mut Context::new(roots);
+        root!(env, new(Env), cx);
+        string_match("^[0-9]+\\.\\([0-9]+\\)", "27.1", None, None, env, cx).unwrap();
+        assert_eq!(match_beginning(0, env, cx).unwrap(), 0);
+        assert_eq!(match_end(0, env, cx).unwrap(), 4);
+        assert_eq!(match_beginning(1, env, cx).unwrap(), 3);
+        assert_eq!(match_end(1, env, cx).unwrap(), 4);
+    }
 }
@@ -18,14 +18,17 @@ fn go_internal(obj: Object) -> JoinHandle<()> {
     let sexp = obj.clone_in(&block);
     let raw = sexp.into_raw();
     crate::debug::enable_debug();
-    thread::spawn(move || {
+    // the default stack of a spawned thread is much smaller than the main one
+    let thread = thread::Builder::new().stack_size(crate::eval::LISP_THREAD_STACK_SIZE);
+    let handle = thread.spawn(move || {
         let roots = &RootSet::default();
         let cx = &mut Context::from_block(block, roots);
         root!(env, new(Env), cx);
         let obj = unsafe { Object::from_raw(raw) };
         root!(obj, cx);
-        _ = crate::interpreter::eval(obj, None, env, cx);
-    })
+        _ = crate::interpreter::eval_toplevel(obj, env, cx);
+    });
+    handle.expect("failed to spawn thread")
 }
 
 #[cfg(test)]
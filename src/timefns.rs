@@ -13,7 +13,7 @@ defvar!(CURRENT_TIME_LIST, true);
 #[defun]
 fn current_time<'ob>(cx: &'ob Context, env: &Rt<Env>) -> Object<'ob> {
     assert!(
-        env.vars.get(sym::CURRENT_TIME_LIST).unwrap() == &sym::TRUE,
+        env.var(sym::CURRENT_TIME_LIST).unwrap() == &sym::TRUE,
         "current-time-list is nil"
     );
     let duration = SystemTime::now()